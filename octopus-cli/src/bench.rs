@@ -0,0 +1,404 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use octopus_client::Client;
+use octopus_common::core::types::{Order, Price, Quantity, Side};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+use tokio::{sync::Semaphore, task::JoinSet, time};
+
+use crate::{errors::CliError, output::Render};
+
+/// What a benchmark run sends
+#[derive(Debug, Clone, Args)]
+pub struct Options {
+    /// Number of accounts placing orders
+    #[arg(long, default_value_t = 10)]
+    pub accounts: usize,
+    /// Names of the accounts, followed by their number
+    #[arg(long, default_value = "bench-")]
+    pub prefix: String,
+    /// Amount deposited into each account before the run
+    #[arg(long, default_value_t = 1_000_000_000)]
+    pub funding: u64,
+    /// Number of requests to send
+    #[arg(long, default_value_t = 1000)]
+    pub requests: usize,
+    /// Target requests per second, 0 sends as fast as the concurrency allows
+    #[arg(long, default_value_t = 0)]
+    pub rate: u64,
+    /// Maximum number of requests in flight
+    #[arg(long, default_value_t = 8)]
+    pub concurrency: usize,
+    /// Relative weights of buy orders, sell orders and cancels
+    #[arg(long, default_value = "buy:45,sell:45,cancel:10")]
+    pub mix: Mix,
+    /// Orders are priced around this
+    #[arg(long, default_value_t = 100)]
+    pub mid: u64,
+    /// Orders are priced at random up to this far from the mid price
+    #[arg(long, default_value_t = 5)]
+    pub spread: u64,
+    /// Orders are for a random amount between 1 and this
+    #[arg(long, default_value_t = 10)]
+    pub max_amount: u64,
+    /// Seed of the random requests, runs with the same seed and options send the same requests
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+/// Relative weights of the kinds of requests, e.g. `buy:45,sell:45,cancel:10`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mix {
+    buy: u32,
+    sell: u32,
+    cancel: u32,
+}
+
+impl FromStr for Mix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mix = Mix {
+            buy: 0,
+            sell: 0,
+            cancel: 0,
+        };
+        for part in s.split(',') {
+            let (kind, weight) = part
+                .split_once(':')
+                .ok_or_else(|| format!("expected 'kind:weight', got '{}'", part))?;
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight '{}'", weight))?;
+            match kind.trim() {
+                "buy" => mix.buy = weight,
+                "sell" => mix.sell = weight,
+                "cancel" => mix.cancel = weight,
+                other => {
+                    return Err(format!(
+                        "unknown kind '{}', expected buy, sell or cancel",
+                        other
+                    ))
+                }
+            }
+        }
+        if mix.buy + mix.sell == 0 {
+            return Err("the mix needs buy or sell orders".to_string());
+        }
+        Ok(mix)
+    }
+}
+
+/// A request of the benchmark, decided before the run so the seed alone determines it
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    Order(Order),
+    /// Cancels one of the orders resting at the time, `pick` selects which
+    Cancel {
+        pick: u64,
+    },
+}
+
+/// What happened to a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    Ok(Duration),
+    /// Failed with the error code, see [`CliError::to_response`]
+    Failed(Duration, String),
+    /// A cancel while no order was resting
+    Skipped,
+}
+
+/// Throughput and latency of a run
+#[derive(Debug, Serialize)]
+pub struct Report {
+    seed: u64,
+    requests: usize,
+    succeeded: usize,
+    failed: usize,
+    skipped: usize,
+    elapsed_secs: f64,
+    /// Responses per second
+    throughput: f64,
+    latency_ms: Latency,
+    /// Failures by error code
+    rejections: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct Latency {
+    p50: f64,
+    p99: f64,
+    p999: f64,
+    max: f64,
+}
+
+impl Report {
+    fn new(seed: u64, elapsed: Duration, outcomes: &[Outcome]) -> Self {
+        let mut latencies = vec![];
+        let mut rejections = BTreeMap::new();
+        let mut skipped = 0;
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Ok(latency) => latencies.push(*latency),
+                Outcome::Failed(latency, code) => {
+                    latencies.push(*latency);
+                    *rejections.entry(code.clone()).or_insert(0) += 1;
+                }
+                Outcome::Skipped => skipped += 1,
+            }
+        }
+        latencies.sort_unstable();
+        let failed = rejections.values().sum();
+        let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+
+        Report {
+            seed,
+            requests: outcomes.len(),
+            succeeded: latencies.len() - failed,
+            failed,
+            skipped,
+            elapsed_secs: elapsed.as_secs_f64(),
+            throughput: latencies.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            latency_ms: Latency {
+                p50: ms(percentile(&latencies, 0.5)),
+                p99: ms(percentile(&latencies, 0.99)),
+                p999: ms(percentile(&latencies, 0.999)),
+                max: ms(latencies.last().copied().unwrap_or_default()),
+            },
+            rejections,
+        }
+    }
+}
+
+impl Render for Report {
+    fn headers(&self) -> &'static [&'static str] {
+        &["metric", "value"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![
+            vec!["seed".to_string(), self.seed.to_string()],
+            vec!["requests".to_string(), self.requests.to_string()],
+            vec!["succeeded".to_string(), self.succeeded.to_string()],
+            vec!["failed".to_string(), self.failed.to_string()],
+            vec!["skipped".to_string(), self.skipped.to_string()],
+            vec![
+                "elapsed_secs".to_string(),
+                format!("{:.3}", self.elapsed_secs),
+            ],
+            vec!["throughput".to_string(), format!("{:.1}", self.throughput)],
+            vec!["p50_ms".to_string(), format!("{:.3}", self.latency_ms.p50)],
+            vec!["p99_ms".to_string(), format!("{:.3}", self.latency_ms.p99)],
+            vec![
+                "p999_ms".to_string(),
+                format!("{:.3}", self.latency_ms.p999),
+            ],
+            vec!["max_ms".to_string(), format!("{:.3}", self.latency_ms.max)],
+        ];
+        rows.extend(
+            self.rejections
+                .iter()
+                .map(|(code, count)| vec![format!("rejected: {}", code), count.to_string()]),
+        );
+        rows
+    }
+}
+
+/// Funds the accounts, then sends the requests and measures how long each takes to be answered
+pub async fn run(cli: &Client, options: &Options) -> Result<Report, CliError> {
+    let seed = options.seed.unwrap_or_else(rand::random);
+    for i in 0..options.accounts {
+        cli.deposit(&account(options, i), options.funding.into())
+            .await?;
+    }
+    let requests = plan(options, seed);
+
+    let resting = Arc::new(Mutex::new(vec![]));
+    let semaphore = Arc::new(Semaphore::new(options.concurrency.max(1)));
+    let mut ticker = (options.rate > 0)
+        .then(|| time::interval(Duration::from_secs_f64(1.0 / options.rate as f64)));
+    let mut tasks = JoinSet::new();
+
+    let start = Instant::now();
+    for request in requests {
+        if let Some(ticker) = &mut ticker {
+            ticker.tick().await;
+        }
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        let cli = cli.clone();
+        let resting = resting.clone();
+        tasks.spawn(async move {
+            let outcome = send(&cli, request, &resting).await;
+            drop(permit);
+            outcome
+        });
+    }
+    let mut outcomes = vec![];
+    while let Some(outcome) = tasks.join_next().await {
+        outcomes.push(outcome.expect("requests don't panic"));
+    }
+
+    Ok(Report::new(seed, start.elapsed(), &outcomes))
+}
+
+/// Sends a request, remembering the orders that rest on the book so they can be cancelled
+async fn send(cli: &Client, request: Request, resting: &Mutex<Vec<u64>>) -> Outcome {
+    let started = Instant::now();
+    let result = match request {
+        Request::Order(order) => cli.order(&order).await.map(|receipt| {
+            let filled = receipt
+                .matches
+                .iter()
+                .fold(Quantity::ZERO, |filled, m| filled + m.amount);
+            if filled < order.amount {
+                resting.lock().unwrap().push(receipt.ordinal);
+            }
+        }),
+        Request::Cancel { pick } => {
+            let ordinal = {
+                let mut resting = resting.lock().unwrap();
+                if resting.is_empty() {
+                    return Outcome::Skipped;
+                }
+                let i = (pick % resting.len() as u64) as usize;
+                resting.swap_remove(i)
+            };
+            cli.cancel(ordinal).await.map(|_| ())
+        }
+    };
+
+    match result {
+        Ok(()) => Outcome::Ok(started.elapsed()),
+        Err(e) => Outcome::Failed(started.elapsed(), CliError::from(e).to_response().code),
+    }
+}
+
+fn account(options: &Options, i: usize) -> String {
+    format!("{}{}", options.prefix, i)
+}
+
+/// The requests of a run, all drawn from `seed`
+fn plan(options: &Options, seed: u64) -> Vec<Request> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let Mix { buy, sell, cancel } = options.mix;
+    let low = options.mid.saturating_sub(options.spread).max(1);
+    let high = options.mid.saturating_add(options.spread).max(low);
+
+    (0..options.requests)
+        .map(|_| {
+            let kind = rng.gen_range(0..buy + sell + cancel);
+            if kind >= buy + sell {
+                return Request::Cancel { pick: rng.gen() };
+            }
+            Request::Order(Order {
+                signer: account(options, rng.gen_range(0..options.accounts.max(1))),
+                side: if kind < buy { Side::Buy } else { Side::Sell },
+                amount: Quantity::from(rng.gen_range(1..=options.max_amount.max(1))),
+                price: Price::from(rng.gen_range(low..=high)),
+            })
+        })
+        .collect()
+}
+
+/// The nearest-rank percentile `q` of the sorted `latencies`
+fn percentile(latencies: &[Duration], q: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (q * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Debug, Parser)]
+    struct TestArgs {
+        #[command(flatten)]
+        options: Options,
+    }
+
+    fn options(args: &[&str]) -> Options {
+        TestArgs::try_parse_from(std::iter::once("bench").chain(args.iter().copied()))
+            .unwrap()
+            .options
+    }
+
+    #[test]
+    fn test_Mix_from_str() {
+        assert_eq!(
+            "buy:1, sell:2,cancel:0".parse::<Mix>(),
+            Ok(Mix {
+                buy: 1,
+                sell: 2,
+                cancel: 0
+            })
+        );
+        assert!("cancel:1".parse::<Mix>().is_err());
+        assert!("buy:x".parse::<Mix>().is_err());
+        assert!("hold:1".parse::<Mix>().is_err());
+    }
+
+    #[test]
+    fn test_plan_is_reproducible_from_the_seed() {
+        let options = options(&["--accounts", "3", "--requests", "200", "--spread", "200"]);
+
+        let requests = plan(&options, 7);
+        assert_eq!(requests, plan(&options, 7));
+        assert_ne!(requests, plan(&options, 8));
+
+        for request in &requests {
+            if let Request::Order(order) = request {
+                assert!(["bench-0", "bench-1", "bench-2"].contains(&order.signer.as_str()));
+                assert!((Price::from(1)..=Price::from(300)).contains(&order.price));
+                assert!((Quantity::from(1)..=Quantity::from(10)).contains(&order.amount));
+            }
+        }
+        assert!(requests.iter().any(|r| matches!(r, Request::Cancel { .. })));
+    }
+
+    #[test]
+    fn test_Report_counts_outcomes_and_percentiles() {
+        let mut outcomes: Vec<_> = (1..=1000)
+            .map(|ms| Outcome::Ok(Duration::from_millis(ms)))
+            .collect();
+        outcomes.push(Outcome::Failed(
+            Duration::from_millis(2000),
+            "order_not_found".to_string(),
+        ));
+        outcomes.push(Outcome::Skipped);
+
+        let report = Report::new(1, Duration::from_secs(2), &outcomes);
+        assert_eq!(
+            (
+                report.requests,
+                report.succeeded,
+                report.failed,
+                report.skipped
+            ),
+            (1002, 1000, 1, 1)
+        );
+        assert_eq!(report.rejections["order_not_found"], 1);
+        assert_eq!(report.latency_ms.p50, 501.0);
+        assert_eq!(report.latency_ms.p999, 1000.0);
+        assert_eq!(report.latency_ms.max, 2000.0);
+        assert_eq!(report.throughput, 500.5);
+    }
+}
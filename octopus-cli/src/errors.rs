@@ -1,5 +1,13 @@
+use octopus_client::Error as ClientError;
+use octopus_common::core::types::ErrorResponse;
 use thiserror::Error;
-use url::ParseError;
+
+/// Exit code of a command that the server rejected
+pub const EXIT_REJECTED: i32 = 1;
+/// Exit code of a command that was invalid, as used by clap
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code of a command that couldn't be completed because the server was unreachable or misbehaved, or the terminal failed
+pub const EXIT_UNAVAILABLE: i32 = 3;
 
 #[derive(Error, Debug)]
 pub enum CliError {
@@ -9,12 +17,44 @@ pub enum CliError {
     #[error("invalid order parameters")]
     InvalidOrderParameters(String),
 
-    #[error("reqwest error: {0}")]
-    ReqwestError(#[from] reqwest::Error),
-
     #[error(transparent)]
-    ParseError(#[from] ParseError),
+    Client(#[from] ClientError),
+
+    #[error("terminal error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl CliError {
+    /// The process exit code to report this error with
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::InvalidNumber()
+            | CliError::InvalidOrderParameters(_)
+            | CliError::Client(ClientError::InvalidBaseUrl(_)) => EXIT_USAGE,
+            CliError::Client(ClientError::Rejected { status, .. }) if status.is_client_error() => {
+                EXIT_REJECTED
+            }
+            CliError::Client(_) | CliError::IoError(_) => EXIT_UNAVAILABLE,
+        }
+    }
 
-    #[error("{0}")]
-    LogicError(String),
+    /// The error in the shape the server reports its errors, so it can be rendered like one
+    pub fn to_response(&self) -> ErrorResponse {
+        let code = match self {
+            CliError::Client(ClientError::Rejected { error, .. }) => return error.clone(),
+            CliError::InvalidNumber() => "invalid_number",
+            CliError::InvalidOrderParameters(_) => "invalid_order_parameters",
+            CliError::Client(ClientError::InvalidBaseUrl(_)) => "invalid_url",
+            CliError::Client(ClientError::Timeout) => "timeout",
+            CliError::Client(ClientError::Transport(_)) => "unavailable",
+            CliError::Client(ClientError::Server { .. } | ClientError::InvalidResponse(_)) => {
+                "unexpected_response"
+            }
+            CliError::IoError(_) => "io_error",
+        };
+        ErrorResponse {
+            code: code.to_string(),
+            message: self.to_string(),
+        }
+    }
 }
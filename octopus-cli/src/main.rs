@@ -1,213 +1,402 @@
+mod bench;
 mod errors;
-mod paths;
-
-use errors::CliError;
+mod output;
+mod repl;
+mod script;
+mod watch;
+
+use clap::{Parser, Subcommand};
+use errors::{CliError, EXIT_USAGE};
+use octopus_client::Client;
 use octopus_common::{
     core::types::{
-        AccountBalanceRequest, AccountUpdateRequest, Order, PartialOrder, Receipt, SendRequest,
-        Side,
+        AccountsQuery, Notional, Order, OrderbookQuery, Page, Price, Quantity, SendRequest, Side,
+        TxLogQuery,
     },
-    tx::Tx,
+    tx::TxKind,
 };
-use reqwest::Url;
-use serde::{Deserialize, Serialize};
-use std::{env, io, num::ParseIntError};
-
-enum Operation {
-    Deposit,
-    Withdraw,
+use output::{render, OutputFormat};
+use serde::{de, Deserialize, Deserializer};
+use std::{path::PathBuf, process::exit, time::Duration};
+
+/// Command line client of the octopus trading platform
+#[derive(Debug, Parser)]
+#[command(version, after_help = EXIT_CODES)]
+struct Args {
+    /// Base URL of the octopus server
+    #[arg(
+        long,
+        global = true,
+        env = "OCTOPUS_SERVER",
+        default_value = "http://127.0.0.1:8080"
+    )]
+    server: String,
+
+    /// Format of results on stdout and errors on stderr
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Json)]
+    output: OutputFormat,
+
+    /// Seconds to wait for each response of the server
+    #[arg(long, global = true, default_value_t = 10)]
+    timeout: u64,
+
+    #[command(subcommand)]
+    command: Command,
 }
 
-struct OctopusClient {
-    base_url: Url,
-    client: reqwest::Client,
+const EXIT_CODES: &str = "Exit codes:
+  0  the command succeeded
+  1  the server rejected the command, e.g. insufficient funds or an unknown account
+  2  the command line is invalid
+  3  the server couldn't be reached or failed";
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Op(Operation),
+    /// Execute the operations in a script, one after the other
+    Run {
+        /// A YAML (.yaml, .yml) or JSON (.json) list of operations, or one operation per line like on the command line
+        file: PathBuf,
+        /// Keep going after an operation fails
+        #[arg(long)]
+        continue_on_error: bool,
+        /// Only validate the script, without contacting the server
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Watch the order book live, and place and cancel orders of an account with keystrokes
+    Watch {
+        /// The account whose orders are highlighted and placed
+        #[arg(long)]
+        account: String,
+        /// Milliseconds between refreshes
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// Measure the throughput and latency of the server with random orders and cancels
+    Bench(bench::Options),
+    /// Start the interactive mode
+    Repl,
 }
 
-impl OctopusClient {
-    fn new(base_url: &str) -> Result<Self, CliError> {
-        let base_url = reqwest::Url::parse(base_url)?;
+/// A single request to the server, given on the command line or in a script
+#[derive(Debug, Subcommand, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Operation {
+    /// Deposit currency into an account, creating it if necessary
+    Deposit {
+        #[arg(long)]
+        account: String,
+        #[arg(long)]
+        amount: Notional,
+    },
+    /// Withdraw currency from an account
+    Withdraw {
+        #[arg(long)]
+        account: String,
+        #[arg(long)]
+        amount: Notional,
+    },
+    /// Send currency from one account to another
+    Send {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: Notional,
+    },
+    /// Submit an order
+    Order {
+        #[arg(long)]
+        account: String,
+        #[arg(long, value_parser = parse_side)]
+        #[serde(deserialize_with = "deserialize_side")]
+        side: Side,
+        #[arg(long)]
+        amount: Quantity,
+        #[arg(long)]
+        price: Price,
+    },
+    /// Cancel a resting order
+    Cancel {
+        /// The ordinal from the order's receipt
+        #[arg(long)]
+        ordinal: u64,
+    },
+    /// Show the balance of an account
+    Balance {
+        #[arg(long)]
+        account: String,
+    },
+    /// List accounts and their balances
+    Accounts {
+        /// Only list accounts whose name starts with this prefix
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Only list accounts with at least this balance
+        #[arg(long)]
+        min_balance: Option<Notional>,
+    },
+    /// List the resting orders
+    Orderbook {
+        /// Only list orders on this side of the book
+        #[arg(long, value_parser = parse_side)]
+        #[serde(default, deserialize_with = "deserialize_optional_side")]
+        side: Option<Side>,
+        /// Only list orders of this account
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// List the transaction log
+    Txlog {
+        /// Only list transactions of this account
+        #[arg(long)]
+        account: Option<String>,
+        /// Only list transactions of this kind (deposit, withdraw or fee)
+        #[arg(long, value_parser = parse_kind)]
+        kind: Option<TxKind>,
+    },
+}
 
-        Ok(Self {
-            base_url,
-            client: reqwest::Client::new(),
-        })
+impl Operation {
+    /// The subcommand name of the operation
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Deposit { .. } => "deposit",
+            Operation::Withdraw { .. } => "withdraw",
+            Operation::Send { .. } => "send",
+            Operation::Order { .. } => "order",
+            Operation::Cancel { .. } => "cancel",
+            Operation::Balance { .. } => "balance",
+            Operation::Accounts { .. } => "accounts",
+            Operation::Orderbook { .. } => "orderbook",
+            Operation::Txlog { .. } => "txlog",
+        }
     }
+}
 
-    async fn deposit_withdraw(&self, op_type: Operation) -> Result<Tx, CliError> {
-        let account = read_from_stdin("Account:");
-
-        let amount = read_from_stdin("Amount:")
-            .parse::<u64>()
-            .map_err(|_| CliError::InvalidNumber())?;
-
-        let op = AccountUpdateRequest {
-            signer: account,
-            amount,
-        };
-
-        let path = match op_type {
-            Operation::Deposit => self.base_url.join(paths::DEPOSIT)?,
-            Operation::Withdraw => self.base_url.join(paths::WITHDRAW)?,
-        };
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let cli = match Client::builder(&args.server)
+        .timeout(Duration::from_secs(args.timeout))
+        .build()
+    {
+        Ok(cli) => cli,
+        Err(e) => {
+            let e = CliError::from(e);
+            eprintln!("{}", render(args.output, &e.to_response()));
+            exit(EXIT_USAGE);
+        }
+    };
 
-        send_request(&self.client, path, Some(op)).await
+    match args.command {
+        Command::Op(op) => match execute(&cli, op, args.output).await {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("{}", render(args.output, &e.to_response()));
+                exit(e.exit_code());
+            }
+        },
+        Command::Run {
+            file,
+            continue_on_error,
+            dry_run,
+        } => {
+            let options = script::Options {
+                continue_on_error,
+                dry_run,
+            };
+            exit(script::run(&cli, &file, options, args.output).await)
+        }
+        Command::Watch { account, interval } => {
+            if let Err(e) = watch::run(&cli, account, Duration::from_millis(interval)).await {
+                eprintln!("{}", render(args.output, &e.to_response()));
+                exit(e.exit_code());
+            }
+        }
+        Command::Bench(options) => match bench::run(&cli, &options).await {
+            Ok(report) => println!("{}", render(args.output, &report)),
+            Err(e) => {
+                eprintln!("{}", render(args.output, &e.to_response()));
+                exit(e.exit_code());
+            }
+        },
+        Command::Repl => repl::run(&cli, args.output).await,
     }
+}
 
-    async fn send(&self) -> Result<(Tx, Tx), CliError> {
-        let sender = read_from_stdin("Sender Account:");
-        let recipient = read_from_stdin("Recipient Account:");
-        let amount = read_from_stdin("Amount:")
-            .parse::<u64>()
-            .map_err(|_| CliError::InvalidNumber())?;
-
-        let op = SendRequest {
-            sender,
-            recipient,
+/// Sends `op` to the server and renders the response
+pub async fn execute(
+    cli: &Client,
+    op: Operation,
+    format: OutputFormat,
+) -> Result<String, CliError> {
+    let output = match op {
+        Operation::Deposit { account, amount } => {
+            render(format, &cli.deposit(&account, amount).await?)
+        }
+        Operation::Withdraw { account, amount } => {
+            render(format, &cli.withdraw(&account, amount).await?)
+        }
+        Operation::Send { from, to, amount } => render(
+            format,
+            &cli.send(&SendRequest {
+                sender: from,
+                recipient: to,
+                amount,
+            })
+            .await?,
+        ),
+        Operation::Order {
+            account,
+            side,
             amount,
-        };
-
-        send_request(&self.client, self.base_url.join(paths::SEND)?, Some(op)).await
-    }
-
-    async fn order(&self) -> Result<Receipt, CliError> {
-        let order = read_order_parameters().map_err(CliError::InvalidOrderParameters)?;
-
-        send_request(&self.client, self.base_url.join(paths::ORDER)?, Some(order)).await
-    }
-
-    async fn balance(&self) -> Result<u64, CliError> {
-        let account = read_from_stdin("Account:");
-
-        let op = AccountBalanceRequest { signer: account };
+            price,
+        } => render(
+            format,
+            &cli.order(&Order {
+                price,
+                amount,
+                side,
+                signer: account,
+            })
+            .await?,
+        ),
+        Operation::Cancel { ordinal } => render(format, &cli.cancel(ordinal).await?),
+        Operation::Balance { account } => render(format, &cli.balance(&account).await?),
+        Operation::Accounts {
+            prefix,
+            min_balance,
+        } => render(
+            format,
+            &whole(
+                cli.all_accounts(&AccountsQuery {
+                    prefix,
+                    min_balance,
+                    ..Default::default()
+                })
+                .await?,
+            ),
+        ),
+        Operation::Orderbook { side, account } => render(
+            format,
+            &whole(
+                cli.all_orders(&OrderbookQuery {
+                    side,
+                    signer: account,
+                    ..Default::default()
+                })
+                .await?,
+            ),
+        ),
+        Operation::Txlog { account, kind } => render(
+            format,
+            &whole(
+                cli.all_transactions(&TxLogQuery {
+                    account,
+                    kind,
+                    ..Default::default()
+                })
+                .await?,
+            ),
+        ),
+    };
+    Ok(output)
+}
 
-        send_request(&self.client, self.base_url.join(paths::BALANCE)?, Some(op)).await
-    }
+/// All items of a listing as a single page, to render them like one
+pub fn whole<T, C>(items: Vec<T>) -> Page<T, C> {
+    Page { items, next: None }
+}
 
-    async fn accounts(&self) -> Result<Vec<(String, u64)>, CliError> {
-        send_request::<(), Vec<(String, u64)>>(
-            &self.client,
-            self.base_url.join(paths::ACCOUNTS)?,
-            None,
-        )
-        .await
+fn parse_side(s: &str) -> Result<Side, String> {
+    match s.to_lowercase().as_str() {
+        "buy" => Ok(Side::Buy),
+        "sell" => Ok(Side::Sell),
+        _ => Err("expected 'buy' or 'sell'".to_string()),
     }
+}
 
-    async fn orderbook(&self) -> Result<Vec<PartialOrder>, CliError> {
-        send_request::<(), Vec<PartialOrder>>(
-            &self.client,
-            self.base_url.join(paths::ORDERBOOK)?,
-            None,
-        )
-        .await
-    }
+fn deserialize_side<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Side, D::Error> {
+    parse_side(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+}
 
-    async fn txlog(&self) -> Result<Vec<Tx>, CliError> {
-        send_request::<(), Vec<Tx>>(&self.client, self.base_url.join(paths::TXLOG)?, None).await
-    }
+fn deserialize_optional_side<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Side>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|side| parse_side(&side).map_err(de::Error::custom))
+        .transpose()
 }
 
-#[tokio::main]
-async fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("USAGE octopus-cli <SERVER_URL>");
+fn parse_kind(s: &str) -> Result<TxKind, String> {
+    match s.to_lowercase().as_str() {
+        "deposit" => Ok(TxKind::Deposit),
+        "withdraw" => Ok(TxKind::Withdraw),
+        "fee" => Ok(TxKind::Fee),
+        _ => Err("expected 'deposit', 'withdraw' or 'fee'".to_string()),
     }
+}
 
-    let cli = OctopusClient::new(args[1].as_str()).expect("failed to parse SERVER_URL");
-
-    println!("Hello, accounting world!");
-
-    loop {
-        let input = read_from_stdin(
-            "Choose operation [deposit(d), withdraw(w), send(s), balance(b), accounts(a), txlog(tx), order(o), orderbook(ob), quit(q)], confirm with return:",
-        );
-        match input.as_str() {
-            "deposit" | "d" => handle_command(cli.deposit_withdraw(Operation::Deposit).await),
-
-            "withdraw" | "w" => handle_command(cli.deposit_withdraw(Operation::Withdraw).await),
-
-            "send" | "s" => handle_command(cli.send().await),
-
-            "order" | "o" => handle_command(cli.order().await),
-
-            "balance" | "b" => handle_command(cli.balance().await),
-
-            "accounts" | "a" => handle_command(cli.accounts().await),
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
 
-            "orderbook" | "ob" => handle_command(cli.orderbook().await),
+    use super::*;
+    use clap::CommandFactory;
 
-            "txlog" | "tx" => handle_command(cli.txlog().await),
+    #[test]
+    fn test_Args_are_consistent() {
+        Args::command().debug_assert();
+    }
 
-            "quit" | "q" => {
-                println!("Quitting...");
-                break;
-            }
-            _ => {
-                eprintln!("Invalid option: '{}'", input);
+    #[test]
+    fn test_Args_parse_order() {
+        let args = Args::try_parse_from([
+            "octopus-cli",
+            "--server",
+            "http://localhost:9000",
+            "order",
+            "--account",
+            "ALICE",
+            "--side",
+            "Sell",
+            "--amount",
+            "3",
+            "--price",
+            "10",
+        ])
+        .unwrap();
+
+        assert_eq!(args.server, "http://localhost:9000");
+        match args.command {
+            Command::Op(Operation::Order {
+                account,
+                side,
+                amount,
+                price,
+            }) => {
+                assert_eq!(account, "ALICE");
+                assert_eq!(side, Side::Sell);
+                assert_eq!((amount, price), (Quantity::from(3), Price::from(10)));
             }
+            other => panic!("unexpected command {:?}", other),
         }
     }
-}
-
-fn read_order_parameters() -> Result<Order, String> {
-    let account = read_from_stdin("Account:");
-    let side = match read_from_stdin("Buy or Sell?:").to_lowercase().as_ref() {
-        "buy" => Ok(Side::Buy),
-        "sell" => Ok(Side::Sell),
-        _ => Err("Unsupported order side"),
-    }?;
-
-    let amount = read_from_stdin("Amount:")
-        .parse()
-        .map_err(|e: ParseIntError| e.to_string())?;
-    let price = read_from_stdin("Price:")
-        .parse()
-        .map_err(|e: ParseIntError| e.to_string())?;
-
-    Ok(Order {
-        price,
-        amount,
-        side,
-        signer: account,
-    })
-}
-
-fn read_from_stdin(label: &str) -> String {
-    let mut buffer = String::new();
-    println!("{}", label);
-    io::stdin()
-        .read_line(&mut buffer)
-        .expect("Couldn't read from stdin");
-    buffer.trim().to_owned()
-}
 
-async fn send_request<T, U>(
-    client: &reqwest::Client,
-    path: Url,
-    body: Option<T>,
-) -> Result<U, CliError>
-where
-    T: Serialize,
-    U: for<'de> Deserialize<'de>,
-{
-    let response = match body {
-        Some(body) => client.post(path).json(&body).send().await?,
-        None => client.get(path).send().await?,
-    };
-
-    if response.status() == 200 {
-        Ok(response.json().await?)
-    } else {
-        Err(CliError::LogicError(response.text().await?))
-    }
-}
+    #[test]
+    fn test_Args_reject_invalid_values_with_usage_exit_code() {
+        let err =
+            Args::try_parse_from(["octopus-cli", "deposit", "--account", "A", "--amount", "-1"])
+                .unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE);
 
-fn handle_command<T>(res: Result<T, CliError>)
-where
-    T: std::fmt::Debug,
-{
-    match res {
-        Ok(res) => println!("Operation succesfull:\n{:#?}", res),
-        Err(e) => eprintln!("Operation failed: '{:?}'", e),
+        let err = Args::try_parse_from(["octopus-cli", "txlog", "--kind", "transfer"]).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE);
     }
 }
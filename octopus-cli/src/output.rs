@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use octopus_common::{
+    core::{
+        decimal::Decimal,
+        types::{
+            AccountBalance, ErrorResponse, Page, PartialOrder, Price, Quantity, Receipt, Side,
+        },
+    },
+    tx::{LedgerEntry, Tx},
+};
+use serde::Serialize;
+
+/// How results and errors are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The server's JSON response
+    Json,
+    /// Aligned columns for humans, the order book as a price ladder
+    Table,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// A response that can be written in every [`OutputFormat`]. JSON is the serialized value, table and CSV are made from `headers` and `rows`.
+pub trait Render: Serialize {
+    fn headers(&self) -> &'static [&'static str];
+
+    fn rows(&self) -> Vec<Vec<String>>;
+
+    fn table(&self) -> String {
+        table(self.headers(), &self.rows())
+    }
+}
+
+/// Formats `value` as `format`, without a trailing newline
+pub fn render<T: Render>(format: OutputFormat, value: &T) -> String {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_string_pretty(value).expect("responses are serializable")
+        }
+        OutputFormat::Table => value.table(),
+        OutputFormat::Csv => csv(value.headers(), &value.rows()),
+    }
+}
+
+impl Render for ErrorResponse {
+    fn headers(&self) -> &'static [&'static str] {
+        &["code", "message"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.code.clone(), self.message.clone()]]
+    }
+
+    fn table(&self) -> String {
+        format!("error: {} ({})", self.message, self.code)
+    }
+}
+
+impl Render for Tx {
+    fn headers(&self) -> &'static [&'static str] {
+        &["kind", "account", "amount"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![tx_row(self)]
+    }
+}
+
+impl Render for (Tx, Tx) {
+    fn headers(&self) -> &'static [&'static str] {
+        &["kind", "account", "amount"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![tx_row(&self.0), tx_row(&self.1)]
+    }
+}
+
+impl Render for Receipt {
+    fn headers(&self) -> &'static [&'static str] {
+        &[
+            "ordinal", "matched", "signer", "side", "price", "amount", "fee",
+        ]
+    }
+
+    /// One row per match with the fee the order paid for it, or a single row with the ordinal if nothing matched
+    fn rows(&self) -> Vec<Vec<String>> {
+        if self.matches.is_empty() {
+            return vec![vec![self.ordinal.to_string()]];
+        }
+        self.matches
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                vec![
+                    self.ordinal.to_string(),
+                    m.ordinal.to_string(),
+                    m.signer.clone(),
+                    side(&m.side).to_string(),
+                    m.price.to_string(),
+                    m.amount.to_string(),
+                    self.fees
+                        .get(i)
+                        .map_or(String::new(), |fee| fee.taker.to_string()),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Render for AccountBalance {
+    fn headers(&self) -> &'static [&'static str] {
+        &["signer", "balance"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.signer.clone(), self.balance.to_string()]]
+    }
+}
+
+impl Render for Page<AccountBalance, String> {
+    fn headers(&self) -> &'static [&'static str] {
+        &["signer", "balance"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.items.iter().flat_map(|a| a.rows()).collect()
+    }
+}
+
+impl Render for Page<LedgerEntry, u64> {
+    fn headers(&self) -> &'static [&'static str] {
+        &["id", "timestamp", "kind", "account", "amount"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.items
+            .iter()
+            .map(|entry| {
+                let mut row = vec![entry.id.to_string(), entry.timestamp.to_string()];
+                row.extend(tx_row(&entry.tx));
+                row
+            })
+            .collect()
+    }
+}
+
+impl Render for PartialOrder {
+    fn headers(&self) -> &'static [&'static str] {
+        &["ordinal", "side", "signer", "price", "remaining"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.ordinal.to_string(),
+            side(&self.side).to_string(),
+            self.signer.clone(),
+            self.price.to_string(),
+            self.remaining.to_string(),
+        ]]
+    }
+}
+
+impl Render for Page<PartialOrder, u64> {
+    fn headers(&self) -> &'static [&'static str] {
+        &["ordinal", "side", "signer", "price", "remaining"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.items.iter().flat_map(|o| o.rows()).collect()
+    }
+
+    /// A price ladder: asks above bids, both from the highest price down, with the open quantity per level
+    fn table(&self) -> String {
+        let levels = ladder(&self.items);
+        let rows: Vec<_> = levels
+            .iter()
+            .map(|level| {
+                let size = level.size.to_string();
+                match level.side {
+                    Side::Buy => vec![size, level.price.to_string(), String::new()],
+                    Side::Sell => vec![String::new(), level.price.to_string(), size],
+                }
+            })
+            .collect();
+        let spread = levels.iter().filter(|l| l.side == Side::Sell).count();
+
+        let mut lines: Vec<_> = table(&["bid", "price", "ask"], &rows)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if spread > 0 && spread < rows.len() {
+            // header + asks, then a divider between the best ask and the best bid
+            let width = lines.iter().map(String::len).max().unwrap_or_default();
+            lines.insert(spread + 1, "-".repeat(width));
+        }
+        lines.join("\n")
+    }
+}
+
+/// The open quantity at one price of one side of the book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Level {
+    pub side: Side,
+    pub price: Price,
+    pub size: Quantity,
+}
+
+/// Aggregates orders into price levels: the asks, then the bids, both from the highest price down
+pub fn ladder(orders: &[PartialOrder]) -> Vec<Level> {
+    let mut bids = BTreeMap::new();
+    let mut asks = BTreeMap::new();
+    for order in orders {
+        let levels = match order.side {
+            Side::Buy => &mut bids,
+            Side::Sell => &mut asks,
+        };
+        let size = levels.entry(order.price).or_insert(Quantity::ZERO);
+        *size = Quantity(size.0.saturating_add(order.remaining.0));
+    }
+
+    let levels = |side: Side, levels: BTreeMap<Price, Quantity>| {
+        levels.into_iter().rev().map(move |(price, size)| Level {
+            side: side.clone(),
+            price,
+            size,
+        })
+    };
+    levels(Side::Sell, asks)
+        .chain(levels(Side::Buy, bids))
+        .collect()
+}
+
+fn tx_row(tx: &Tx) -> Vec<String> {
+    let (kind, account, amount) = match tx {
+        Tx::Deposit { account, amount } => ("deposit", account, amount),
+        Tx::Withdraw { account, amount } => ("withdraw", account, amount),
+        Tx::Fee {
+            account, amount, ..
+        } => ("fee", account, amount),
+    };
+    vec![kind.to_string(), account.clone(), amount.to_string()]
+}
+
+fn side(side: &Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+/// Aligns the columns, text to the left and numbers to the right
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<_> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let numeric: Vec<_> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .all(|cell| cell.is_empty() || cell.parse::<Decimal>().is_ok())
+        })
+        .collect();
+
+    let line = |cells: Vec<&str>| {
+        let cells: Vec<_> = cells
+            .iter()
+            .zip(&widths)
+            .zip(&numeric)
+            .map(|((cell, &width), &numeric)| {
+                if numeric {
+                    format!("{:>width$}", cell)
+                } else {
+                    format!("{:<width$}", cell)
+                }
+            })
+            .collect();
+        cells.join("  ").trim_end().to_string()
+    };
+
+    let headers: Vec<_> = headers.iter().map(|h| h.to_uppercase()).collect();
+    let mut lines = vec![line(headers.iter().map(String::as_str).collect())];
+    for row in rows {
+        let mut cells: Vec<&str> = row.iter().map(String::as_str).collect();
+        cells.resize(headers.len(), "");
+        lines.push(line(cells));
+    }
+    lines.join("\n")
+}
+
+/// Writes RFC 4180 CSV, quoting fields with separators, quotes or line breaks
+fn csv(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let field = |f: &str| {
+        if f.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", f.replace('"', "\"\""))
+        } else {
+            f.to_string()
+        }
+    };
+
+    let mut lines = vec![headers.join(",")];
+    for row in rows {
+        let mut fields: Vec<_> = row.iter().map(|f| field(f)).collect();
+        fields.resize(headers.len(), String::new());
+        lines.push(fields.join(","));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+
+    fn order(ordinal: u64, side: Side, price: u64, remaining: u64) -> PartialOrder {
+        PartialOrder {
+            price: Price::from(price),
+            amount: Quantity::from(remaining),
+            remaining: Quantity::from(remaining),
+            side,
+            signer: "ALICE".to_string(),
+            ordinal,
+        }
+    }
+
+    #[test]
+    fn test_render_orderbook_table_is_a_ladder() {
+        let book = Page {
+            items: vec![
+                order(1, Side::Sell, 11, 3),
+                order(2, Side::Buy, 9, 5),
+                order(3, Side::Sell, 12, 1),
+                order(4, Side::Sell, 11, 2),
+                order(5, Side::Buy, 10, 4),
+            ],
+            next: None,
+        };
+
+        assert_eq!(
+            render(OutputFormat::Table, &book),
+            [
+                "BID  PRICE  ASK",
+                "        12    1",
+                "        11    5",
+                "---------------",
+                "  4     10",
+                "  5      9",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_accounts_table_aligns_columns() {
+        let accounts = Page {
+            items: vec![
+                AccountBalance {
+                    signer: "ALICE".to_string(),
+                    balance: 100.into(),
+                },
+                AccountBalance {
+                    signer: "BO".to_string(),
+                    balance: 5.into(),
+                },
+            ],
+            next: None,
+        };
+
+        assert_eq!(
+            render(OutputFormat::Table, &accounts),
+            ["SIGNER  BALANCE", "ALICE       100", "BO            5"].join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_csv_quotes_fields() {
+        let accounts = Page {
+            items: vec![AccountBalance {
+                signer: "Smith, \"J\"".to_string(),
+                balance: 1.into(),
+            }],
+            next: None,
+        };
+
+        assert_eq!(
+            render(OutputFormat::Csv, &accounts),
+            "signer,balance\n\"Smith, \"\"J\"\"\",1"
+        );
+    }
+
+    #[test]
+    fn test_render_json_is_the_response_schema() {
+        let tx = Tx::Deposit {
+            account: "ALICE".to_string(),
+            amount: 1.into(),
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, &tx)).unwrap();
+        assert_eq!(json, serde_json::to_value(&tx).unwrap());
+    }
+}
@@ -1,8 +0,0 @@
-pub const DEPOSIT: &str = "/account/deposit";
-pub const WITHDRAW: &str = "/account/withdraw";
-pub const SEND: &str = "/account/send";
-pub const ORDER: &str = "/order";
-pub const BALANCE: &str = "/balance";
-pub const ORDERBOOK: &str = "/orderbook";
-pub const TXLOG: &str = "/txlog";
-pub const ACCOUNTS: &str = "/accounts";
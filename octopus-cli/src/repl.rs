@@ -0,0 +1,150 @@
+use crate::{
+    errors::CliError,
+    output::{render, OutputFormat, Render},
+    whole,
+};
+use octopus_client::Client;
+use octopus_common::{
+    core::types::{
+        AccountsQuery, Notional, Order, OrderbookQuery, Price, Quantity, Receipt, SendRequest,
+        Side, TxLogQuery,
+    },
+    tx::Tx,
+};
+use std::io;
+
+enum Operation {
+    Deposit,
+    Withdraw,
+}
+
+/// The interactive mode: prompts for an operation and its parameters until the user quits
+pub async fn run(cli: &Client, format: OutputFormat) {
+    println!("Hello, accounting world!");
+
+    loop {
+        let input = read_from_stdin(
+            "Choose operation [deposit(d), withdraw(w), send(s), balance(b), accounts(a), txlog(tx), order(o), orderbook(ob), quit(q)], confirm with return:",
+        );
+        match input.as_str() {
+            "deposit" | "d" => {
+                handle_command(format, deposit_withdraw(cli, Operation::Deposit).await)
+            }
+
+            "withdraw" | "w" => {
+                handle_command(format, deposit_withdraw(cli, Operation::Withdraw).await)
+            }
+
+            "send" | "s" => handle_command(format, send(cli).await),
+
+            "order" | "o" => handle_command(format, order(cli).await),
+
+            "balance" | "b" => {
+                handle_command(format, cli.balance(&read_from_stdin("Account:")).await)
+            }
+
+            "accounts" | "a" => handle_command(
+                format,
+                cli.all_accounts(&AccountsQuery::default()).await.map(whole),
+            ),
+
+            "orderbook" | "ob" => handle_command(
+                format,
+                cli.all_orders(&OrderbookQuery::default()).await.map(whole),
+            ),
+
+            "txlog" | "tx" => handle_command(
+                format,
+                cli.all_transactions(&TxLogQuery::default())
+                    .await
+                    .map(whole),
+            ),
+
+            "quit" | "q" => {
+                println!("Quitting...");
+                break;
+            }
+            _ => {
+                eprintln!("Invalid option: '{}'", input);
+            }
+        }
+    }
+}
+
+async fn deposit_withdraw(cli: &Client, op_type: Operation) -> Result<Tx, CliError> {
+    let account = read_from_stdin("Account:");
+
+    let amount = read_from_stdin("Amount:")
+        .parse::<Notional>()
+        .map_err(|_| CliError::InvalidNumber())?;
+
+    match op_type {
+        Operation::Deposit => Ok(cli.deposit(&account, amount).await?),
+        Operation::Withdraw => Ok(cli.withdraw(&account, amount).await?),
+    }
+}
+
+async fn send(cli: &Client) -> Result<(Tx, Tx), CliError> {
+    let sender = read_from_stdin("Sender Account:");
+    let recipient = read_from_stdin("Recipient Account:");
+    let amount = read_from_stdin("Amount:")
+        .parse::<Notional>()
+        .map_err(|_| CliError::InvalidNumber())?;
+
+    Ok(cli
+        .send(&SendRequest {
+            sender,
+            recipient,
+            amount,
+        })
+        .await?)
+}
+
+async fn order(cli: &Client) -> Result<Receipt, CliError> {
+    let order = read_order_parameters().map_err(CliError::InvalidOrderParameters)?;
+
+    Ok(cli.order(&order).await?)
+}
+
+fn read_order_parameters() -> Result<Order, String> {
+    let account = read_from_stdin("Account:");
+    let side = match read_from_stdin("Buy or Sell?:").to_lowercase().as_ref() {
+        "buy" => Ok(Side::Buy),
+        "sell" => Ok(Side::Sell),
+        _ => Err("Unsupported order side"),
+    }?;
+
+    let amount = read_from_stdin("Amount:")
+        .parse::<Quantity>()
+        .map_err(|e| e.to_string())?;
+    let price = read_from_stdin("Price:")
+        .parse::<Price>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(Order {
+        price,
+        amount,
+        side,
+        signer: account,
+    })
+}
+
+fn read_from_stdin(label: &str) -> String {
+    let mut buffer = String::new();
+    println!("{}", label);
+    io::stdin()
+        .read_line(&mut buffer)
+        .expect("Couldn't read from stdin");
+    buffer.trim().to_owned()
+}
+
+fn handle_command<T, E>(format: OutputFormat, res: Result<T, E>)
+where
+    T: Render,
+    E: Into<CliError>,
+{
+    match res {
+        Ok(res) => println!("{}", render(format, &res)),
+        Err(e) => eprintln!("{}", render(format, &e.into().to_response())),
+    }
+}
@@ -0,0 +1,304 @@
+use std::{fs, path::Path};
+
+use clap::Parser;
+use octopus_client::Client;
+use octopus_common::core::types::ErrorResponse;
+use serde::Serialize;
+
+use crate::{
+    errors::EXIT_USAGE,
+    execute,
+    output::{render, table, OutputFormat, Render},
+    Operation,
+};
+
+/// How a script is executed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    /// Execute the remaining operations after one failed
+    pub continue_on_error: bool,
+    /// Validate the script without executing it
+    pub dry_run: bool,
+}
+
+/// One operation of a script
+#[derive(Debug)]
+struct Step {
+    /// Line in the script, known for line-based scripts
+    line: Option<usize>,
+    op: Operation,
+}
+
+/// A line of a line-based script: an operation as it would be given on the command line
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true)]
+struct ScriptLine {
+    #[command(subcommand)]
+    op: Operation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// Executed successfully
+    Ok,
+    /// Rejected by the server or not executed because of an error
+    Failed,
+    /// Not executed because an earlier operation failed
+    Skipped,
+    /// Parsed successfully in a dry run
+    Valid,
+}
+
+#[derive(Debug, Serialize)]
+struct StepResult {
+    step: usize,
+    line: Option<usize>,
+    op: &'static str,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorResponse>,
+}
+
+/// What happened to each operation of a script
+#[derive(Debug, Serialize)]
+struct Summary {
+    succeeded: usize,
+    failed: usize,
+    skipped: usize,
+    valid: usize,
+    steps: Vec<StepResult>,
+}
+
+impl Summary {
+    fn new(steps: Vec<StepResult>) -> Self {
+        let count = |status| steps.iter().filter(|s| s.status == status).count();
+        Summary {
+            succeeded: count(Status::Ok),
+            failed: count(Status::Failed),
+            skipped: count(Status::Skipped),
+            valid: count(Status::Valid),
+            steps,
+        }
+    }
+}
+
+impl Render for Summary {
+    fn headers(&self) -> &'static [&'static str] {
+        &["step", "line", "op", "status", "code", "message"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.steps
+            .iter()
+            .map(|step| {
+                let (code, message) = match &step.error {
+                    Some(e) => (e.code.clone(), e.message.clone()),
+                    None => Default::default(),
+                };
+                vec![
+                    step.step.to_string(),
+                    step.line.map(|l| l.to_string()).unwrap_or_default(),
+                    step.op.to_string(),
+                    format!("{:?}", step.status).to_lowercase(),
+                    code,
+                    message,
+                ]
+            })
+            .collect()
+    }
+
+    fn table(&self) -> String {
+        let counts = if self.valid > 0 {
+            format!("{} valid", self.valid)
+        } else {
+            format!(
+                "{} succeeded, {} failed, {} skipped",
+                self.succeeded, self.failed, self.skipped
+            )
+        };
+        format!("{}\n\n{}", table(self.headers(), &self.rows()), counts)
+    }
+}
+
+/// Executes the script at `path` and prints a summary. Returns the exit code: that of the first failure, if any.
+pub async fn run(cli: &Client, path: &Path, options: Options, format: OutputFormat) -> i32 {
+    let steps = match read(path) {
+        Ok(steps) => steps,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", render(format, &error));
+            }
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut results = vec![];
+    let mut exit_code = 0;
+    for (i, step) in steps.into_iter().enumerate() {
+        let mut result = StepResult {
+            step: i + 1,
+            line: step.line,
+            op: step.op.name(),
+            status: Status::Valid,
+            error: None,
+        };
+        if !options.dry_run {
+            if exit_code != 0 && !options.continue_on_error {
+                result.status = Status::Skipped;
+            } else {
+                match execute(cli, step.op, format).await {
+                    Ok(_) => result.status = Status::Ok,
+                    Err(e) => {
+                        if exit_code == 0 {
+                            exit_code = e.exit_code();
+                        }
+                        result.status = Status::Failed;
+                        result.error = Some(e.to_response());
+                    }
+                }
+            }
+        }
+        results.push(result);
+    }
+
+    println!("{}", render(format, &Summary::new(results)));
+    exit_code
+}
+
+/// Reads the operations of a script, picking the format by the file extension. Reports every invalid line of line-based scripts.
+fn read(path: &Path) -> Result<Vec<Step>, Vec<ErrorResponse>> {
+    let invalid = |message: String| ErrorResponse {
+        code: "invalid_script".to_string(),
+        message,
+    };
+    let content = fs::read_to_string(path)
+        .map_err(|e| vec![invalid(format!("can't read {}: {}", path.display(), e))])?;
+
+    let ops = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str::<Vec<Operation>>(&content)
+            .map_err(|e| vec![invalid(e.to_string())])?,
+        Some("yaml" | "yml") => serde_yaml::from_str::<Vec<Operation>>(&content)
+            .map_err(|e| vec![invalid(e.to_string())])?,
+        _ => {
+            return parse_lines(&content).map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|(line, message)| invalid(format!("line {}: {}", line, message)))
+                    .collect()
+            })
+        }
+    };
+    Ok(ops.into_iter().map(|op| Step { line: None, op }).collect())
+}
+
+/// Parses one operation per line. Blank lines and lines starting with `#` are ignored.
+fn parse_lines(content: &str) -> Result<Vec<Step>, Vec<(usize, String)>> {
+    let mut steps = vec![];
+    let mut errors = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some(words) = shlex::split(line) else {
+            errors.push((line_number, "unbalanced quotes".to_string()));
+            continue;
+        };
+        match ScriptLine::try_parse_from(words) {
+            Ok(parsed) => steps.push(Step {
+                line: Some(line_number),
+                op: parsed.op,
+            }),
+            Err(e) => {
+                // clap's first line is the gist, the rest is usage help
+                let message = e.to_string();
+                let message = message.lines().next().unwrap_or_default();
+                errors.push((
+                    line_number,
+                    message.trim_start_matches("error: ").to_string(),
+                ));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(steps)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use octopus_common::core::types::{Notional, Side};
+
+    #[test]
+    fn test_parse_lines_skips_comments_and_reports_every_error() {
+        let steps = parse_lines(
+            "# seed\n\ndeposit --account 'DESK 1' --amount 100\norder --account A --side buy --amount 1 --price 10\n",
+        )
+        .unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].line, Some(3));
+        match &steps[0].op {
+            Operation::Deposit { account, amount } => {
+                assert_eq!((account.as_str(), *amount), ("DESK 1", Notional::from(100)))
+            }
+            other => panic!("unexpected operation {:?}", other),
+        }
+
+        let errors = parse_lines("deposit --account A\nbalance --account A\nfly\n").unwrap_err();
+        assert_eq!(
+            errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn test_yaml_and_json_scripts_are_tagged_by_op() {
+        let yaml = "- op: deposit\n  account: ALICE\n  amount: 10\n- op: order\n  account: ALICE\n  side: sell\n  amount: 1\n  price: 5\n- op: cancel\n  ordinal: 1\n";
+        let ops: Vec<Operation> = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            ops.iter().map(Operation::name).collect::<Vec<_>>(),
+            vec!["deposit", "order", "cancel"]
+        );
+        match &ops[1] {
+            Operation::Order { side, .. } => assert_eq!(side, &Side::Sell),
+            other => panic!("unexpected operation {:?}", other),
+        }
+
+        let json = r#"[{"op": "orderbook", "side": "Buy"}, {"op": "txlog"}]"#;
+        let ops: Vec<Operation> = serde_json::from_str(json).unwrap();
+        assert_eq!(ops.len(), 2);
+
+        let unknown = r#"[{"op": "deposit", "account": "A", "amount": 1, "price": 3}]"#;
+        assert!(serde_json::from_str::<Vec<Operation>>(unknown).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_dry_run_does_not_contact_the_server() {
+        let path = std::env::temp_dir().join(format!("octopus-dry-run-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "deposit --account A --amount 1\ncancel --ordinal 3\n",
+        )
+        .unwrap();
+        // nothing listens on port 1
+        let cli = Client::new("http://127.0.0.1:1").unwrap();
+
+        let options = Options {
+            dry_run: true,
+            ..Default::default()
+        };
+        let exit_code = run(&cli, &path, options, OutputFormat::Json).await;
+        fs::remove_file(&path).unwrap();
+        assert_eq!(exit_code, 0);
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    io::{self, Stdout},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use octopus_common::core::{
+    decimal::Decimal,
+    types::{Order, OrderbookQuery, PartialOrder, Price, Quantity, Side, Trade, TradesQuery},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+
+use crate::{errors::CliError, output::ladder};
+use octopus_client::Client;
+
+/// Number of trades shown
+const RECENT_TRADES: usize = 50;
+
+const HELP: &str = "b buy  s sell  ↑/↓ select  c cancel  r refresh  q quit";
+
+/// What the user asked for with a keystroke
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    Refresh,
+    Place(Order),
+    Cancel(u64),
+}
+
+/// An order being typed in as `amount@price`
+#[derive(Debug)]
+struct Input {
+    side: Side,
+    text: String,
+}
+
+/// State of the watch screen
+struct App {
+    account: String,
+    book: Vec<PartialOrder>,
+    trades: Vec<Trade>,
+    /// Index into [`App::own_orders`]
+    selected: usize,
+    input: Option<Input>,
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    fn new(account: String) -> Self {
+        App {
+            account,
+            book: vec![],
+            trades: vec![],
+            selected: 0,
+            input: None,
+            status: String::new(),
+            quit: false,
+        }
+    }
+
+    /// The account's resting orders, oldest first
+    fn own_orders(&self) -> Vec<&PartialOrder> {
+        let mut orders: Vec<_> = self
+            .book
+            .iter()
+            .filter(|order| order.signer == self.account)
+            .collect();
+        orders.sort_by_key(|order| order.ordinal);
+        orders
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return Action::None;
+        }
+
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.text.pop();
+                }
+                KeyCode::Char(c) => input.text.push(c),
+                KeyCode::Enter => {
+                    let input = self.input.take().expect("input is being edited");
+                    match parse_order(&input.text) {
+                        Ok((amount, price)) => {
+                            return Action::Place(Order {
+                                price,
+                                amount,
+                                side: input.side,
+                                signer: self.account.clone(),
+                            })
+                        }
+                        Err(e) => self.status = e,
+                    }
+                }
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('r') => return Action::Refresh,
+            KeyCode::Char('b') => {
+                self.input = Some(Input {
+                    side: Side::Buy,
+                    text: String::new(),
+                })
+            }
+            KeyCode::Char('s') => {
+                self.input = Some(Input {
+                    side: Side::Sell,
+                    text: String::new(),
+                })
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected += 1,
+            KeyCode::Char('c') | KeyCode::Delete => {
+                if let Some(order) = self.own_orders().get(self.selected) {
+                    return Action::Cancel(order.ordinal);
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Keeps the selection on an existing order after the book changed
+    fn clamp_selection(&mut self) {
+        self.selected = self.selected.min(self.own_orders().len().saturating_sub(1));
+    }
+}
+
+/// Parses `amount@price` (or `amount price`)
+fn parse_order(text: &str) -> Result<(Quantity, Price), String> {
+    let (amount, price) = text
+        .split_once(['@', ' '])
+        .ok_or_else(|| format!("expected amount@price, not '{}'", text))?;
+    let number = |s: &str| {
+        s.trim()
+            .parse::<Decimal>()
+            .map_err(|_| format!("'{}' is not a number", s.trim()))
+    };
+    Ok((Quantity(number(amount)?), Price(number(price)?)))
+}
+
+/// Shows the live order book of the server and lets `account` place and cancel orders, until the user quits
+pub async fn run(cli: &Client, account: String, interval: Duration) -> Result<(), CliError> {
+    let mut terminal = setup()?;
+    let result = event_loop(&mut terminal, cli, App::new(account), interval).await;
+    restore()?;
+    result
+}
+
+async fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    cli: &Client,
+    mut app: App,
+    interval: Duration,
+) -> Result<(), CliError> {
+    refresh(cli, &mut app).await;
+    let mut last_refresh = Instant::now();
+    // refresh right away instead of waiting for the interval, e.g. after placing an order
+    let mut stale = false;
+
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, &app))?;
+
+        let timeout = if stale {
+            Duration::ZERO
+        } else {
+            interval.saturating_sub(last_refresh.elapsed())
+        };
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match app.handle_key(key) {
+                    Action::None => {}
+                    Action::Refresh => stale = true,
+                    Action::Place(order) => {
+                        app.status = match cli.order(&order).await {
+                            Ok(receipt) => format!(
+                                "order {} accepted, {} matches",
+                                receipt.ordinal,
+                                receipt.matches.len()
+                            ),
+                            Err(e) => e.to_string(),
+                        };
+                        stale = true;
+                    }
+                    Action::Cancel(ordinal) => {
+                        app.status = match cli.cancel(ordinal).await {
+                            Ok(_) => format!("order {} cancelled", ordinal),
+                            Err(e) => e.to_string(),
+                        };
+                        stale = true;
+                    }
+                }
+            }
+        }
+
+        if stale || last_refresh.elapsed() >= interval {
+            refresh(cli, &mut app).await;
+            last_refresh = Instant::now();
+            stale = false;
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the book and the recent trades. Failures are shown, the next refresh tries again.
+async fn refresh(cli: &Client, app: &mut App) {
+    let trades = TradesQuery {
+        limit: Some(RECENT_TRADES),
+        ..Default::default()
+    };
+    match cli.all_orders(&OrderbookQuery::default()).await {
+        Ok(book) => app.book = book,
+        Err(e) => app.status = e.to_string(),
+    }
+    match cli.trades(&trades).await {
+        Ok(trades) => app.trades = trades.items,
+        Err(e) => app.status = e.to_string(),
+    }
+    app.clamp_selection();
+}
+
+fn setup() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    // Leave the terminal usable if we panic
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
+
+fn side_style(side: &Side) -> Style {
+    match side {
+        Side::Buy => Style::default().fg(Color::Green),
+        Side::Sell => Style::default().fg(Color::Red),
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(rows[0]);
+    let panels = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(columns[1]);
+
+    draw_ladder(frame, app, columns[0]);
+    draw_own_orders(frame, app, panels[0]);
+    draw_trades(frame, app, panels[1]);
+
+    let status = match &app.input {
+        Some(input) => format!(
+            "{} amount@price: {}_",
+            if input.side == Side::Buy {
+                "Buy"
+            } else {
+                "Sell"
+            },
+            input.text
+        ),
+        None => app.status.clone(),
+    };
+    frame.render_widget(Paragraph::new(status), rows[1]);
+    frame.render_widget(
+        Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::DIM)),
+        rows[2],
+    );
+}
+
+/// Asks above bids, levels with orders of the account in bold
+fn draw_ladder(frame: &mut Frame, app: &App, area: Rect) {
+    let own = app.own_orders();
+    let rows = ladder(&app.book).into_iter().map(|level| {
+        let size = level.size.to_string();
+        let cells = match level.side {
+            Side::Buy => [size, level.price.to_string(), String::new()],
+            Side::Sell => [String::new(), level.price.to_string(), size],
+        };
+        let mut style = side_style(&level.side);
+        if own
+            .iter()
+            .any(|order| order.side == level.side && order.price == level.price)
+        {
+            style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        }
+        Row::new(cells).style(style)
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ],
+    )
+    .header(Row::new(["BID", "PRICE", "ASK"]).style(Style::default().add_modifier(Modifier::BOLD)))
+    .block(Block::default().borders(Borders::ALL).title("Order book"));
+    frame.render_widget(table, area);
+}
+
+fn draw_own_orders(frame: &mut Frame, app: &App, area: Rect) {
+    let rows = app.own_orders().into_iter().map(|order| {
+        Row::new([
+            order.ordinal.to_string(),
+            format!("{:?}", order.side),
+            order.price.to_string(),
+            order.remaining.to_string(),
+        ])
+        .style(side_style(&order.side))
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ],
+    )
+    .header(
+        Row::new(["ORDINAL", "SIDE", "PRICE", "REMAINING"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Orders of {}", app.account)),
+    );
+    let mut state = TableState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_trades(frame: &mut Frame, app: &App, area: Rect) {
+    let rows = app.trades.iter().map(|trade| {
+        Row::new([
+            trade.id.to_string(),
+            format!("{:?}", trade.side),
+            trade.price.to_string(),
+            trade.amount.to_string(),
+        ])
+        .style(side_style(&trade.side))
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ],
+    )
+    .header(
+        Row::new(["TRADE", "SIDE", "PRICE", "AMOUNT"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Recent trades"),
+    );
+    frame.render_widget(table, area);
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn order(ordinal: u64, side: Side, price: u64, signer: &str) -> PartialOrder {
+        PartialOrder {
+            price: Price::from(price),
+            amount: Quantity::from(1),
+            remaining: Quantity::from(1),
+            side,
+            signer: signer.to_string(),
+            ordinal,
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn app() -> App {
+        let mut app = App::new("ALICE".to_string());
+        app.book = vec![
+            order(3, Side::Sell, 12, "ALICE"),
+            order(1, Side::Sell, 11, "BOB"),
+            order(2, Side::Buy, 9, "ALICE"),
+        ];
+        app
+    }
+
+    #[test]
+    fn test_parse_order_accepts_at_and_space() {
+        assert_eq!(
+            parse_order("5@10"),
+            Ok((Quantity::from(5), Price::from(10)))
+        );
+        assert_eq!(
+            parse_order("5 10"),
+            Ok((Quantity::from(5), Price::from(10)))
+        );
+        assert!(parse_order("5").is_err());
+        assert!(parse_order("five@10").is_err());
+    }
+
+    #[test]
+    fn test_App_places_typed_order() {
+        let mut app = app();
+
+        assert_eq!(app.handle_key(key(KeyCode::Char('s'))), Action::None);
+        for c in "2@13".chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            Action::Place(Order {
+                price: Price::from(13),
+                amount: Quantity::from(2),
+                side: Side::Sell,
+                signer: "ALICE".to_string(),
+            })
+        );
+        assert!(app.input.is_none());
+
+        // typing 'q' into the prompt doesn't quit
+        app.handle_key(key(KeyCode::Char('b')));
+        app.handle_key(key(KeyCode::Char('q')));
+        assert!(!app.quit);
+        assert_eq!(app.handle_key(key(KeyCode::Enter)), Action::None);
+        assert!(!app.status.is_empty());
+    }
+
+    #[test]
+    fn test_App_cancels_selected_own_order() {
+        let mut app = app();
+
+        assert_eq!(app.handle_key(key(KeyCode::Char('c'))), Action::Cancel(2));
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.handle_key(key(KeyCode::Char('c'))), Action::Cancel(3));
+
+        app.handle_key(key(KeyCode::Down));
+        app.clamp_selection();
+        assert_eq!(app.selected, 1);
+
+        app.handle_key(key(KeyCode::Char('q')));
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_draw_highlights_own_levels() {
+        let app = app();
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| {
+            (0..32)
+                .map(|x| buffer.get(x, y).symbol().to_string())
+                .collect::<String>()
+        };
+        // border, header, then the asks from the highest price down and the bids
+        assert!(row(2).contains("12"));
+        assert!(row(3).contains("11"));
+        assert!(row(4).contains("9"));
+        let bold = |y: u16| buffer.get(12, y).modifier.contains(Modifier::BOLD);
+        assert!(bold(2));
+        assert!(!bold(3));
+        assert!(bold(4));
+    }
+}
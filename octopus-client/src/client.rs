@@ -0,0 +1,625 @@
+use std::{cmp, time::Duration};
+
+use octopus_common::{
+    audit::AuditEntry,
+    core::types::{
+        AccountBalance, AccountStatus, AccountsQuery, AmountRequest, AuctionStatus, AuditQuery,
+        CancelOrdersQuery, ErrorResponse, Instrument, MarketStatus, Notional, Order,
+        OrderbookQuery, Page, PartialOrder, Receipt, RiskLimits, SendRequest, Trade, TradesQuery,
+        TradingStateRequest, TxLogQuery,
+    },
+    tx::{LedgerEntry, Tx},
+};
+use reqwest::{header::RETRY_AFTER, Method, RequestBuilder, Response, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::Error;
+
+/// Time allowed for a whole request if not configured otherwise
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Time allowed for connecting if not configured otherwise
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How idempotent requests are repeated after transient failures. The delay between attempts doubles, up to `max_backoff`,
+/// unless the server asks for another one with `Retry-After`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Configures a [`Client`]
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    base_url: String,
+    timeout: Duration,
+    connect_timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl ClientBuilder {
+    /// Time allowed for a request, from connecting to reading the whole response (default 10s)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Time allowed for connecting to the server (default 5s)
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// How reads are retried (default: 3 times, starting after 100ms)
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Error> {
+        let base_url =
+            Url::parse(&self.base_url).map_err(|_| Error::InvalidBaseUrl(self.base_url.clone()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::InvalidBaseUrl(self.base_url));
+        }
+        let http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .build()
+            .map_err(Error::Transport)?;
+
+        Ok(Client {
+            base_url,
+            http,
+            retry: self.retry,
+        })
+    }
+}
+
+/// A typed client of the `/v1` API of an octopus server.
+///
+/// Reads (`GET`) and the admin updates that set limits or states (`PUT`) are retried by the [`RetryPolicy`] on transient errors,
+/// other writes are sent exactly once.
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: Url,
+    http: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl Client {
+    /// A client with the default configuration. `base_url` may contain a path prefix, e.g. `https://example.com/octopus`.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        Client::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: &str) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.to_string(),
+            timeout: TIMEOUT,
+            connect_timeout: CONNECT_TIMEOUT,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// The URL the API paths are appended to
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Deposits `amount` into `account`, creating the account if necessary
+    pub async fn deposit(&self, account: &str, amount: Notional) -> Result<Tx, Error> {
+        let request = self.request(Method::POST, &["v1", "accounts", account, "deposit"]);
+        send(request.json(&AmountRequest { amount })).await
+    }
+
+    /// Withdraws `amount` from `account`
+    pub async fn withdraw(&self, account: &str, amount: Notional) -> Result<Tx, Error> {
+        let request = self.request(Method::POST, &["v1", "accounts", account, "withdraw"]);
+        send(request.json(&AmountRequest { amount })).await
+    }
+
+    /// Transfers currency between two accounts, returns the withdrawal and the deposit
+    pub async fn send(&self, send_request: &SendRequest) -> Result<(Tx, Tx), Error> {
+        let request = self.request(Method::POST, &["v1", "transfers"]);
+        send(request.json(send_request)).await
+    }
+
+    /// Submits an order
+    pub async fn order(&self, order: &Order) -> Result<Receipt, Error> {
+        let request = self.request(Method::POST, &["v1", "orders"]);
+        send(request.json(order)).await
+    }
+
+    /// Cancels a resting order, returns it as it was removed from the book
+    pub async fn cancel(&self, ordinal: u64) -> Result<PartialOrder, Error> {
+        let ordinal = ordinal.to_string();
+        send(self.request(Method::DELETE, &["v1", "orders", &ordinal])).await
+    }
+
+    /// Cancels the resting orders of a signer, or only those on one side, and returns them
+    pub async fn cancel_orders(
+        &self,
+        query: &CancelOrdersQuery,
+    ) -> Result<Vec<PartialOrder>, Error> {
+        send(self.request(Method::DELETE, &["v1", "orders"]).query(query)).await
+    }
+
+    pub async fn balance(&self, account: &str) -> Result<AccountBalance, Error> {
+        self.get(&["v1", "accounts", account, "balance"], &()).await
+    }
+
+    /// The pre-trade limits that apply to the orders of `account`
+    pub async fn limits(&self, account: &str) -> Result<RiskLimits, Error> {
+        self.get(&["v1", "accounts", account, "limits"], &()).await
+    }
+
+    /// Gives `account` its own pre-trade limits, returns the ones that apply from now on
+    pub async fn set_limits(
+        &self,
+        account: &str,
+        limits: &RiskLimits,
+    ) -> Result<RiskLimits, Error> {
+        self.put(&["admin", "accounts", account, "limits"], limits)
+            .await
+    }
+
+    /// Fetches one page of accounts
+    pub async fn accounts(
+        &self,
+        query: &AccountsQuery,
+    ) -> Result<Page<AccountBalance, String>, Error> {
+        self.get(&["v1", "accounts"], query).await
+    }
+
+    /// Fetches one page of resting orders
+    pub async fn orderbook(
+        &self,
+        query: &OrderbookQuery,
+    ) -> Result<Page<PartialOrder, u64>, Error> {
+        self.get(&["v1", "orderbook"], query).await
+    }
+
+    /// The traded instruments and their trading rules, e.g. to round orders to the tick and lot size
+    pub async fn instruments(&self) -> Result<Vec<Instrument>, Error> {
+        self.get(&["v1", "instruments"], &()).await
+    }
+
+    /// The trading states of the market and its instruments
+    pub async fn market(&self) -> Result<MarketStatus, Error> {
+        self.get(&["v1", "market"], &()).await
+    }
+
+    /// Where an auction would uncross if its call phase ended now
+    pub async fn auction(&self) -> Result<AuctionStatus, Error> {
+        self.get(&["v1", "auction"], &()).await
+    }
+
+    /// Fetches one page of trades, newest first
+    pub async fn trades(&self, query: &TradesQuery) -> Result<Page<Trade, u64>, Error> {
+        self.get(&["v1", "trades"], query).await
+    }
+
+    /// Fetches one page of the transaction log
+    pub async fn txlog(&self, query: &TxLogQuery) -> Result<Page<LedgerEntry, u64>, Error> {
+        self.get(&["v1", "txlog"], query).await
+    }
+
+    /// Fetches all accounts matching `query`, following the pages from `query.after` on
+    pub async fn all_accounts(&self, query: &AccountsQuery) -> Result<Vec<AccountBalance>, Error> {
+        let mut query = query.clone();
+        let mut accounts = vec![];
+        loop {
+            let page = self.accounts(&query).await?;
+            accounts.extend(page.items);
+            match page.next {
+                Some(next) => query.after = Some(next),
+                None => return Ok(accounts),
+            }
+        }
+    }
+
+    /// Fetches all resting orders matching `query`, following the pages from `query.after` on
+    pub async fn all_orders(&self, query: &OrderbookQuery) -> Result<Vec<PartialOrder>, Error> {
+        let mut query = query.clone();
+        let mut orders = vec![];
+        loop {
+            let page = self.orderbook(&query).await?;
+            orders.extend(page.items);
+            match page.next {
+                Some(next) => query.after = Some(next),
+                None => return Ok(orders),
+            }
+        }
+    }
+
+    /// Fetches all transactions matching `query`, following the pages from `query.after` on
+    pub async fn all_transactions(&self, query: &TxLogQuery) -> Result<Vec<LedgerEntry>, Error> {
+        let mut query = query.clone();
+        let mut entries = vec![];
+        loop {
+            let page = self.txlog(&query).await?;
+            entries.extend(page.items);
+            match page.next {
+                Some(next) => query.after = Some(next),
+                None => return Ok(entries),
+            }
+        }
+    }
+
+    /// Stops `account` from placing orders and cancels its resting orders
+    pub async fn disable(&self, account: &str) -> Result<AccountStatus, Error> {
+        send(self.request(Method::POST, &["admin", "accounts", account, "disable"])).await
+    }
+
+    /// Lets a disabled `account` place orders again
+    pub async fn enable(&self, account: &str) -> Result<AccountStatus, Error> {
+        send(self.request(Method::POST, &["admin", "accounts", account, "enable"])).await
+    }
+
+    /// Sets the trading state of the whole market, e.g. to halt or resume trading
+    pub async fn set_market_state(
+        &self,
+        request: &TradingStateRequest,
+    ) -> Result<MarketStatus, Error> {
+        self.put(&["admin", "market", "state"], request).await
+    }
+
+    /// Sets the trading state of the instrument with `symbol`
+    pub async fn set_instrument_state(
+        &self,
+        symbol: &str,
+        request: &TradingStateRequest,
+    ) -> Result<MarketStatus, Error> {
+        self.put(&["admin", "instruments", symbol, "state"], request)
+            .await
+    }
+
+    /// Fetches one page of the audit log
+    pub async fn audit(&self, query: &AuditQuery) -> Result<Page<AuditEntry, u64>, Error> {
+        self.get(&["admin", "audit"], query).await
+    }
+
+    /// The OpenAPI document of the API
+    pub async fn openapi(&self) -> Result<serde_json::Value, Error> {
+        self.get(&["v1", "openapi.json"], &()).await
+    }
+
+    /// Whether the server is up (`/healthz`)
+    pub async fn health(&self) -> Result<(), Error> {
+        let response = check(self.request(Method::GET, &["healthz"]).send().await?).await?;
+        response.bytes().await?;
+        Ok(())
+    }
+
+    /// Whether the server has recovered its state and accepts orders (`/readyz`). Not retried, so it can be polled.
+    pub async fn ready(&self) -> Result<bool, Error> {
+        match check(self.request(Method::GET, &["readyz"]).send().await?).await {
+            Ok(_) => Ok(true),
+            Err(Error::Server {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The Prometheus metrics of the server (`/metrics`)
+    pub async fn metrics(&self) -> Result<String, Error> {
+        let response = check(self.request(Method::GET, &["metrics"]).send().await?).await?;
+        Ok(response.text().await?)
+    }
+
+    /// The base URL followed by `segments`, each escaped as a path segment
+    pub fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("base URLs are checked when building the client")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        self.http.request(method, self.url(segments))
+    }
+
+    /// Sends a `GET` request, retrying transient failures
+    async fn get<Q, U>(&self, segments: &[&str], query: &Q) -> Result<U, Error>
+    where
+        Q: Serialize + ?Sized,
+        U: DeserializeOwned,
+    {
+        self.retrying(|| self.request(Method::GET, segments).query(query))
+            .await
+    }
+
+    /// Sends a `PUT` request with a JSON `body`, retrying transient failures
+    async fn put<B, U>(&self, segments: &[&str], body: &B) -> Result<U, Error>
+    where
+        B: Serialize + ?Sized,
+        U: DeserializeOwned,
+    {
+        self.retrying(|| self.request(Method::PUT, segments).json(body))
+            .await
+    }
+
+    /// Sends the request `build` makes until it succeeds, fails for good or runs out of retries
+    async fn retrying<U: DeserializeOwned>(
+        &self,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<U, Error> {
+        // Only idempotent requests are retried: repeating a deposit or an order could book it twice
+        let mut backoff = self.retry.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match send(build()).await {
+                Err(e) if e.is_transient() && retries < self.retry.max_retries => {
+                    retries += 1;
+                    tokio::time::sleep(e.retry_after().unwrap_or(backoff)).await;
+                    backoff = cmp::min(backoff * 2, self.retry.max_backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+async fn send<U: DeserializeOwned>(request: RequestBuilder) -> Result<U, Error> {
+    let response = check(request.send().await?).await?;
+    Ok(response.json().await?)
+}
+
+/// Turns unsuccessful responses into errors
+async fn check(response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    // Only a number of seconds is understood, not a date
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await?;
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(error) if status.is_client_error() => Err(Error::Rejected { status, error }),
+        _ => Err(Error::Server {
+            status,
+            body,
+            retry_after,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use octopus_common::core::types::TradingState;
+    use std::sync::{Arc, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Serves the canned `(status, body)` responses in turn and records the requests as `METHOD /path?query`.
+    /// An empty body with status 0 never answers.
+    async fn serve(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        serve_with_headers(
+            responses
+                .into_iter()
+                .map(|(status, body)| (status, "", body))
+                .collect(),
+        )
+        .await
+    }
+
+    /// Like [`serve`], with `(status, headers, body)` responses whose headers are lines ending in `\r\n`
+    async fn serve_with_headers(
+        responses: Vec<(u16, &'static str, &'static str)>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let log = requests.clone();
+        tokio::spawn(async move {
+            for (status, headers, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let n = socket.read(&mut buffer).await.unwrap();
+                let head = String::from_utf8_lossy(&buffer[..n]).to_string();
+                let line = head.lines().next().unwrap_or_default();
+                let target: Vec<_> = line.split(' ').take(2).collect();
+                log.lock().unwrap().push(target.join(" "));
+
+                if status == 0 {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    continue;
+                }
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (format!("http://{}", addr), requests)
+    }
+
+    fn fast_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_Client_url_keeps_base_path_and_escapes_segments() {
+        let client = Client::new("http://example.com/octopus").unwrap();
+        assert_eq!(
+            client
+                .url(&["v1", "accounts", "DESK 1/a", "balance"])
+                .as_str(),
+            "http://example.com/octopus/v1/accounts/DESK%201%2Fa/balance"
+        );
+
+        let client = Client::new("http://example.com/octopus/").unwrap();
+        assert_eq!(
+            client.url(&["v1", "orders"]).as_str(),
+            "http://example.com/octopus/v1/orders"
+        );
+
+        assert!(matches!(
+            Client::new("mailto:desk@example.com"),
+            Err(Error::InvalidBaseUrl(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_Client_retries_idempotent_calls() {
+        let (url, requests) = serve(vec![
+            (503, "recovering"),
+            (502, ""),
+            (200, r#"{"signer":"ALICE","balance":3}"#),
+        ])
+        .await;
+        let client = Client::builder(&url).retry(fast_retries()).build().unwrap();
+
+        let balance = client.balance("ALICE").await.unwrap();
+        assert_eq!(balance.balance, 3.into());
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_Client_gives_up_after_max_retries() {
+        let (url, requests) = serve(vec![(503, ""), (503, ""), (503, ""), (200, "{}")]).await;
+        let client = Client::builder(&url).retry(fast_retries()).build().unwrap();
+
+        let err = client
+            .orderbook(&OrderbookQuery::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Server { status, .. } if status == StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_Client_retries_admin_updates_when_the_server_asks_to() {
+        let (url, requests) = serve_with_headers(vec![
+            (
+                503,
+                "retry-after: 1\r\n",
+                r#"{"code":"overloaded","message":"too many pending requests, retry later"}"#,
+            ),
+            (200, "", r#"{"market":{"state":"halted"},"instruments":[]}"#),
+        ])
+        .await;
+        let client = Client::builder(&url).retry(fast_retries()).build().unwrap();
+
+        let started = std::time::Instant::now();
+        let request = TradingStateRequest {
+            state: TradingState::Halted,
+            reason: None,
+        };
+        let status = client.set_market_state(&request).await.unwrap();
+        assert_eq!(status.market.state, TradingState::Halted);
+        // The server's delay instead of the policy's millisecond
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["PUT /admin/market/state", "PUT /admin/market/state"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_Client_sends_writes_once() {
+        let (url, requests) = serve(vec![(503, ""), (200, "{}")]).await;
+        let client = Client::builder(&url).retry(fast_retries()).build().unwrap();
+
+        assert!(client.deposit("ALICE", 1.into()).await.is_err());
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["POST /v1/accounts/ALICE/deposit"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_Client_reports_rejections_with_code() {
+        let (url, _) = serve(vec![(
+            422,
+            r#"{"code":"account_under_funded","message":"account 'ALICE' has less than 5"}"#,
+        )])
+        .await;
+        let client = Client::new(&url).unwrap();
+
+        let err = client.withdraw("ALICE", 5.into()).await.unwrap_err();
+        assert_eq!(err.code(), Some("account_under_funded"));
+        assert!(!err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_Client_times_out() {
+        let (url, _) = serve(vec![(0, "")]).await;
+        let client = Client::builder(&url)
+            .timeout(Duration::from_millis(50))
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+
+        let err = client.txlog(&TxLogQuery::default()).await.unwrap_err();
+        assert!(matches!(err, Error::Timeout), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_Client_sends_queries() {
+        let (url, requests) = serve(vec![(200, r#"{"items":[],"next":null}"#)]).await;
+        let client = Client::new(&format!("{}/prefix", url)).unwrap();
+
+        let query = AccountsQuery {
+            prefix: Some("bot".to_string()),
+            limit: Some(5),
+            ..Default::default()
+        };
+        client.accounts(&query).await.unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["GET /prefix/v1/accounts?limit=5&prefix=bot"]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use octopus_common::core::types::ErrorResponse;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+/// Everything that can go wrong talking to an octopus server
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The base URL can't have paths appended, e.g. `mailto:` URLs
+    #[error("invalid base URL '{0}'")]
+    InvalidBaseUrl(String),
+
+    /// The server refused the request, e.g. because of insufficient funds or an unknown account
+    #[error("{} ({})", .error.message, .error.code)]
+    Rejected {
+        status: StatusCode,
+        error: ErrorResponse,
+    },
+
+    /// The server failed without explaining why in an [`ErrorResponse`], or is unavailable for now
+    #[error("server error {status}: {body}")]
+    Server {
+        status: StatusCode,
+        body: String,
+        /// How long the server asked to wait before trying again (`Retry-After`)
+        retry_after: Option<Duration>,
+    },
+
+    /// No response arrived within the configured timeout
+    #[error("request timed out")]
+    Timeout,
+
+    /// The server couldn't be reached or the connection broke
+    #[error("transport error: {0}")]
+    Transport(reqwest::Error),
+
+    /// The response didn't have the expected shape
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+impl Error {
+    /// The server's error code, if it rejected the request
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Rejected { error, .. } => Some(&error.code),
+            _ => None,
+        }
+    }
+
+    /// How long the server asked to wait before repeating the request
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Server { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether repeating the request might succeed: the server was unreachable, slow or temporarily unavailable
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Timeout | Error::Transport(_) => true,
+            Error::Server { status, .. } => matches!(
+                *status,
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            Error::InvalidBaseUrl(_) | Error::Rejected { .. } | Error::InvalidResponse(_) => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout
+        } else if e.is_decode() {
+            Error::InvalidResponse(e.to_string())
+        } else {
+            Error::Transport(e)
+        }
+    }
+}
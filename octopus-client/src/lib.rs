@@ -0,0 +1,7 @@
+//! An async client of the octopus trading platform's REST API
+
+mod client;
+mod error;
+
+pub use client::{Client, ClientBuilder, RetryPolicy};
+pub use error::Error;
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::core::types::{RiskLimits, Side, TradingState};
+
+/// An administrative or bulk action, which unlike a [`crate::tx::Tx`] doesn't move currency
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, ToSchema)]
+pub enum AuditEvent {
+    /// The account may not place orders anymore, its resting orders were cancelled
+    AccountDisabled {
+        account: String,
+        /// Ordinals of the orders that were cancelled, none if the account was disabled already
+        cancelled: Vec<u64>,
+    },
+
+    /// The account may place orders again
+    AccountEnabled { account: String },
+
+    /// The account's resting orders were cancelled at once
+    OrdersCancelled {
+        account: String,
+        /// Only orders of this side were cancelled
+        side: Option<Side>,
+        /// Ordinals of the orders that were cancelled
+        cancelled: Vec<u64>,
+    },
+
+    /// The account was given its own pre-trade limits, or lost them if none are set
+    RiskLimitsSet { account: String, limits: RiskLimits },
+
+    /// An order that waited for trading to open entered the book
+    QueuedOrderPlaced { account: String, ordinal: u64 },
+
+    /// An order that waited for trading to open failed its checks once trading opened
+    QueuedOrderRejected {
+        account: String,
+        ordinal: u64,
+        /// The error the order was rejected with
+        reason: String,
+    },
+
+    /// The trading state of the market or an instrument changed, by operations or a circuit breaker
+    TradingStateChanged {
+        /// The instrument, or the whole market if there is none
+        instrument: Option<String>,
+        state: TradingState,
+        reason: Option<String>,
+    },
+}
+
+impl AuditEvent {
+    /// The account the action was taken on, if it was about an account
+    pub fn account(&self) -> Option<&str> {
+        match self {
+            AuditEvent::AccountDisabled { account, .. }
+            | AuditEvent::AccountEnabled { account }
+            | AuditEvent::OrdersCancelled { account, .. }
+            | AuditEvent::RiskLimitsSet { account, .. }
+            | AuditEvent::QueuedOrderPlaced { account, .. }
+            | AuditEvent::QueuedOrderRejected { account, .. } => Some(account),
+            AuditEvent::TradingStateChanged { .. } => None,
+        }
+    }
+}
+
+/// An [`AuditEvent`] as it was recorded in the audit log
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 1
+    pub id: u64,
+    /// When the action was taken, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub event: AuditEvent,
+}
@@ -0,0 +1,452 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use utoipa::{
+    openapi::{ObjectBuilder, RefOr, Schema, SchemaType},
+    ToSchema,
+};
+
+/// Most decimal places a [`Decimal`] can have, so that `10^scale` fits into a `u64`
+pub const MAX_SCALE: u8 = 18;
+
+/// A non-negative fixed-point number of `units / 10^scale`, e.g. 1250 units at scale 2 are 12.50.
+///
+/// Numbers compare by value, so 12.5 and 12.50 are equal. Arithmetic is exact or fails, unless it says it rounds.
+///
+/// In JSON a decimal is a string like `"12.50"`, which keeps every decimal place; plain integers are accepted as input too.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Decimal {
+    units: u64,
+    scale: u8,
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal { units: 0, scale: 0 };
+
+    /// The number `units / 10^scale`
+    ///
+    /// # Panics
+    /// `scale` is greater than [`MAX_SCALE`]
+    pub const fn new(units: u64, scale: u8) -> Self {
+        assert!(scale <= MAX_SCALE, "scale is greater than MAX_SCALE");
+        Decimal { units, scale }
+    }
+
+    /// The number as a multiple of `10^-scale`
+    pub fn units(self) -> u64 {
+        self.units
+    }
+
+    /// Number of decimal places
+    pub fn scale(self) -> u8 {
+        self.scale
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.units == 0
+    }
+
+    /// The same number with `scale` decimal places, or `None` if that would drop a non-zero digit or overflow
+    pub fn rescale(self, scale: u8) -> Option<Decimal> {
+        if scale > MAX_SCALE {
+            None
+        } else if scale >= self.scale {
+            self.units
+                .checked_mul(pow10(scale - self.scale))
+                .map(|units| Decimal::new(units, scale))
+        } else {
+            let divisor = pow10(self.scale - scale);
+            self.units
+                .is_multiple_of(divisor)
+                .then(|| Decimal::new(self.units / divisor, scale))
+        }
+    }
+
+    /// The number with `scale` decimal places, dropping the ones beyond, or `None` if it overflows
+    pub fn round_down(self, scale: u8) -> Option<Decimal> {
+        if scale >= self.scale {
+            self.rescale(scale)
+        } else {
+            Some(Decimal::new(self.units / pow10(self.scale - scale), scale))
+        }
+    }
+
+    /// The exact sum at the larger of both scales, or `None` if it overflows
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let units = self
+            .rescale(scale)?
+            .units
+            .checked_add(other.rescale(scale)?.units)?;
+        Some(Decimal::new(units, scale))
+    }
+
+    /// The exact difference at the larger of both scales, or `None` if it would be negative or overflows
+    pub fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let units = self
+            .rescale(scale)?
+            .units
+            .checked_sub(other.rescale(scale)?.units)?;
+        Some(Decimal::new(units, scale))
+    }
+
+    /// The sum, or the largest number there is if it overflows
+    pub fn saturating_add(self, other: Decimal) -> Decimal {
+        self.checked_add(other)
+            .unwrap_or(Decimal::new(u64::MAX, self.scale.max(other.scale)))
+    }
+
+    /// The product rounded down to `scale` decimal places, or `None` if it doesn't fit
+    pub fn checked_mul(self, other: Decimal, scale: u8) -> Option<Decimal> {
+        if scale > MAX_SCALE {
+            return None;
+        }
+        // The exact product of two u64 always fits into a u128, at up to twice the scale
+        let product = u128::from(self.units) * u128::from(other.units);
+        let exact = self.scale + other.scale;
+        let units = if scale >= exact {
+            product.checked_mul(10u128.pow(u32::from(scale - exact)))?
+        } else {
+            product / 10u128.pow(u32::from(exact - scale))
+        };
+        u64::try_from(units)
+            .ok()
+            .map(|units| Decimal::new(units, scale))
+    }
+
+    /// Whether the number is a whole multiple of `step`, e.g. 12.50 of a tick size of 0.05. Only zero is a multiple of zero.
+    pub fn is_multiple_of(self, step: Decimal) -> bool {
+        let scale = self.scale.max(step.scale);
+        let (units, step) = (self.units_at(scale), step.units_at(scale));
+        units.is_multiple_of(step)
+    }
+
+    /// The nearest `f64`, e.g. for metrics
+    pub fn to_f64(self) -> f64 {
+        self.units as f64 / pow10(self.scale) as f64
+    }
+
+    /// The units at a scale at least as large as the own one, which always fit into a u128
+    fn units_at(self, scale: u8) -> u128 {
+        u128::from(self.units) * 10u128.pow(u32::from(scale - self.scale))
+    }
+}
+
+fn pow10(exp: u8) -> u64 {
+    10u64.pow(u32::from(exp))
+}
+
+impl From<u64> for Decimal {
+    fn from(value: u64) -> Self {
+        Decimal::new(value, 0)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.scale == other.scale {
+            return self.units.cmp(&other.units);
+        }
+        let scale = self.scale.max(other.scale);
+        self.units_at(scale).cmp(&other.units_at(scale))
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Equal numbers have to hash the same, so trailing zeros are dropped first
+        let (mut units, mut scale) = (self.units, self.scale);
+        while scale > 0 && units.is_multiple_of(10) {
+            units /= 10;
+            scale -= 1;
+        }
+        units.hash(state);
+        scale.hash(state);
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.scale == 0 {
+            return write!(f, "{}", self.units);
+        }
+        let divisor = pow10(self.scale);
+        write!(
+            f,
+            "{}.{:0width$}",
+            self.units / divisor,
+            self.units % divisor,
+            width = usize::from(self.scale)
+        )
+    }
+}
+
+/// Why a string isn't a [`Decimal`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDecimalError {
+    /// Not digits with an optional fractional part, like `12` or `12.50`
+    Invalid(String),
+    /// More than [`MAX_SCALE`] decimal places
+    TooPrecise(String),
+    /// More units than fit into a `u64`
+    TooLarge(String),
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDecimalError::Invalid(s) => {
+                write!(f, "'{}' is not a decimal number like 12.50", s)
+            }
+            ParseDecimalError::TooPrecise(s) => {
+                write!(f, "'{}' has more than {} decimal places", s, MAX_SCALE)
+            }
+            ParseDecimalError::TooLarge(s) => write!(f, "'{}' is too large", s),
+        }
+    }
+}
+
+impl Error for ParseDecimalError {}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        if !digits(integer) || (s.contains('.') && !digits(fraction)) {
+            return Err(ParseDecimalError::Invalid(s.to_string()));
+        }
+        let scale = u8::try_from(fraction.len())
+            .ok()
+            .filter(|scale| *scale <= MAX_SCALE)
+            .ok_or_else(|| ParseDecimalError::TooPrecise(s.to_string()))?;
+
+        let too_large = || ParseDecimalError::TooLarge(s.to_string());
+        let integer: u64 = integer.parse().map_err(|_| too_large())?;
+        let fraction: u64 = if fraction.is_empty() {
+            0
+        } else {
+            fraction.parse().map_err(|_| too_large())?
+        };
+        integer
+            .checked_mul(pow10(scale))
+            .and_then(|units| units.checked_add(fraction))
+            .map(|units| Decimal::new(units, scale))
+            .ok_or_else(too_large)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DecimalVisitor;
+
+        impl Visitor<'_> for DecimalVisitor {
+            type Value = Decimal;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a decimal string like \"12.50\" or a non-negative integer")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimal, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+                Ok(Decimal::from(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+                u64::try_from(v)
+                    .map(Decimal::from)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
+impl<'s> ToSchema<'s> for Decimal {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "Decimal",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .description(Some(
+                    "A non-negative decimal number, e.g. \"12.50\"; integers are accepted as input",
+                ))
+                .pattern(Some(r"^[0-9]+(\.[0-9]+)?$"))
+                .example(Some("12.50".into()))
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_Decimal_parses_and_displays_every_decimal_place() {
+        assert_eq!(decimal("12.50"), Decimal::new(1250, 2));
+        assert_eq!(decimal("12.50").to_string(), "12.50");
+        assert_eq!(decimal("0.007").to_string(), "0.007");
+        assert_eq!(decimal("42").to_string(), "42");
+        assert_eq!(decimal("18446744073709551615"), Decimal::from(u64::MAX));
+        assert_eq!(
+            decimal("1.000000000000000000"),
+            Decimal::new(10u64.pow(18), 18)
+        );
+
+        for invalid in ["", ".5", "5.", "-1", "+1", "1e3", "1.2.3", " 1", "1,5"] {
+            assert_eq!(
+                invalid.parse::<Decimal>(),
+                Err(ParseDecimalError::Invalid(invalid.to_string())),
+                "{}",
+                invalid
+            );
+        }
+        assert!(matches!(
+            "0.0000000000000000001".parse::<Decimal>(),
+            Err(ParseDecimalError::TooPrecise(_))
+        ));
+        assert!(matches!(
+            "18446744073709551616".parse::<Decimal>(),
+            Err(ParseDecimalError::TooLarge(_))
+        ));
+        assert!(matches!(
+            "18446744073709551615.5".parse::<Decimal>(),
+            Err(ParseDecimalError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_Decimal_compares_by_value() {
+        assert_eq!(decimal("12.5"), decimal("12.500"));
+        assert!(decimal("12.49") < decimal("12.5"));
+        assert!(Decimal::from(u64::MAX) > Decimal::new(u64::MAX, 18));
+
+        let hash = |d: Decimal| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            d.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(decimal("12.5")), hash(decimal("12.500")));
+    }
+
+    #[test]
+    fn test_Decimal_rescale_is_exact() {
+        assert_eq!(decimal("12.5").rescale(3), Some(Decimal::new(12500, 3)));
+        assert_eq!(decimal("12.50").rescale(1), Some(Decimal::new(125, 1)));
+        assert_eq!(decimal("12.55").rescale(1), None);
+        assert_eq!(Decimal::from(u64::MAX).rescale(1), None);
+        assert_eq!(decimal("1").rescale(MAX_SCALE + 1), None);
+    }
+
+    #[test]
+    fn test_Decimal_rounds_down() {
+        assert_eq!(decimal("12.559").round_down(2), Some(decimal("12.55")));
+        assert_eq!(decimal("0.009").round_down(2), Some(Decimal::new(0, 2)));
+        assert_eq!(decimal("12.5").round_down(2), Some(decimal("12.50")));
+
+        // 0.333 × 0.5 = 0.1665
+        assert_eq!(
+            decimal("0.333").checked_mul(decimal("0.5"), 2),
+            Some(Decimal::new(16, 2))
+        );
+        assert_eq!(
+            decimal("10.25").checked_mul(decimal("3"), 4),
+            Some(decimal("30.7500"))
+        );
+        assert_eq!(Decimal::from(u64::MAX).checked_mul(decimal("2"), 0), None);
+        // Too large at scale 4, but not once rounded down to scale 0
+        assert_eq!(
+            Decimal::new(u64::MAX, 2).checked_mul(Decimal::new(200, 2), 0),
+            Some(Decimal::from(368934881474191032))
+        );
+    }
+
+    #[test]
+    fn test_Decimal_is_multiple_of_at_any_scale() {
+        let step: Decimal = "0.05".parse().unwrap();
+        assert!(Decimal::new(1250, 2).is_multiple_of(step));
+        assert!(Decimal::from(3).is_multiple_of(step));
+        assert!(!Decimal::new(1251, 3).is_multiple_of(step));
+        assert!(!Decimal::new(1, 2).is_multiple_of(step));
+        assert!(Decimal::from(u64::MAX).is_multiple_of(Decimal::new(1, 18)));
+
+        assert!(Decimal::ZERO.is_multiple_of(Decimal::ZERO));
+        assert!(!Decimal::from(1).is_multiple_of(Decimal::ZERO));
+    }
+
+    #[test]
+    fn test_Decimal_adds_and_subtracts_at_the_larger_scale() {
+        assert_eq!(
+            decimal("1.5").checked_add(decimal("0.25")),
+            Some(Decimal::new(175, 2))
+        );
+        assert_eq!(
+            decimal("1.5").checked_sub(decimal("1.50")),
+            Some(Decimal::new(0, 2))
+        );
+        assert_eq!(decimal("1.5").checked_sub(decimal("1.51")), None);
+        assert_eq!(Decimal::from(u64::MAX).checked_add(decimal("0.1")), None);
+    }
+
+    #[test]
+    fn test_Decimal_json_is_a_string() {
+        assert_eq!(
+            serde_json::to_string(&decimal("12.50")).unwrap(),
+            r#""12.50""#
+        );
+        assert_eq!(
+            serde_json::from_str::<Decimal>(r#""12.50""#).unwrap(),
+            decimal("12.50")
+        );
+        assert_eq!(
+            serde_json::from_str::<Decimal>("42").unwrap(),
+            Decimal::from(42)
+        );
+        assert!(serde_json::from_str::<Decimal>("12.5").is_err());
+        assert!(serde_json::from_str::<Decimal>("-1").is_err());
+        assert!(serde_json::from_str::<Decimal>(r#""12.5.0""#).is_err());
+    }
+}
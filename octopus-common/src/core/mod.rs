@@ -1 +1,2 @@
+pub mod decimal;
 pub mod types;
@@ -1,9 +1,22 @@
-use std::cmp::Reverse;
+use std::{
+    cmp::Reverse,
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    audit::AuditEntry,
+    core::decimal::{Decimal, ParseDecimalError},
+    errors::ApplicationError,
+    tx::{LedgerEntry, TxKind},
+};
 
 /// Simplified side of a position as well as order.
-#[derive(Clone, PartialOrd, PartialEq, Eq, Debug, Ord, Serialize, Deserialize)]
+#[derive(Clone, PartialOrd, PartialEq, Eq, Debug, Ord, Serialize, Deserialize, ToSchema)]
 pub enum Side {
     /// Want to buy
     Buy,
@@ -11,13 +24,463 @@ pub enum Side {
     Sell,
 }
 
+/// Price of one unit, in currency
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(transparent)]
+pub struct Price(pub Decimal);
+
+impl Price {
+    /// What `quantity` units at this price are worth with `scale` decimal places, or `None` if that doesn't fit into a [`Notional`]
+    ///
+    /// Decimal places beyond `scale` are dropped, so a buyer never pays more than the exact product.
+    pub fn notional(self, quantity: Quantity, scale: u8) -> Option<Notional> {
+        // Rounding down also makes the notionals of the fills of an order add up to at most that of the order
+        self.0.checked_mul(quantity.0, scale).map(Notional)
+    }
+}
+
+/// A number of units
+///
+/// Adding and subtracting panics on overflow in every build instead of wrapping around, as the engine only subtracts what is there.
+/// Use the `checked_` methods for input.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(transparent)]
+pub struct Quantity(pub Decimal);
+
+impl Quantity {
+    pub const ZERO: Quantity = Quantity(Decimal::ZERO);
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn checked_add(self, other: Quantity) -> Option<Quantity> {
+        self.0.checked_add(other.0).map(Quantity)
+    }
+
+    pub fn checked_sub(self, other: Quantity) -> Option<Quantity> {
+        self.0.checked_sub(other.0).map(Quantity)
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+
+    fn add(self, other: Quantity) -> Quantity {
+        self.checked_add(other).expect("quantity overflow")
+    }
+}
+
+impl AddAssign for Quantity {
+    fn add_assign(&mut self, other: Quantity) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Quantity;
+
+    fn sub(self, other: Quantity) -> Quantity {
+        self.checked_sub(other).expect("quantity underflow")
+    }
+}
+
+impl SubAssign for Quantity {
+    fn sub_assign(&mut self, other: Quantity) {
+        *self = *self - other;
+    }
+}
+
+/// An amount of currency, like a balance or what a quantity is worth at a price
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(transparent)]
+pub struct Notional(pub Decimal);
+
+impl Notional {
+    pub const ZERO: Notional = Notional(Decimal::ZERO);
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn checked_add(self, other: Notional) -> Option<Notional> {
+        self.0.checked_add(other.0).map(Notional)
+    }
+
+    pub fn checked_sub(self, other: Notional) -> Option<Notional> {
+        self.0.checked_sub(other.0).map(Notional)
+    }
+}
+
+impl From<u64> for Price {
+    fn from(value: u64) -> Self {
+        Price(Decimal::from(value))
+    }
+}
+
+impl From<u64> for Quantity {
+    fn from(value: u64) -> Self {
+        Quantity(Decimal::from(value))
+    }
+}
+
+impl From<u64> for Notional {
+    fn from(value: u64) -> Self {
+        Notional(Decimal::from(value))
+    }
+}
+
+impl FromStr for Price {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Price)
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Quantity)
+    }
+}
+
+impl FromStr for Notional {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Notional)
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Notional {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// How an incoming order is allocated among the resting orders at a price level
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Allocation {
+    /// Price-time priority: the oldest order is filled first
+    #[default]
+    Fifo,
+    /// In proportion to what is left of each order, rounded down to the lot size
+    ///
+    /// Orders that would get less than `min_allocation` get nothing, and what rounding leaves over is filled oldest first
+    ProRata { min_allocation: Quantity },
+    /// The top order, which bettered the best price when it arrived, first, then the rest like [`Allocation::ProRata`]
+    TopOrder { min_allocation: Quantity },
+}
+
+/// A tradeable instrument, the decimal places its prices and quantities have and the rules its orders have to follow
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Instrument {
+    /// Short name, e.g. `OCTO`
+    pub symbol: String,
+    /// Decimal places of a price, e.g. 2 for cents
+    pub price_scale: u8,
+    /// Decimal places of a quantity, 0 for whole units
+    pub quantity_scale: u8,
+    /// Prices are multiples of it
+    pub tick_size: Price,
+    /// Quantities are multiples of it
+    pub lot_size: Quantity,
+    /// Smallest quantity of an order
+    pub min_quantity: Quantity,
+    /// Largest quantity of an order, if there is a limit
+    pub max_quantity: Option<Quantity>,
+    /// Smallest amount of currency an order may be worth at its limit price
+    pub min_notional: Notional,
+    /// How orders at the same price share a match
+    #[serde(default)]
+    pub allocation: Allocation,
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Instrument::new("OCTO", 2, 0)
+    }
+}
+
+impl Instrument {
+    /// An instrument that takes any price and quantity with its decimal places
+    pub fn new(symbol: &str, price_scale: u8, quantity_scale: u8) -> Self {
+        Instrument {
+            symbol: symbol.to_string(),
+            price_scale,
+            quantity_scale,
+            tick_size: Price(Decimal::new(1, price_scale)),
+            lot_size: Quantity(Decimal::new(1, quantity_scale)),
+            min_quantity: Quantity::ZERO,
+            max_quantity: None,
+            min_notional: Notional::ZERO,
+            allocation: Allocation::Fifo,
+        }
+    }
+
+    /// Checks that the rules can be met: positive tick and lot sizes within the scales, and a minimum quantity up to the maximum
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tick_size.0.is_zero() || self.tick_size.0.rescale(self.price_scale).is_none() {
+            return Err(format!(
+                "tick size {} must be greater than zero with at most {} decimal places",
+                self.tick_size, self.price_scale
+            ));
+        }
+        if self.lot_size.is_zero() || self.lot_size.0.rescale(self.quantity_scale).is_none() {
+            return Err(format!(
+                "lot size {} must be greater than zero with at most {} decimal places",
+                self.lot_size, self.quantity_scale
+            ));
+        }
+        match self.max_quantity {
+            Some(max) if max < self.min_quantity => Err(format!(
+                "maximum quantity {} is less than the minimum of {}",
+                max, self.min_quantity
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a normalized order against the instrument's rules, given what it is worth at its limit price.
+    ///
+    /// # Errors
+    /// - The price isn't a multiple of the tick size
+    /// - The amount isn't a multiple of the lot size or out of the quantity limits
+    /// - The order is worth less than the minimum notional
+    pub fn check(&self, order: &Order, notional: Notional) -> Result<(), ApplicationError> {
+        if !order.price.0.is_multiple_of(self.tick_size.0) {
+            return Err(ApplicationError::PriceOffTick(order.price, self.tick_size));
+        }
+        if !order.amount.0.is_multiple_of(self.lot_size.0) {
+            return Err(ApplicationError::QuantityOffLot(
+                order.amount,
+                self.lot_size,
+            ));
+        }
+        if order.amount < self.min_quantity {
+            return Err(ApplicationError::QuantityBelowMinimum(
+                order.amount,
+                self.min_quantity,
+            ));
+        }
+        if let Some(max) = self.max_quantity.filter(|max| order.amount > *max) {
+            return Err(ApplicationError::QuantityAboveMaximum(order.amount, max));
+        }
+        if notional < self.min_notional {
+            return Err(ApplicationError::NotionalBelowMinimum(
+                notional,
+                self.min_notional,
+            ));
+        }
+        Ok(())
+    }
+
+    /// The order with the instrument's decimal places. Nothing is rounded: an order with more decimal places is rejected.
+    ///
+    /// # Errors
+    /// The price or amount has more decimal places than the instrument, or is too large at its scale
+    pub fn normalize(&self, order: Order) -> Result<Order, ApplicationError> {
+        let price = order.price.0.rescale(self.price_scale).ok_or_else(|| {
+            ApplicationError::InvalidOrder(format!(
+                "price {} can't be given with {} decimal places",
+                order.price, self.price_scale
+            ))
+        })?;
+        let amount = order.amount.0.rescale(self.quantity_scale).ok_or_else(|| {
+            ApplicationError::InvalidOrder(format!(
+                "amount {} can't be given with {} decimal places",
+                order.amount, self.quantity_scale
+            ))
+        })?;
+        Ok(Order {
+            price: Price(price),
+            amount: Quantity(amount),
+            ..order
+        })
+    }
+}
+
+/// Whether orders are matched. The states are ordered from the least to the most restrictive.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TradingState {
+    /// Continuous trading
+    #[default]
+    Open,
+    /// Orders are collected, but not matched continuously
+    AuctionOnly,
+    /// Trading is interrupted, e.g. by a circuit breaker
+    Halted,
+    /// No orders are accepted
+    Closed,
+}
+
+/// The trading state of the market or an instrument
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TradingStatus {
+    pub state: TradingState,
+    /// Why the market or instrument is in this state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// When trading opens again by itself, in milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<u64>,
+}
+
+impl TradingStatus {
+    /// The state at `now`, which is [`TradingState::Open`] once `until` has passed
+    pub fn state_at(&self, now: u64) -> TradingState {
+        match self.until {
+            Some(until) if until <= now => TradingState::Open,
+            _ => self.state,
+        }
+    }
+}
+
+/// Trading states of an instrument
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct InstrumentStatus {
+    pub symbol: String,
+    /// The instrument's own state
+    pub status: TradingStatus,
+    /// The state orders are treated by: the more restrictive of the market's and the instrument's
+    pub state: TradingState,
+    /// Orders waiting for trading to open
+    pub queued: usize,
+    /// The orders waiting for trading to open, oldest first
+    #[serde(default)]
+    pub queued_orders: Vec<PartialOrder>,
+}
+
+/// Trading states of the market and its instruments
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MarketStatus {
+    /// The state of the market as a whole
+    pub market: TradingStatus,
+    pub instruments: Vec<InstrumentStatus>,
+}
+
+/// The indicative outcome of an auction: where and how much it would execute if the call phase ended now
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AuctionStatus {
+    pub symbol: String,
+    /// The state orders are treated by, [`TradingState::AuctionOnly`] during the call phase
+    pub state: TradingState,
+    /// The clearing price, none unless bids and asks cross
+    pub price: Option<Price>,
+    /// Units that would execute at the price
+    pub volume: Quantity,
+    /// Units of the side with more interest that would be left over
+    pub surplus: Quantity,
+    /// The side with more interest, if the sides aren't even
+    pub surplus_side: Option<Side>,
+}
+
+/// A trading state set by operations
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TradingStateRequest {
+    pub state: TradingState,
+    /// Why, shown with the state and recorded in the audit log
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Pre-trade limits of an account, those that aren't set fall back to the platform's defaults
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RiskLimits {
+    /// Largest quantity of a single order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_order_quantity: Option<Quantity>,
+    /// Most a single order may be worth at its limit price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_notional: Option<Notional>,
+    /// Most orders that may rest in the book at the same time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_open_orders: Option<usize>,
+    /// Most the account may trade per UTC day, including the order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_daily_volume: Option<Notional>,
+    /// How far, in basis points, a price may be from the last trade, or the mid of the book before the first trade
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_collar_bps: Option<u32>,
+}
+
+impl RiskLimits {
+    /// These limits, with the ones that aren't set taken from `defaults`
+    pub fn or(&self, defaults: &RiskLimits) -> RiskLimits {
+        RiskLimits {
+            max_order_quantity: self.max_order_quantity.or(defaults.max_order_quantity),
+            max_notional: self.max_notional.or(defaults.max_notional),
+            max_open_orders: self.max_open_orders.or(defaults.max_open_orders),
+            max_daily_volume: self.max_daily_volume.or(defaults.max_daily_volume),
+            price_collar_bps: self.price_collar_bps.or(defaults.price_collar_bps),
+        }
+    }
+}
+
 /// An order for a specified symbol to buy or sell an amount at a given price.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct Order {
     /// Max/min price (depending on the side)
-    pub price: u64,
+    pub price: Price,
     /// Number of units to trade
-    pub amount: u64,
+    pub amount: Quantity,
     /// The side of the order book (buy or sell)
     pub side: Side,
     /// The account signer
@@ -25,8 +488,28 @@ pub struct Order {
 }
 
 impl Order {
+    /// Checks what every order needs to be tradeable: a price and an amount greater than zero
+    pub fn validate(&self) -> Result<(), ApplicationError> {
+        if self.price.0.is_zero() {
+            return Err(ApplicationError::InvalidOrder(
+                "price must be greater than zero".to_string(),
+            ));
+        }
+        if self.amount.is_zero() {
+            return Err(ApplicationError::InvalidOrder(
+                "amount must be greater than zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// What the order is worth at its limit price with `scale` decimal places, see [`Price::notional`]
+    pub fn notional(&self, scale: u8) -> Option<Notional> {
+        self.price.notional(self.amount, scale)
+    }
+
     /// Convert an [`Order`] into a [`PartialOrder`] with the added parameters
-    pub fn into_partial_order(self, ordinal: u64, remaining: u64) -> PartialOrder {
+    pub fn into_partial_order(self, ordinal: u64, remaining: Quantity) -> PartialOrder {
         let Order {
             price,
             amount,
@@ -45,14 +528,14 @@ impl Order {
 }
 
 /// A position represents an unfilled order that is kept in the system for later filling.
-#[derive(Clone, PartialEq, Debug, Eq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Debug, Eq, Serialize, Deserialize, ToSchema)]
 pub struct PartialOrder {
     /// Price per unit
-    pub price: u64,
+    pub price: Price,
     /// Initial number of units in the order
-    pub amount: u64,
+    pub amount: Quantity,
     /// Remaining number of units after potential matches
-    pub remaining: u64,
+    pub remaining: Quantity,
     /// Buy or sell side of the book
     pub side: Side,
     /// Signer of the order
@@ -63,8 +546,7 @@ pub struct PartialOrder {
 
 impl PartialOrd for PartialOrder {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        // this reverses the comparison to create a min heap
-        Reverse(self.ordinal).partial_cmp(&Reverse(other.ordinal))
+        Some(self.cmp(other))
     }
 }
 
@@ -76,18 +558,56 @@ impl Ord for PartialOrder {
 }
 
 /// A receipt issued to the caller for accepting an [`Order`]
-#[derive(Clone, PartialOrd, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialOrd, PartialEq, Eq, Debug, Serialize, Deserialize, ToSchema)]
 pub struct Receipt {
     /// Sequence number
     pub ordinal: u64,
 
     /// Matches that happened immediately
     pub matches: Vec<PartialOrder>,
+
+    /// The fees charged for each of the `matches`, in the same order
+    #[serde(default)]
+    pub fees: Vec<Fee>,
+
+    /// The order waits for trading to open, see the audit log for what became of it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub queued: bool,
+}
+
+/// The trading fees charged for one match, in basis points of its notional
+#[derive(Clone, Copy, PartialOrd, PartialEq, Eq, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Fee {
+    /// Charged to the signer of the resting order
+    pub maker: Notional,
+    /// Charged to the signer of the incoming order
+    pub taker: Notional,
+    /// The maker's rate
+    pub maker_bps: u32,
+    /// The taker's rate
+    pub taker_bps: u32,
+}
+
+/// A match between an incoming order and a resting one
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Trade {
+    /// Position in the sequence of all trades, starting at 1
+    pub id: u64,
+    /// Ordinal of the incoming order
+    pub ordinal: u64,
+    /// Ordinal of the resting order
+    pub maker_ordinal: u64,
+    /// Price per unit, the resting order's price
+    pub price: Price,
+    /// Units traded
+    pub amount: Quantity,
+    /// Side of the incoming order
+    pub side: Side,
 }
 
 impl PartialOrder {
     /// Splits one [`PartialOrder`] into two by taking a defined `take` amount
-    pub fn take_from(pos: &mut PartialOrder, take: u64, price: u64) -> PartialOrder {
+    pub fn take_from(pos: &mut PartialOrder, take: Quantity, price: Price) -> PartialOrder {
         pos.remaining -= take;
         let mut new = pos.clone();
         new.amount = take;
@@ -96,20 +616,148 @@ impl PartialOrder {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Legacy request body of `/account/deposit` and `/account/withdraw`
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct AccountUpdateRequest {
     pub signer: String,
-    pub amount: u64,
+    pub amount: Notional,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Legacy request body of `/balance`
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct AccountBalanceRequest {
     pub signer: String,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Transfer of currency between two accounts
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct SendRequest {
     pub sender: String,
     pub recipient: String,
-    pub amount: u64,
+    pub amount: Notional,
+}
+
+/// Currency to deposit into or withdraw from the account given in the path
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AmountRequest {
+    pub amount: Notional,
+}
+
+/// The current balance of an account
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AccountBalance {
+    pub signer: String,
+    pub balance: Notional,
+}
+
+/// Whether an account may place orders, and the orders that were cancelled when it was disabled
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AccountStatus {
+    pub signer: String,
+    pub disabled: bool,
+    /// The resting orders taken out of the book by this request
+    pub cancelled: Vec<PartialOrder>,
+}
+
+/// The body of every error response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    /// A stable, machine-readable error code, e.g. `account_not_found`
+    pub code: String,
+    /// A human-readable description
+    pub message: String,
+}
+
+/// One page of a listing. Pass `next` as the `after` parameter to fetch the following page.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[aliases(
+    TxLogPage = Page<LedgerEntry, u64>,
+    AccountsPage = Page<AccountBalance, String>,
+    OrderbookPage = Page<PartialOrder, u64>,
+    TradesPage = Page<Trade, u64>,
+    AuditPage = Page<AuditEntry, u64>
+)]
+pub struct Page<T, C> {
+    /// The items of this page
+    pub items: Vec<T>,
+    /// Cursor of the next page, absent on the last page
+    pub next: Option<C>,
+}
+
+/// Filters and cursor for the transaction log
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TxLogQuery {
+    /// Maximum number of entries to return
+    pub limit: Option<usize>,
+    /// Only return entries with a greater `id`
+    pub after: Option<u64>,
+    /// Only return transactions of this account
+    pub account: Option<String>,
+    /// Only return transactions of this kind
+    pub kind: Option<TxKind>,
+    /// Only return entries recorded at or after this time (ms since the Unix epoch)
+    pub from: Option<u64>,
+    /// Only return entries recorded before this time (ms since the Unix epoch)
+    pub to: Option<u64>,
+}
+
+/// Filters and cursor for the account listing
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AccountsQuery {
+    /// Maximum number of accounts to return
+    pub limit: Option<usize>,
+    /// Only return accounts whose signer sorts after this one
+    pub after: Option<String>,
+    /// Only return accounts whose signer starts with this prefix
+    pub prefix: Option<String>,
+    /// Only return accounts with at least this balance
+    pub min_balance: Option<Notional>,
+}
+
+/// Filters and cursor for the order book
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderbookQuery {
+    /// Maximum number of orders to return
+    pub limit: Option<usize>,
+    /// Only return orders with a greater ordinal
+    pub after: Option<u64>,
+    /// Only return orders on this side of the book
+    pub side: Option<Side>,
+    /// Only return orders of this signer
+    pub signer: Option<String>,
+}
+
+/// Cursor for the trades, which are listed newest first
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TradesQuery {
+    /// Maximum number of trades to return
+    pub limit: Option<usize>,
+    /// Only return trades with a smaller `id`
+    pub before: Option<u64>,
+}
+
+/// Which resting orders of an account to cancel at once
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CancelOrdersQuery {
+    /// Cancel the orders of this signer
+    pub signer: String,
+    /// Only cancel orders on this side of the book
+    pub side: Option<Side>,
+}
+
+/// Filters and cursor for the audit log
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// Maximum number of entries to return
+    pub limit: Option<usize>,
+    /// Only return entries with a greater `id`
+    pub after: Option<u64>,
+    /// Only return actions taken on this account
+    pub account: Option<String>,
 }
@@ -1,5 +1,9 @@
+use std::fmt;
+
 use warp::reject::Reject;
 
+use crate::core::types::{Notional, Price, Quantity};
+
 /// An application-specific error type
 #[derive(Debug, PartialEq, Eq)]
 pub enum ApplicationError {
@@ -7,10 +11,191 @@ pub enum ApplicationError {
     AccountNotFound(String),
 
     /// Not enough currency in the account (underflow)
-    AccountUnderFunded(String, u64),
+    AccountUnderFunded(String, Notional),
 
     /// Too much currency in the account (overflow)
-    AccountOverFunded(String, u64),
+    AccountOverFunded(String, Notional),
+
+    /// No resting order with this ordinal
+    OrderNotFound(u64),
+
+    /// The account was disabled and may not place orders
+    AccountDisabled(String),
+
+    /// No instrument with this symbol is traded
+    InstrumentNotFound(String),
+
+    /// Trading of the instrument is halted and orders are rejected meanwhile
+    TradingHalted(String),
+
+    /// The market or the instrument is closed
+    TradingClosed(String),
+
+    /// The order is worth more than any amount of currency there can be
+    NotionalOverflow(Price, Quantity),
+
+    /// The order can't be traded, e.g. because its price is zero
+    InvalidOrder(String),
+
+    /// The amount of currency has more decimal places than balances
+    InvalidAmount(String),
+
+    /// The price isn't a multiple of the instrument's tick size
+    PriceOffTick(Price, Price),
+
+    /// The quantity isn't a multiple of the instrument's lot size
+    QuantityOffLot(Quantity, Quantity),
+
+    /// The quantity is less than the instrument's minimum
+    QuantityBelowMinimum(Quantity, Quantity),
+
+    /// The quantity is more than the instrument's maximum
+    QuantityAboveMaximum(Quantity, Quantity),
+
+    /// The order is worth less than the instrument's minimum notional
+    NotionalBelowMinimum(Notional, Notional),
+
+    /// The order's quantity is above the account's limit
+    OrderQuantityLimit(Quantity, Quantity),
+
+    /// The order is worth more than the account's limit
+    OrderNotionalLimit(Notional, Notional),
+
+    /// The account has as many resting orders as it may have
+    OpenOrdersLimit(String, usize),
+
+    /// The order would take the account's volume of the day above its limit
+    DailyVolumeLimit(String, Notional),
+
+    /// The price is outside of the collar around the reference price
+    PriceOutsideCollar(Price, Price, Price),
+}
+
+impl ApplicationError {
+    /// A short, stable identifier for the kind of error, e.g. for metric labels
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApplicationError::AccountNotFound(_) => "account_not_found",
+            ApplicationError::AccountUnderFunded(_, _) => "account_under_funded",
+            ApplicationError::AccountOverFunded(_, _) => "account_over_funded",
+            ApplicationError::OrderNotFound(_) => "order_not_found",
+            ApplicationError::AccountDisabled(_) => "account_disabled",
+            ApplicationError::InstrumentNotFound(_) => "instrument_not_found",
+            ApplicationError::TradingHalted(_) => "trading_halted",
+            ApplicationError::TradingClosed(_) => "trading_closed",
+            ApplicationError::NotionalOverflow(_, _) => "notional_overflow",
+            ApplicationError::InvalidOrder(_) => "invalid_order",
+            ApplicationError::InvalidAmount(_) => "invalid_amount",
+            ApplicationError::PriceOffTick(_, _) => "price_off_tick",
+            ApplicationError::QuantityOffLot(_, _) => "quantity_off_lot",
+            ApplicationError::QuantityBelowMinimum(_, _) => "quantity_below_minimum",
+            ApplicationError::QuantityAboveMaximum(_, _) => "quantity_above_maximum",
+            ApplicationError::NotionalBelowMinimum(_, _) => "notional_below_minimum",
+            ApplicationError::OrderQuantityLimit(_, _) => "order_quantity_limit",
+            ApplicationError::OrderNotionalLimit(_, _) => "order_notional_limit",
+            ApplicationError::OpenOrdersLimit(_, _) => "open_orders_limit",
+            ApplicationError::DailyVolumeLimit(_, _) => "daily_volume_limit",
+            ApplicationError::PriceOutsideCollar(_, _, _) => "price_outside_collar",
+        }
+    }
+}
+
+impl fmt::Display for ApplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplicationError::AccountNotFound(signer) => {
+                write!(f, "account '{}' not found", signer)
+            }
+            ApplicationError::AccountUnderFunded(signer, amount) => {
+                write!(f, "account '{}' has less than {}", signer, amount)
+            }
+            ApplicationError::AccountOverFunded(signer, amount) => {
+                write!(
+                    f,
+                    "adding {} to account '{}' would overflow",
+                    amount, signer
+                )
+            }
+            ApplicationError::OrderNotFound(ordinal) => {
+                write!(f, "no resting order with ordinal {}", ordinal)
+            }
+            ApplicationError::AccountDisabled(signer) => {
+                write!(f, "account '{}' is disabled", signer)
+            }
+            ApplicationError::InstrumentNotFound(symbol) => {
+                write!(f, "instrument '{}' not found", symbol)
+            }
+            ApplicationError::TradingHalted(symbol) => {
+                write!(f, "trading of '{}' is halted", symbol)
+            }
+            ApplicationError::TradingClosed(symbol) => {
+                write!(f, "trading of '{}' is closed", symbol)
+            }
+            ApplicationError::NotionalOverflow(price, amount) => {
+                write!(
+                    f,
+                    "{} units at {} are worth more than an account can hold",
+                    amount, price
+                )
+            }
+            ApplicationError::InvalidOrder(reason) => write!(f, "invalid order: {}", reason),
+            ApplicationError::InvalidAmount(reason) => write!(f, "invalid amount: {}", reason),
+            ApplicationError::PriceOffTick(price, tick_size) => {
+                write!(
+                    f,
+                    "price {} is not a multiple of the tick size {}",
+                    price, tick_size
+                )
+            }
+            ApplicationError::QuantityOffLot(amount, lot_size) => {
+                write!(
+                    f,
+                    "amount {} is not a multiple of the lot size {}",
+                    amount, lot_size
+                )
+            }
+            ApplicationError::QuantityBelowMinimum(amount, min) => {
+                write!(f, "amount {} is less than the minimum of {}", amount, min)
+            }
+            ApplicationError::QuantityAboveMaximum(amount, max) => {
+                write!(f, "amount {} is more than the maximum of {}", amount, max)
+            }
+            ApplicationError::NotionalBelowMinimum(notional, min) => {
+                write!(
+                    f,
+                    "the order is worth {}, less than the minimum of {}",
+                    notional, min
+                )
+            }
+            ApplicationError::OrderQuantityLimit(amount, max) => {
+                write!(
+                    f,
+                    "amount {} is above the account's limit of {}",
+                    amount, max
+                )
+            }
+            ApplicationError::OrderNotionalLimit(notional, max) => {
+                write!(
+                    f,
+                    "the order is worth {}, above the account's limit of {}",
+                    notional, max
+                )
+            }
+            ApplicationError::OpenOrdersLimit(signer, max) => {
+                write!(f, "account '{}' already has {} resting orders", signer, max)
+            }
+            ApplicationError::DailyVolumeLimit(signer, max) => {
+                write!(f, "account '{}' may trade {} per day at most", signer, max)
+            }
+            ApplicationError::PriceOutsideCollar(price, low, high) => {
+                write!(
+                    f,
+                    "price {} is outside of the collar from {} to {}",
+                    price, low, high
+                )
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+pub mod audit;
 pub mod core;
 pub mod errors;
 pub mod tx;
@@ -1,12 +1,61 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::core::types::Notional;
 
 /// A transaction type. Transactions should be able to rebuild a ledger's state
 /// when they are applied in the same sequence to an empty state.
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, ToSchema)]
 pub enum Tx {
     /// Currency was added to the account
-    Deposit { account: String, amount: u64 },
+    Deposit { account: String, amount: Notional },
 
     /// Currency was withdrawn from the account
-    Withdraw { account: String, amount: u64 },
+    Withdraw { account: String, amount: Notional },
+
+    /// A trading fee was moved from the account to the platform's fee account
+    Fee {
+        account: String,
+        fee_account: String,
+        amount: Notional,
+    },
+}
+
+impl Tx {
+    /// The account affected by the transaction
+    pub fn account(&self) -> &str {
+        match self {
+            Tx::Deposit { account, .. }
+            | Tx::Withdraw { account, .. }
+            | Tx::Fee { account, .. } => account,
+        }
+    }
+
+    /// The kind of transaction, without its data
+    pub fn kind(&self) -> TxKind {
+        match self {
+            Tx::Deposit { .. } => TxKind::Deposit,
+            Tx::Withdraw { .. } => TxKind::Withdraw,
+            Tx::Fee { .. } => TxKind::Fee,
+        }
+    }
+}
+
+/// The variants of [`Tx`], e.g. for filtering
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TxKind {
+    Deposit,
+    Withdraw,
+    Fee,
+}
+
+/// A [`Tx`] as it was recorded in the transaction log
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LedgerEntry {
+    /// Position in the log, starting at 1
+    pub id: u64,
+    /// When the transaction was applied, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub tx: Tx,
 }
@@ -0,0 +1,151 @@
+//! Benchmarks of the [`MatchingEngine`] hot paths.
+//!
+//! Each iteration leaves the book as it found it, so its size is what the benchmark says.
+
+// Most benchmarks process an order and then one that undoes it. The history is cleared every iteration,
+// so only pushing a receipt onto it is measured.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use octopus_common::core::types::{Order, Price, Quantity, Side};
+use octopus_web::core::matching::MatchingEngine;
+
+/// Book sizes to measure, in orders or price levels
+const SIZES: [u64; 3] = [10, 1_000, 10_000];
+
+fn order(signer: &str, side: Side, price: u64, amount: u64) -> Order {
+    Order {
+        price: Price::from(price),
+        amount: Quantity::from(amount),
+        side,
+        signer: signer.to_string(),
+    }
+}
+
+/// `levels` bids below 10_000 and `levels` asks above it, one order per level
+fn book_with_levels(levels: u64) -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    for i in 1..=levels {
+        engine
+            .process(order("MAKER", Side::Buy, 10_000 - i, 1))
+            .unwrap();
+        engine
+            .process(order("MAKER", Side::Sell, 10_000 + i, 1))
+            .unwrap();
+    }
+    engine.history.clear();
+    engine
+}
+
+/// `depth` sell orders of different makers at a single price
+fn book_with_depth(depth: u64) -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    for i in 0..depth {
+        engine
+            .process(order(&format!("MAKER-{}", i % 100), Side::Sell, 100, 1))
+            .unwrap();
+    }
+    engine.history.clear();
+    engine
+}
+
+/// An order that rests inside the spread and one that fills it, with many price levels on both sides
+fn many_levels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("many_levels");
+    for levels in SIZES {
+        let mut engine = book_with_levels(levels);
+        group.bench_with_input(BenchmarkId::from_parameter(levels), &levels, |b, _| {
+            b.iter(|| {
+                engine
+                    .process(order("ALICE", Side::Buy, 10_000, 1))
+                    .unwrap();
+                engine.process(order("BOB", Side::Sell, 10_000, 1)).unwrap();
+                engine.history.clear();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// A taker that fills the oldest order of a deep price level, and a maker that replaces it
+fn deep_book(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_book");
+    for depth in SIZES {
+        let mut engine = book_with_depth(depth);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, _| {
+            b.iter(|| {
+                engine.process(order("TAKER", Side::Buy, 100, 1)).unwrap();
+                engine
+                    .process(order("MAKER-0", Side::Sell, 100, 1))
+                    .unwrap();
+                engine.history.clear();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// A taker that clears every ask level at once
+fn sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sweep");
+    for levels in SIZES {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(levels),
+            &levels,
+            |b, &levels| {
+                b.iter_batched(
+                    || book_with_levels(levels),
+                    |mut engine| {
+                        engine
+                            .process(order("TAKER", Side::Buy, u64::MAX, levels))
+                            .unwrap()
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// A taker whose own orders are ahead of the only order it may match at a price level
+fn self_match(c: &mut Criterion) {
+    let mut group = c.benchmark_group("self_match");
+    for depth in SIZES {
+        let mut engine = MatchingEngine::new();
+        for _ in 0..depth {
+            engine.process(order("ALICE", Side::Sell, 100, 1)).unwrap();
+        }
+        engine.process(order("BOB", Side::Sell, 100, 1)).unwrap();
+        engine.history.clear();
+
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, _| {
+            b.iter(|| {
+                engine.process(order("ALICE", Side::Buy, 100, 1)).unwrap();
+                engine.process(order("BOB", Side::Sell, 100, 1)).unwrap();
+                engine.history.clear();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Cancelling the newest order of a book with many price levels, and placing it again
+fn cancel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cancel");
+    for levels in SIZES {
+        let mut engine = book_with_levels(levels);
+        group.bench_with_input(BenchmarkId::from_parameter(levels), &levels, |b, _| {
+            b.iter(|| {
+                let receipt = engine
+                    .process(order("ALICE", Side::Buy, 10_000, 1))
+                    .unwrap();
+                engine.cancel(receipt.ordinal).unwrap();
+                engine.history.clear();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, many_levels, deep_book, sweep, self_match, cancel);
+criterion_main!(benches);
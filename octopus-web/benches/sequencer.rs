@@ -0,0 +1,135 @@
+//! Throughput of concurrent clients on a shared [`TradingPlatform`], through the [`Sequencer`] and through a mutex
+//!
+//! Each client places and cancels an order, or every tenth reads a page of the order book, which sorts it.
+
+// The book holds `size` resting orders besides the clients' ones
+
+use std::{future, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use octopus_common::core::types::{Notional, Order, OrderbookQuery, Price, Quantity, Side};
+use octopus_web::{sequencer::Sequencer, trading_platform::TradingPlatform};
+use tokio::{runtime::Runtime, sync::Mutex};
+
+/// Resting orders in the book
+const SIZES: [u64; 2] = [100, 10_000];
+/// Clients sending requests at the same time
+const CLIENTS: u64 = 64;
+/// Requests of each client per iteration
+const REQUESTS: u64 = 10;
+
+fn order(signer: &str, side: Side, price: u64) -> Order {
+    Order {
+        price: Price::from(price),
+        amount: Quantity::from(1),
+        side,
+        signer: signer.to_string(),
+    }
+}
+
+fn platform(size: u64) -> TradingPlatform {
+    let mut platform = TradingPlatform::new();
+    // Balances have two decimal places, so the largest deposit is a hundredth of u64::MAX
+    platform
+        .deposit("MAKER", Notional::from(u64::MAX / 1_000))
+        .unwrap();
+    for i in 0..CLIENTS {
+        platform
+            .deposit(&format!("CLIENT-{}", i), Notional::from(1_000_000))
+            .unwrap();
+    }
+    for i in 0..size {
+        platform
+            .order(order("MAKER", Side::Sell, 1_000 + i % 100))
+            .unwrap();
+    }
+    platform
+}
+
+fn query() -> OrderbookQuery {
+    OrderbookQuery {
+        limit: Some(100),
+        ..Default::default()
+    }
+}
+
+async fn mutex_client(platform: Arc<Mutex<TradingPlatform>>, client: u64) {
+    let signer = format!("CLIENT-{}", client);
+    for _ in 0..REQUESTS {
+        if client.is_multiple_of(10) {
+            let platform = platform.lock().await;
+            criterion::black_box(platform.query_orderbook(&query()));
+        } else {
+            let receipt = platform
+                .lock()
+                .await
+                .order(order(&signer, Side::Buy, 10))
+                .unwrap();
+            platform.lock().await.cancel(receipt.ordinal).unwrap();
+        }
+    }
+}
+
+async fn sequencer_client(sequencer: Sequencer, client: u64) {
+    let signer = format!("CLIENT-{}", client);
+    for _ in 0..REQUESTS {
+        if client.is_multiple_of(10) {
+            let platform = sequencer.read().await;
+            criterion::black_box(platform.query_orderbook(&query()));
+        } else {
+            let signer = signer.clone();
+            let receipt = sequencer
+                .submit(move |platform| platform.order(order(&signer, Side::Buy, 10)))
+                .await
+                .unwrap()
+                .unwrap();
+            sequencer
+                .submit(move |platform| platform.cancel(receipt.ordinal))
+                .await
+                .unwrap()
+                .unwrap();
+        }
+    }
+}
+
+fn concurrent_clients(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("concurrent_clients");
+    group.throughput(Throughput::Elements(CLIENTS * REQUESTS));
+
+    for size in SIZES {
+        let locked = Arc::new(Mutex::new(platform(size)));
+        group.bench_with_input(BenchmarkId::new("mutex", size), &size, |b, _| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    let clients: Vec<_> = (0..CLIENTS)
+                        .map(|client| tokio::spawn(mutex_client(locked.clone(), client)))
+                        .collect();
+                    for client in clients {
+                        client.await.unwrap();
+                    }
+                })
+            })
+        });
+
+        // Every client has at most one request in the queue
+        let (sequencer, _) = runtime
+            .block_on(async { Sequencer::start(CLIENTS as usize, future::ready(platform(size))) });
+        group.bench_with_input(BenchmarkId::new("sequencer", size), &size, |b, _| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    let clients: Vec<_> = (0..CLIENTS)
+                        .map(|client| tokio::spawn(sequencer_client(sequencer.clone(), client)))
+                        .collect();
+                    for client in clients {
+                        client.await.unwrap();
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_clients);
+criterion_main!(benches);
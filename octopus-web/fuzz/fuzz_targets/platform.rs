@@ -0,0 +1,128 @@
+//! Arbitrary streams of operations on a [`TradingPlatform`] never panic or overflow, and keep currency conserved.
+
+// The operations are deposits, withdrawals, transfers, orders and cancels, so currency may only come and go
+// through deposits and withdrawals.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use octopus_common::core::{
+    decimal::Decimal,
+    types::{Instrument, Notional, Order, Price, Quantity, Side},
+};
+use octopus_web::trading_platform::TradingPlatform;
+
+const ACCOUNTS: [&str; 4] = ["ALICE", "BOB", "CHARLIE", "DAVE"];
+/// Notionals get more decimal places than balances, so that settlements are rounded
+const CURRENCY_SCALE: u8 = 2;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Deposit {
+        account: u8,
+        amount: u64,
+    },
+    Withdraw {
+        account: u8,
+        amount: u64,
+    },
+    Send {
+        sender: u8,
+        recipient: u8,
+        amount: u64,
+    },
+    Order {
+        account: u8,
+        buy: bool,
+        price: u64,
+        amount: u64,
+    },
+    Cancel {
+        ordinal: u64,
+    },
+}
+
+fn account(index: u8) -> &'static str {
+    ACCOUNTS[index as usize % ACCOUNTS.len()]
+}
+
+/// Amounts of currency, prices and quantities are given in units of their last decimal place
+fn currency(units: u64) -> Notional {
+    Notional(Decimal::new(units, CURRENCY_SCALE))
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let instrument = Instrument::new("OCTO", 2, 1);
+    let mut platform = TradingPlatform::new()
+        .with_instrument(instrument.clone(), CURRENCY_SCALE)
+        .with_fees("0:10:20,1000:5:10".parse().unwrap(), "FEES");
+    let (mut deposited, mut withdrawn) = (0u128, 0u128);
+
+    for op in ops {
+        match op {
+            Op::Deposit { account: a, amount } => {
+                if platform.deposit(account(a), currency(amount)).is_ok() {
+                    deposited += amount as u128;
+                }
+            }
+            Op::Withdraw { account: a, amount } => {
+                if platform.withdraw(account(a), currency(amount)).is_ok() {
+                    withdrawn += amount as u128;
+                }
+            }
+            Op::Send {
+                sender,
+                recipient,
+                amount,
+            } => {
+                let _ = platform.send(account(sender), account(recipient), currency(amount));
+            }
+            Op::Order {
+                account: a,
+                buy,
+                price,
+                amount,
+            } => {
+                let side = if buy { Side::Buy } else { Side::Sell };
+                let (price, amount) = (
+                    Price(Decimal::new(price, instrument.price_scale)),
+                    Quantity(Decimal::new(amount, instrument.quantity_scale)),
+                );
+                let order = Order {
+                    price,
+                    amount,
+                    side: side.clone(),
+                    signer: account(a).to_string(),
+                };
+                if let Ok(receipt) = platform.order(order) {
+                    let filled = receipt
+                        .matches
+                        .iter()
+                        .try_fold(Quantity::ZERO, |filled, m| filled.checked_add(m.amount))
+                        .expect("fills add up to at most the order");
+                    assert!(filled <= amount);
+                    for m in &receipt.matches {
+                        match side {
+                            Side::Buy => assert!(m.price <= price),
+                            Side::Sell => assert!(m.price >= price),
+                        }
+                    }
+                }
+            }
+            Op::Cancel { ordinal } => {
+                let _ = platform.cancel(ordinal);
+            }
+        }
+
+        let total: u128 = platform
+            .accounts()
+            .iter()
+            .map(|(_, balance)| {
+                let units = balance.0.rescale(CURRENCY_SCALE);
+                units.expect("balances have two decimal places").units() as u128
+            })
+            .sum();
+        assert_eq!(total, deposited - withdrawn);
+    }
+});
@@ -0,0 +1,86 @@
+//! Arbitrary bodies posted to the JSON routes of [`filters::routes`] get a success or a client error, never a 5xx.
+
+#![no_main]
+
+use std::{
+    future,
+    sync::{atomic::AtomicBool, Arc, OnceLock},
+};
+
+use libfuzzer_sys::fuzz_target;
+use octopus_common::core::types::{Order, Price, Quantity, Side};
+use octopus_web::{
+    filters, metrics::Metrics, sequencer::Sequencer, trading_platform::TradingPlatform,
+};
+use tokio::runtime::Runtime;
+
+/// The routes that take a JSON body, picked by the first byte of the input
+const ROUTES: [&str; 9] = [
+    "/v1/accounts/ALICE/deposit",
+    "/v1/accounts/ALICE/withdraw",
+    "/v1/transfers",
+    "/v1/orders",
+    "/account/deposit",
+    "/account/withdraw",
+    "/account/send",
+    "/order",
+    "/balance",
+];
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    })
+}
+
+/// Funded accounts and a resting order on each side, so that orders can match
+fn platform() -> TradingPlatform {
+    let mut platform = TradingPlatform::new();
+    for signer in ["ALICE", "BOB"] {
+        platform.deposit(signer, 1_000_000.into()).unwrap();
+    }
+    for (side, price) in [(Side::Buy, 90), (Side::Sell, 110)] {
+        platform
+            .order(Order {
+                price: Price::from(price),
+                amount: Quantity::from(10),
+                side,
+                signer: "BOB".to_string(),
+            })
+            .unwrap();
+    }
+    platform
+}
+
+fuzz_target!(|input: (u8, &[u8])| {
+    let (route, body) = input;
+    let path = ROUTES[route as usize % ROUTES.len()];
+
+    runtime().block_on(async {
+        let (sequencer, _) = Sequencer::start(16, future::ready(platform()));
+        let routes = filters::routes(
+            sequencer,
+            Arc::new(Metrics::new()),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let res = warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("content-type", "application/json")
+            .body(body.to_vec())
+            .reply(&routes)
+            .await;
+        assert!(
+            !res.status().is_server_error(),
+            "{} answered {} to {:?}: {:?}",
+            path,
+            res.status(),
+            String::from_utf8_lossy(body),
+            res.body()
+        );
+    });
+});
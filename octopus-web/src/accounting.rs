@@ -1,11 +1,11 @@
-use octopus_common::{errors::ApplicationError, tx::Tx};
+use octopus_common::{core::types::Notional, errors::ApplicationError, tx::Tx};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// A type for managing accounts and their current currency balance
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Accounts {
-    accounts: HashMap<String, u64>,
+    accounts: HashMap<String, Notional>,
 }
 
 impl Accounts {
@@ -17,27 +17,45 @@ impl Accounts {
     }
 
     /// Retrieves the balance of an account
-    pub fn balance_of(&self, signer: &str) -> Result<&u64, ApplicationError> {
+    pub fn balance_of(&self, signer: &str) -> Result<&Notional, ApplicationError> {
         self.accounts
             .get(signer)
             .ok_or(ApplicationError::AccountNotFound(signer.to_string()))
     }
 
     /// Retrieves the list of all accounts
-    pub fn accounts(&self) -> &HashMap<String, u64> {
+    pub fn accounts(&self) -> &HashMap<String, Notional> {
         &self.accounts
     }
 
+    /// Gives every balance `scale` decimal places, e.g. after restoring balances that were kept with fewer.
+    /// # Errors
+    /// The first account whose balance can't be written with `scale` decimal places. No balance is changed then.
+    pub fn rescale(&mut self, scale: u8) -> Result<(), String> {
+        let rescaled = self
+            .accounts
+            .iter()
+            .map(|(signer, balance)| {
+                balance
+                    .0
+                    .rescale(scale)
+                    .map(|balance| (signer.clone(), Notional(balance)))
+                    .ok_or_else(|| signer.clone())
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        self.accounts = rescaled;
+        Ok(())
+    }
+
     /// Either deposits the `amount` provided into the `signer` account or adds the amount to the existing account.
     /// # Errors
     /// Attempted overflow
-    pub fn deposit(&mut self, signer: &str, amount: u64) -> Result<Tx, ApplicationError> {
+    pub fn deposit(&mut self, signer: &str, amount: Notional) -> Result<Tx, ApplicationError> {
         if let Some(account) = self.accounts.get_mut(signer) {
             (*account)
                 .checked_add(amount)
-                .map(|r| {
+                .inspect(|&r| {
                     *account = r;
-                    r
                 })
                 .ok_or(ApplicationError::AccountOverFunded(
                     signer.to_string(),
@@ -60,13 +78,12 @@ impl Accounts {
     /// Withdraws the `amount` from the `signer` account.
     /// # Errors
     /// Attempted overflow
-    pub fn withdraw(&mut self, signer: &str, amount: u64) -> Result<Tx, ApplicationError> {
+    pub fn withdraw(&mut self, signer: &str, amount: Notional) -> Result<Tx, ApplicationError> {
         if let Some(account) = self.accounts.get_mut(signer) {
             (*account)
                 .checked_sub(amount)
-                .map(|r| {
+                .inspect(|&r| {
                     *account = r;
-                    r
                 })
                 .ok_or(ApplicationError::AccountUnderFunded(
                     signer.to_string(),
@@ -90,7 +107,7 @@ impl Accounts {
         &mut self,
         sender: &str,
         recipient: &str,
-        amount: u64,
+        amount: Notional,
     ) -> Result<(Tx, Tx), ApplicationError> {
         if self.accounts.contains_key(sender)  // sender exists
             && self.accounts.contains_key(recipient) // recipient exists
@@ -104,10 +121,9 @@ impl Accounts {
             // if let Err(e) = my_func_call() { return Err(e); }
             let tx_withdraw = self.withdraw(sender, amount)?;
             self.deposit(recipient, amount)
-                .map_err(|e| {
+                .inspect_err(|_| {
                     // return the funds to the sender on error
                     self.deposit(sender, amount).unwrap();
-                    e
                 })
                 .map(|tx_deposit| (tx_withdraw, tx_deposit))
         } else if !self.accounts.contains_key(sender) {
@@ -116,22 +132,63 @@ impl Accounts {
             Err(ApplicationError::AccountNotFound(recipient.to_string()))
         }
     }
+
+    /// Moves a fee of `amount` from the `signer` account to the `fee_account`, which is opened if necessary.
+    ///
+    /// # Errors
+    /// The account doesn't exist or has less than `amount`
+    pub fn charge(
+        &mut self,
+        signer: &str,
+        fee_account: &str,
+        amount: Notional,
+    ) -> Result<Tx, ApplicationError> {
+        self.withdraw(signer, amount)?;
+        self.deposit(fee_account, amount)
+            .inspect_err(|_| {
+                // return the fee to the signer on error
+                self.deposit(signer, amount).unwrap();
+            })
+            .map(|_| Tx::Fee {
+                account: signer.to_string(),
+                fee_account: fee_account.to_string(),
+                amount,
+            })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn amount(value: u64) -> Notional {
+        Notional::from(value)
+    }
+
+    #[test]
+    fn test_accounts_rescale_keeps_balances_or_fails() {
+        let mut accounts = Accounts::new();
+        accounts.deposit("a-key", amount(42)).unwrap();
+        accounts.deposit("b-key", "0.5".parse().unwrap()).unwrap();
+
+        assert!(accounts.rescale(2).is_ok());
+        assert_eq!(accounts.balance_of("a-key").unwrap().to_string(), "42.00");
+        assert_eq!(accounts.balance_of("b-key").unwrap().to_string(), "0.50");
+
+        assert_eq!(accounts.rescale(0), Err("b-key".to_string()));
+        assert_eq!(accounts.balance_of("a-key").unwrap().to_string(), "42.00");
+    }
+
     #[test]
     fn test_accounts_withdraw_underfunded() {
         let mut accounts = Accounts::new();
-        accounts.deposit("a-key", 0).unwrap();
-        let actual = accounts.withdraw("a-key", 100);
+        accounts.deposit("a-key", amount(0)).unwrap();
+        let actual = accounts.withdraw("a-key", amount(100));
         assert_eq!(
             actual,
             Err(ApplicationError::AccountUnderFunded(
                 "a-key".to_string(),
-                100
+                amount(100)
             ))
         );
     }
@@ -140,14 +197,14 @@ mod tests {
     fn test_accounts_deposit_overfunded() {
         let mut accounts = Accounts::new();
         accounts
-            .deposit("a-key", 1)
+            .deposit("a-key", amount(1))
             .expect("Initial deposit failed");
-        let actual = accounts.deposit("a-key", u64::MAX);
+        let actual = accounts.deposit("a-key", amount(u64::MAX));
         assert_eq!(
             actual,
             Err(ApplicationError::AccountOverFunded(
                 "a-key".to_string(),
-                u64::MAX
+                amount(u64::MAX)
             ))
         );
     }
@@ -155,7 +212,7 @@ mod tests {
     #[test]
     fn test_accounts_deposit_works() {
         let mut accounts = Accounts::new();
-        let amt = 100;
+        let amt = amount(100);
         let actual = accounts.deposit("a-key", amt);
         assert_eq!(
             actual,
@@ -169,7 +226,7 @@ mod tests {
     #[test]
     fn test_accounts_withdraw_works() {
         let mut accounts = Accounts::new();
-        let amt = 100;
+        let amt = amount(100);
         accounts.deposit("a-key", amt).expect("Couldn't deposit");
         let actual = accounts.withdraw("a-key", amt);
         assert_eq!(
@@ -184,11 +241,13 @@ mod tests {
     #[test]
     fn test_accounts_send_works() {
         let mut accounts = Accounts::new();
-        let amt = 100;
+        let amt = amount(100);
         accounts.deposit("a-key", amt).expect("Couldn't deposit");
 
         // creating the receiver is also required
-        accounts.deposit("b-key", 0).expect("Couldn't deposit");
+        accounts
+            .deposit("b-key", amount(0))
+            .expect("Couldn't deposit");
 
         let (tx1, tx2) = accounts.send("a-key", "b-key", amt).expect("Send failed");
         assert_eq!(
@@ -216,19 +275,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_accounts_charge_works() {
+        let mut accounts = Accounts::new();
+        accounts
+            .deposit("a-key", amount(100))
+            .expect("Couldn't deposit");
+
+        let actual = accounts.charge("a-key", "fees", amount(1));
+        assert_eq!(
+            actual,
+            Ok(Tx::Fee {
+                account: "a-key".to_string(),
+                fee_account: "fees".to_string(),
+                amount: amount(1)
+            })
+        );
+        assert_eq!(accounts.balance_of("a-key"), Ok(&amount(99)));
+        assert_eq!(accounts.balance_of("fees"), Ok(&amount(1)));
+
+        assert!(accounts.charge("a-key", "fees", amount(100)).is_err());
+        assert!(accounts.charge("b-key", "fees", amount(1)).is_err());
+        assert_eq!(accounts.balance_of("a-key"), Ok(&amount(99)));
+        assert_eq!(accounts.balance_of("fees"), Ok(&amount(1)));
+    }
+
     #[test]
     fn test_accounts_send_underfunded_fails_and_rolls_back() {
         let mut accounts = Accounts::new();
-        let amt = 100;
+        let amt = amount(100);
         accounts.deposit("a-key", amt).expect("Couldn't deposit");
 
         // creating the receiver is also required
-        accounts.deposit("b-key", 0).expect("Couldn't deposit");
+        accounts
+            .deposit("b-key", amount(0))
+            .expect("Couldn't deposit");
 
-        let actual = accounts.send("a-key", "b-key", amt + 1);
+        let actual = accounts.send("a-key", "b-key", amount(101));
         assert!(actual.is_err());
-        let expected: HashMap<String, u64> =
-            vec![("a-key".to_string(), amt), ("b-key".to_string(), 0)]
+        let expected: HashMap<String, Notional> =
+            vec![("a-key".to_string(), amt), ("b-key".to_string(), amount(0))]
                 .into_iter()
                 .collect();
         assert_eq!(accounts.accounts, expected);
@@ -237,20 +323,22 @@ mod tests {
     #[test]
     fn test_accounts_send_overfunded_fails_and_rolls_back() {
         let mut accounts = Accounts::new();
-        let amt = 100;
+        let amt = amount(100);
         accounts.deposit("a-key", amt).expect("Couldn't deposit");
 
         // creating the receiver is also required
         accounts
-            .deposit("b-key", u64::MAX)
+            .deposit("b-key", amount(u64::MAX))
             .expect("Couldn't deposit");
 
-        let actual = accounts.send("a-key", "b-key", 1);
+        let actual = accounts.send("a-key", "b-key", amount(1));
         assert!(actual.is_err());
-        let expected: HashMap<String, u64> =
-            vec![("a-key".to_string(), amt), ("b-key".to_string(), u64::MAX)]
-                .into_iter()
-                .collect();
+        let expected: HashMap<String, Notional> = vec![
+            ("a-key".to_string(), amt),
+            ("b-key".to_string(), amount(u64::MAX)),
+        ]
+        .into_iter()
+        .collect();
         assert_eq!(accounts.accounts, expected);
     }
 }
@@ -0,0 +1,167 @@
+use octopus_common::core::types::{Price, Side};
+use std::{collections::VecDeque, str::FromStr};
+
+use crate::risk::collar;
+
+/// What happens to orders while trading is halted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HaltPolicy {
+    /// They are rejected with `trading_halted`
+    #[default]
+    Reject,
+    /// They wait for trading to resume and are matched in the order they arrived
+    Queue,
+}
+
+impl FromStr for HaltPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(HaltPolicy::Reject),
+            "queue" => Ok(HaltPolicy::Queue),
+            other => Err(format!("'{}' is neither 'reject' nor 'queue'", other)),
+        }
+    }
+}
+
+/// Halts trading when a trade's price is more than `max_move_bps` basis points away from a trade within the `window` before it
+#[derive(Clone, Debug, Default)]
+pub struct CircuitBreaker {
+    /// Without a limit, the breaker never trips
+    max_move_bps: Option<u32>,
+    /// In milliseconds
+    window: u64,
+    /// How long a halt lasts in milliseconds, without one until trading is resumed by operations
+    halt_duration: Option<u64>,
+    /// Prices of the trades within the window, oldest first
+    trades: VecDeque<(u64, Price)>,
+}
+
+impl CircuitBreaker {
+    /// Trips on moves of more than `max_move_bps` within `window` and halts for `halt_duration`, in milliseconds
+    pub fn new(max_move_bps: u32, window: u64, halt_duration: Option<u64>) -> Self {
+        CircuitBreaker {
+            max_move_bps: Some(max_move_bps),
+            window,
+            halt_duration,
+            trades: VecDeque::new(),
+        }
+    }
+
+    /// How long a halt lasts, in milliseconds
+    pub fn halt_duration(&self) -> Option<u64> {
+        self.halt_duration
+    }
+
+    /// Adds a trade at `price` and `timestamp`, or returns the price it moved too far from and forgets all trades
+    pub fn record(&mut self, timestamp: u64, price: Price) -> Option<Price> {
+        self.max_move_bps?;
+        let start = timestamp.saturating_sub(self.window);
+        while self.trades.front().is_some_and(|(at, _)| *at < start) {
+            self.trades.pop_front();
+        }
+        let tripped = self.trip(timestamp, price);
+        if tripped.is_none() {
+            self.trades.push_back((timestamp, price));
+        }
+        tripped
+    }
+
+    /// Trips like [`CircuitBreaker::record`] if a trade at `price` would move too far, but without a trade
+    pub fn trip(&mut self, timestamp: u64, price: Price) -> Option<Price> {
+        let start = timestamp.saturating_sub(self.window);
+        let tripped = self
+            .collars(start)
+            .find_map(|(from, (low, high))| (price < low || price > high).then_some(from));
+        if tripped.is_some() {
+            // Tripping starts over, so trading after the halt is measured anew
+            self.trades.clear();
+        }
+        tripped
+    }
+
+    /// The highest price a buy, or the lowest a sell, may match at `timestamp` without tripping the breaker
+    pub fn bound(&self, side: &Side, timestamp: u64) -> Option<Price> {
+        let collars = self.collars(timestamp.saturating_sub(self.window));
+        match side {
+            Side::Buy => collars.map(|(_, (_, high))| high).min(),
+            Side::Sell => collars.map(|(_, (low, _))| low).max(),
+        }
+    }
+
+    /// The prices of the trades since `start` with the range of prices each allows
+    fn collars(&self, start: u64) -> impl Iterator<Item = (Price, (Price, Price))> + '_ {
+        let max_move_bps = self.max_move_bps;
+        self.trades
+            .iter()
+            .filter(move |(at, _)| *at >= start)
+            .filter_map(move |(_, from)| Some((*from, collar(*from, max_move_bps?))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+
+    fn price(price: &str) -> Price {
+        price.parse().unwrap()
+    }
+
+    #[test]
+    fn test_CircuitBreaker_trips_on_moves_within_the_window() {
+        // 10% within a second
+        let mut breaker = CircuitBreaker::new(1_000, 1_000, None);
+
+        assert_eq!(breaker.record(0, price("100.00")), None);
+        assert_eq!(breaker.record(100, price("105.00")), None);
+        assert_eq!(breaker.record(200, price("109.00")), None);
+        // Within 10% of 105 and 109, but not of 100
+        assert_eq!(breaker.record(300, price("110.01")), Some(price("100.00")));
+        // It starts over after tripping
+        assert_eq!(breaker.record(500, price("200.00")), None);
+
+        // The first trade left the window
+        let mut breaker = CircuitBreaker::new(1_000, 1_000, None);
+        breaker.record(0, price("100.00"));
+        assert_eq!(breaker.record(1_001, price("109.00")), None);
+        assert_eq!(breaker.record(1_002, price("89.99")), Some(price("109.00")));
+    }
+
+    #[test]
+    fn test_CircuitBreaker_bounds_prices_within_the_window() {
+        let mut breaker = CircuitBreaker::new(1_000, 1_000, None);
+        assert_eq!(breaker.bound(&Side::Buy, 0), None);
+
+        breaker.record(0, price("100.00"));
+        breaker.record(100, price("105.00"));
+        // Within 10% of both trades
+        assert_eq!(breaker.bound(&Side::Buy, 200), Some(price("110.00")));
+        assert_eq!(breaker.bound(&Side::Sell, 200), Some(price("94.50")));
+        // Only the second trade is left in the window
+        assert_eq!(breaker.bound(&Side::Buy, 1_050), Some(price("115.50")));
+
+        // Tripping without a trade forgets the trades as well
+        assert_eq!(breaker.trip(200, price("110.00")), None);
+        assert_eq!(breaker.trip(200, price("110.01")), Some(price("100.00")));
+        assert_eq!(breaker.bound(&Side::Buy, 200), None);
+    }
+
+    #[test]
+    fn test_CircuitBreaker_without_a_limit_never_trips() {
+        let mut breaker = CircuitBreaker::default();
+        breaker.record(0, price("1.00"));
+        assert_eq!(breaker.record(1, price("1000.00")), None);
+        assert_eq!(breaker.bound(&Side::Buy, 1), None);
+    }
+
+    #[test]
+    fn test_HaltPolicy_parses() {
+        assert_eq!("queue".parse(), Ok(HaltPolicy::Queue));
+        assert_eq!("reject".parse(), Ok(HaltPolicy::Reject));
+        assert!("hold".parse::<HaltPolicy>().is_err());
+    }
+}
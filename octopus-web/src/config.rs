@@ -0,0 +1,180 @@
+use std::{env, net::SocketAddr, path::PathBuf, str::FromStr};
+
+use octopus_common::core::{
+    decimal::MAX_SCALE,
+    types::{Allocation, Instrument, Quantity, RiskLimits},
+};
+
+use crate::{
+    circuit_breaker::{CircuitBreaker, HaltPolicy},
+    fees::{FeeSchedule, DEFAULT_FEE_ACCOUNT},
+    trading_platform::DEFAULT_CURRENCY_SCALE,
+};
+
+/// The address the server binds to if `OCTOPUS_ADDR` isn't set
+const SERVER_ADDR: &str = "127.0.0.1:8080";
+/// How many commands may wait for the matching engine if `OCTOPUS_QUEUE_CAPACITY` isn't set
+const QUEUE_CAPACITY: usize = 1024;
+/// The window of price moves if `OCTOPUS_VOLATILITY_WINDOW_SECS` isn't set
+const VOLATILITY_WINDOW_SECS: u64 = 60;
+/// What trading rules and amounts of currency look like
+const DECIMAL: &str = "a decimal like 0.05";
+
+/// How log lines are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Runtime settings of the server, read from the environment.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Socket address to listen on (`OCTOPUS_ADDR`)
+    pub addr: SocketAddr,
+    /// Where the platform state is restored from and flushed to on shutdown (`OCTOPUS_SNAPSHOT`)
+    pub snapshot: Option<PathBuf>,
+    /// Log output format (`OCTOPUS_LOG_FORMAT`, `text` or `json`)
+    pub log_format: LogFormat,
+    /// Commands that may wait for the matching engine before requests are shed (`OCTOPUS_QUEUE_CAPACITY`)
+    pub queue_capacity: usize,
+    /// What is traded and its trading rules (`OCTOPUS_SYMBOL`, `OCTOPUS_TICK_SIZE`, `OCTOPUS_ALLOCATION` and the like)
+    pub instrument: Instrument,
+    /// Decimal places of balances (`OCTOPUS_CURRENCY_SCALE`)
+    pub currency_scale: u8,
+    /// Maker and taker fees by 30-day volume (`OCTOPUS_FEE_TIERS`), none by default
+    pub fees: FeeSchedule,
+    /// The account fees are credited to (`OCTOPUS_FEE_ACCOUNT`)
+    pub fee_account: String,
+    /// Pre-trade limits of accounts without their own (`OCTOPUS_RISK_*`), none by default
+    pub risk_limits: RiskLimits,
+    /// Halts trading on large price moves (`OCTOPUS_VOLATILITY_*`), never by default
+    pub circuit_breaker: CircuitBreaker,
+    /// Whether orders are rejected or queued while trading is halted (`OCTOPUS_HALTED_ORDERS`, `reject` or `queue`)
+    pub halt_policy: HaltPolicy,
+}
+
+impl Config {
+    /// Reads the configuration from environment variables, falling back to defaults.
+    ///
+    /// # Panics
+    /// - `OCTOPUS_ADDR` is not a valid socket address
+    /// - `OCTOPUS_LOG_FORMAT`, `OCTOPUS_ALLOCATION` or `OCTOPUS_HALTED_ORDERS` is unknown
+    /// - `OCTOPUS_QUEUE_CAPACITY` is not a positive number
+    /// - A scale is greater than 18
+    /// - A trading rule isn't a decimal or the rules can't be met
+    /// - `OCTOPUS_FEE_TIERS` is malformed
+    /// - A risk limit or volatility setting isn't a number
+    pub fn from_env() -> Self {
+        let addr = env::var("OCTOPUS_ADDR")
+            .unwrap_or_else(|_| SERVER_ADDR.to_string())
+            .parse()
+            .expect("OCTOPUS_ADDR must be a socket address like 127.0.0.1:8080");
+        let snapshot = env::var_os("OCTOPUS_SNAPSHOT").map(PathBuf::from);
+        let log_format = match env::var("OCTOPUS_LOG_FORMAT").as_deref() {
+            Ok("json") => LogFormat::Json,
+            Ok("text") | Err(_) => LogFormat::Text,
+            Ok(other) => panic!(
+                "OCTOPUS_LOG_FORMAT must be 'text' or 'json', not '{}'",
+                other
+            ),
+        };
+
+        let queue_capacity = env::var("OCTOPUS_QUEUE_CAPACITY")
+            .map_or(Ok(QUEUE_CAPACITY), |capacity| capacity.parse())
+            .ok()
+            .filter(|capacity| *capacity > 0)
+            .expect("OCTOPUS_QUEUE_CAPACITY must be a positive number");
+
+        let default = Instrument::default();
+        let default = Instrument::new(
+            &env::var("OCTOPUS_SYMBOL").unwrap_or(default.symbol),
+            scale("OCTOPUS_PRICE_SCALE", default.price_scale),
+            scale("OCTOPUS_QUANTITY_SCALE", default.quantity_scale),
+        );
+        let min_allocation = var("OCTOPUS_MIN_ALLOCATION", DECIMAL).unwrap_or(Quantity::ZERO);
+        let allocation = match env::var("OCTOPUS_ALLOCATION").as_deref() {
+            Ok("fifo") | Err(_) => Allocation::Fifo,
+            Ok("pro_rata") => Allocation::ProRata { min_allocation },
+            Ok("top_order") => Allocation::TopOrder { min_allocation },
+            Ok(other) => panic!(
+                "OCTOPUS_ALLOCATION must be 'fifo', 'pro_rata' or 'top_order', not '{}'",
+                other
+            ),
+        };
+        let instrument = Instrument {
+            tick_size: var("OCTOPUS_TICK_SIZE", DECIMAL).unwrap_or(default.tick_size),
+            lot_size: var("OCTOPUS_LOT_SIZE", DECIMAL).unwrap_or(default.lot_size),
+            min_quantity: var("OCTOPUS_MIN_QUANTITY", DECIMAL).unwrap_or(default.min_quantity),
+            max_quantity: var("OCTOPUS_MAX_QUANTITY", DECIMAL).or(default.max_quantity),
+            min_notional: var("OCTOPUS_MIN_NOTIONAL", DECIMAL).unwrap_or(default.min_notional),
+            allocation,
+            ..default
+        };
+        if let Err(reason) = instrument.validate() {
+            panic!("invalid trading rules: {}", reason);
+        }
+        let currency_scale = scale("OCTOPUS_CURRENCY_SCALE", DEFAULT_CURRENCY_SCALE);
+
+        let fees = env::var("OCTOPUS_FEE_TIERS")
+            .map_or(Ok(FeeSchedule::default()), |tiers| tiers.parse())
+            .unwrap_or_else(|e| panic!("invalid OCTOPUS_FEE_TIERS: {}", e));
+        let fee_account =
+            env::var("OCTOPUS_FEE_ACCOUNT").unwrap_or_else(|_| DEFAULT_FEE_ACCOUNT.to_string());
+
+        let risk_limits = RiskLimits {
+            max_order_quantity: var("OCTOPUS_RISK_MAX_ORDER_QUANTITY", DECIMAL),
+            max_notional: var("OCTOPUS_RISK_MAX_NOTIONAL", DECIMAL),
+            max_open_orders: var("OCTOPUS_RISK_MAX_OPEN_ORDERS", "a number"),
+            max_daily_volume: var("OCTOPUS_RISK_MAX_DAILY_VOLUME", DECIMAL),
+            price_collar_bps: var("OCTOPUS_RISK_PRICE_COLLAR_BPS", "a number of basis points"),
+        };
+
+        let circuit_breaker = var::<u32>("OCTOPUS_VOLATILITY_HALT_BPS", "a number of basis points")
+            .map_or_else(CircuitBreaker::default, |max_move_bps| {
+                let window: u64 = var("OCTOPUS_VOLATILITY_WINDOW_SECS", "a number of seconds")
+                    .unwrap_or(VOLATILITY_WINDOW_SECS);
+                let halt: Option<u64> = var("OCTOPUS_VOLATILITY_HALT_SECS", "a number of seconds");
+                CircuitBreaker::new(
+                    max_move_bps,
+                    window.saturating_mul(1000),
+                    halt.map(|halt| halt.saturating_mul(1000)),
+                )
+            });
+        let halt_policy = var("OCTOPUS_HALTED_ORDERS", "'reject' or 'queue'").unwrap_or_default();
+
+        Config {
+            addr,
+            snapshot,
+            log_format,
+            queue_capacity,
+            instrument,
+            currency_scale,
+            fees,
+            fee_account,
+            risk_limits,
+            circuit_breaker,
+            halt_policy,
+        }
+    }
+}
+
+/// Reads a number of decimal places from the environment variable `name`
+fn scale(name: &str, default: u8) -> u8 {
+    env::var(name)
+        .map_or(Ok(default), |scale| scale.parse())
+        .ok()
+        .filter(|scale| *scale <= MAX_SCALE)
+        .unwrap_or_else(|| panic!("{} must be a number from 0 to {}", name, MAX_SCALE))
+}
+
+/// Reads the environment variable `name` if it is set, which has to be `expected`
+fn var<T: FromStr>(name: &str, expected: &str) -> Option<T> {
+    env::var(name).ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be {}, not '{}'", name, expected, value))
+    })
+}
@@ -1,151 +1,952 @@
-use std::collections::{BTreeMap, BinaryHeap};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Bound,
+};
 
 use octopus_common::{
-    core::types::{Order, PartialOrder, Receipt, Side},
+    core::{
+        decimal::Decimal,
+        types::{Allocation, Order, PartialOrder, Price, Quantity, Receipt, Side},
+    },
     errors::ApplicationError,
 };
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use slab::Slab;
+
+/// Marks the end of a queue
+const NIL: usize = usize::MAX;
+
+/// Index of a signer in [`Signers`]
+type SignerId = u32;
+
+/// Interned signer names, so resting orders and fills don't carry a `String` each
+#[derive(Default, Debug, Clone)]
+struct Signers {
+    ids: HashMap<String, SignerId>,
+    names: Vec<String>,
+}
+
+impl Signers {
+    fn intern(&mut self, name: String) -> SignerId {
+        if let Some(id) = self.ids.get(&name) {
+            return *id;
+        }
+        let id = self.names.len() as SignerId;
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    fn name(&self, id: SignerId) -> &str {
+        &self.names[id as usize]
+    }
+}
+
+/// A resting order, linked into the queue of its price level
+#[derive(Debug, Clone)]
+struct Node {
+    ordinal: u64,
+    signer: SignerId,
+    price: Price,
+    amount: Quantity,
+    remaining: Quantity,
+    side: Side,
+    /// Slab key of the next older order at this price, or [`NIL`]
+    prev: usize,
+    /// Slab key of the next newer order at this price, or [`NIL`]
+    next: usize,
+}
+
+/// The queue of resting orders at a price, oldest first
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    head: usize,
+    tail: usize,
+    /// Slab key of the order that bettered the best price and opened the level, or [`NIL`] if it left or the level was opened otherwise
+    top: usize,
+}
+
+impl Level {
+    const EMPTY: Level = Level {
+        head: NIL,
+        tail: NIL,
+        top: NIL,
+    };
+
+    fn push_back(&mut self, orders: &mut Slab<Node>, key: usize) {
+        orders[key].prev = self.tail;
+        orders[key].next = NIL;
+        match self.tail {
+            NIL => self.head = key,
+            tail => orders[tail].next = key,
+        }
+        self.tail = key;
+    }
+
+    fn unlink(&mut self, orders: &mut Slab<Node>, key: usize) {
+        let (prev, next) = (orders[key].prev, orders[key].next);
+        match prev {
+            NIL => self.head = next,
+            prev => orders[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => orders[next].prev = prev,
+        }
+        if self.top == key {
+            self.top = NIL;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head == NIL
+    }
+}
+
+/// A match between an incoming order and a resting one, as recorded in the history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fill {
+    /// Ordinal of the incoming order
+    pub ordinal: u64,
+    /// Ordinal of the resting order
+    pub maker_ordinal: u64,
+    maker: SignerId,
+    /// Price of the resting order
+    pub price: Price,
+    /// Units traded
+    pub amount: Quantity,
+    /// Units of the resting order left after the match
+    pub remaining: Quantity,
+    /// Side of the resting order
+    pub side: Side,
+}
+
+/// The single price an auction uncrosses at, and what it executes there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clearing {
+    pub price: Price,
+    /// Units bought and sold at the price
+    pub volume: Quantity,
+    /// Units of the side with more interest at the price that are left over
+    pub surplus: Quantity,
+    /// The side with more interest, if the sides aren't even
+    pub surplus_side: Option<Side>,
+}
 
-#[derive(Default, Debug)]
+/// A match of an auction between a bid and an ask that were both resting in the book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cross {
+    /// Ordinal of the bid
+    pub bid: u64,
+    pub buyer: String,
+    /// Ordinal of the ask
+    pub ask: u64,
+    pub seller: String,
+    /// The clearing price
+    pub price: Price,
+    /// Units traded
+    pub amount: Quantity,
+}
+
+/// What each order at `level` gets of `amount` allocated pro rata, in queue order, or `None` if it overflows
+fn shares(
+    orders: &Slab<Node>,
+    level: &Level,
+    signer: SignerId,
+    amount: Quantity,
+    lot_size: Quantity,
+    min_allocation: Quantity,
+    top: bool,
+) -> Option<Vec<Quantity>> {
+    // With `top`, the top order of the level is filled first. The rest is shared in proportion to what is left of each order,
+    // rounded down to `lot_size`, and shares below `min_allocation` are dropped. What that leaves over is filled oldest first,
+    // so the incoming order gets as much as the level has. Orders of `signer` get nothing, as self-matches are illegal.
+    let keys: Vec<usize> =
+        std::iter::successors((level.head != NIL).then_some(level.head), |&key| {
+            let next = orders[key].next;
+            (next != NIL).then_some(next)
+        })
+        .collect();
+    // Shares are computed in units of the most decimal places involved
+    let scale = keys
+        .iter()
+        .map(|&key| orders[key].remaining.0.scale())
+        .chain([amount, lot_size, min_allocation].map(|q| q.0.scale()))
+        .max()?;
+    let units = |quantity: Quantity| {
+        quantity
+            .0
+            .rescale(scale)
+            .map(|decimal| u128::from(decimal.units()))
+    };
+    let mut available = keys
+        .iter()
+        .map(|&key| {
+            if orders[key].signer == signer {
+                Some(0)
+            } else {
+                units(orders[key].remaining)
+            }
+        })
+        .collect::<Option<Vec<u128>>>()?;
+    let mut left = units(amount)?;
+    let lot = units(lot_size)?.max(1);
+    let min_allocation = units(min_allocation)?;
+    let mut shares = vec![0u128; keys.len()];
+    let mut allot = |i: usize, share: u128, available: &mut [u128], left: &mut u128| {
+        shares[i] += share;
+        available[i] -= share;
+        *left -= share;
+    };
+
+    if let Some(i) = keys.iter().position(|&key| top && key == level.top) {
+        let share = left.min(available[i]);
+        allot(i, share, &mut available, &mut left);
+    }
+    let total: u128 = available.iter().sum();
+    if total > 0 {
+        let pool = left.min(total);
+        for i in 0..keys.len() {
+            let share = pool.checked_mul(available[i])? / total;
+            let share = share - share % lot;
+            if share > 0 && share >= min_allocation {
+                allot(i, share, &mut available, &mut left);
+            }
+        }
+    }
+    for i in 0..keys.len() {
+        let share = left.min(available[i]);
+        allot(i, share, &mut available, &mut left);
+    }
+
+    shares
+        .into_iter()
+        .map(|share| {
+            u64::try_from(share)
+                .ok()
+                .map(|units| Quantity(Decimal::new(units, scale)))
+        })
+        .collect()
+}
+
+/// Matches orders by price, then time or another [`Allocation`] among the orders at a price
+///
+/// Resting orders live in a slab and are linked into a FIFO queue per price level, so matching oldest first and cancelling
+/// don't allocate apart from the receipt.
+#[derive(Default, Debug, Clone)]
 pub struct MatchingEngine {
     /// The last sequence number
-    pub ordinal: u64,
+    ordinal: u64,
+
+    /// The "Bid" or "Buy" side of the order book, by price
+    bids: BTreeMap<Price, Level>,
+    /// The "Ask" or "Sell" side of the order book, by price
+    asks: BTreeMap<Price, Level>,
+    /// The resting orders of both sides
+    orders: Slab<Node>,
+    /// Slab keys of the resting orders by ordinal
+    index: HashMap<u64, usize>,
+    signers: Signers,
+    /// Slab keys of the resting orders by signer
+    open: HashMap<SignerId, HashSet<usize>>,
 
-    /// The "Bid" or "Buy" side of the order book. Ordered by ordinal number.
-    pub bids: BTreeMap<u64, BinaryHeap<PartialOrder>>,
-    /// The "Ask" or "Sell" side of the order book. Ordered by ordinal number.
-    pub asks: BTreeMap<u64, BinaryHeap<PartialOrder>>,
-    /// Previous matches for record keeping
-    pub history: Vec<Receipt>,
+    /// Previous matches for record keeping, oldest first
+    pub history: Vec<Fill>,
+
+    /// How orders at a price share a match, configuration rather than state like the lot size
+    allocation: Allocation,
+    /// What pro-rata allocations are rounded down to
+    lot_size: Quantity,
 }
 
 impl MatchingEngine {
     /// Creates a new [`MatchingEngine`] with an ordinal of 0 and empty books
     pub fn new() -> Self {
-        MatchingEngine {
-            ordinal: 0,
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
-            history: Vec::new(),
-        }
+        Default::default()
+    }
+
+    /// Allocates matches among the orders at a price by `allocation`, rounding pro-rata shares down to `lot_size`
+    pub fn set_allocation(&mut self, allocation: Allocation, lot_size: Quantity) {
+        self.allocation = allocation;
+        self.lot_size = lot_size;
     }
 
     /// Processes an [`Order`] and returns a [`Receipt`]
     /// This includes matching the order to whatever is in the current books and adding the remainder (if any) to the book for future matching.
     pub fn process(&mut self, order: Order) -> Result<Receipt, ApplicationError> {
-        // Increment the ordinal number for this order
+        let ordinal = self.next_ordinal();
+        self.process_as(ordinal, order, None)
+    }
+
+    /// Takes the next ordinal for an order that enters the book later, see [`MatchingEngine::process_as`]
+    pub fn next_ordinal(&mut self) -> u64 {
         self.ordinal += 1;
-        let ordinal = self.ordinal;
-
-        let original_amount = order.amount;
-        let mut partial = order.into_partial_order(ordinal, original_amount);
-
-        // Orders are matched to the opposite side
-        let receipt = match &partial.side {
-            Side::Buy => {
-                // Fetch all orders in the expected price range from this side of the orderbook
-                let orderbook_entry = self.asks.range_mut(u64::MIN..=partial.price);
-
-                let receipt = MatchingEngine::match_order(&partial, orderbook_entry, ordinal)?;
-                let matched_amount: u64 = receipt.matches.iter().map(|m| m.amount).sum();
-
-                // The order wasn't fully matched
-                if matched_amount < original_amount {
-                    partial.amount = original_amount - matched_amount;
-                    let price = partial.price;
-                    let bids = self.bids.entry(price).or_insert(vec![].into());
-                    bids.push(partial);
+        self.ordinal
+    }
+
+    /// Processes an [`Order`] like [`MatchingEngine::process`], under an `ordinal` it was given before and matching no further than `bound`
+    pub fn process_as(
+        &mut self,
+        ordinal: u64,
+        order: Order,
+        bound: Option<Price>,
+    ) -> Result<Receipt, ApplicationError> {
+        let Order {
+            price,
+            amount,
+            side,
+            signer,
+        } = order;
+        let signer = self.signers.intern(signer);
+
+        // The rest of an order stopped by its bound rests at its own price, even if that crosses the book
+        let reach = bound.map_or(price, |bound| match side {
+            Side::Buy => price.min(bound),
+            Side::Sell => price.max(bound),
+        });
+        let (matches, remaining) = self.match_order(ordinal, signer, &side, reach, amount);
+        // The order wasn't fully matched
+        if !remaining.is_zero() {
+            let top = self.best(&side).is_none_or(|best| match side {
+                Side::Buy => price > best,
+                Side::Sell => price < best,
+            });
+            self.rest(
+                Node {
+                    ordinal,
+                    signer,
+                    price,
+                    amount: remaining,
+                    remaining,
+                    side,
+                    prev: NIL,
+                    next: NIL,
+                },
+                top,
+            );
+        }
+
+        for m in &matches {
+            tracing::debug!(
+                ordinal,
+                maker_ordinal = m.ordinal,
+                maker = %m.signer,
+                price = %m.price,
+                amount = %m.amount,
+                "matched"
+            );
+        }
+        Ok(Receipt {
+            ordinal,
+            matches,
+            fees: vec![],
+            queued: false,
+        })
+    }
+
+    /// Removes the resting order with `ordinal` from the books, if it is still there
+    pub fn cancel(&mut self, ordinal: u64) -> Option<PartialOrder> {
+        let key = self.index.remove(&ordinal)?;
+        let (price, side) = (self.orders[key].price, self.orders[key].side.clone());
+        let book = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let level = book
+            .get_mut(&price)
+            .expect("resting orders are queued at their price");
+        level.unlink(&mut self.orders, key);
+        if level.is_empty() {
+            book.remove(&price);
+        }
+
+        let node = self.orders.remove(key);
+        close(&mut self.open, node.signer, key);
+        Some(self.partial_order(&node))
+    }
+
+    /// Adds an order to the book without matching it, which may leave the book crossed until [`MatchingEngine::uncross`]
+    pub fn collect(&mut self, order: Order) -> Receipt {
+        let ordinal = self.next_ordinal();
+        self.collect_as(ordinal, order)
+    }
+
+    /// Adds an order to the book like [`MatchingEngine::collect`], under an `ordinal` it was given before
+    pub fn collect_as(&mut self, ordinal: u64, order: Order) -> Receipt {
+        let signer = self.signers.intern(order.signer);
+        self.rest(
+            Node {
+                ordinal,
+                signer,
+                price: order.price,
+                amount: order.amount,
+                remaining: order.amount,
+                side: order.side,
+                prev: NIL,
+                next: NIL,
+            },
+            false,
+        );
+        Receipt {
+            ordinal,
+            matches: vec![],
+            fees: vec![],
+            queued: false,
+        }
+    }
+
+    /// The price a crossed book uncrosses at: the limit price that executes the most units, see the README for ties
+    pub fn clearing(&self) -> Option<Clearing> {
+        let depth = |(price, level): (&Price, &Level)| {
+            let units = self.keys(level.head).fold(Quantity::ZERO, |total, key| {
+                Quantity(total.0.saturating_add(self.orders[key].remaining.0))
+            });
+            (*price, units)
+        };
+        let bids: Vec<(Price, Quantity)> = self.bids.iter().map(depth).collect();
+        let asks: Vec<(Price, Quantity)> = self.asks.iter().map(depth).collect();
+        let (best_bid, best_ask) = (self.best(&Side::Buy)?, self.best(&Side::Sell)?);
+        if best_bid < best_ask {
+            return None;
+        }
+
+        let mut candidates: Vec<Price> = bids
+            .iter()
+            .chain(&asks)
+            .map(|(price, _)| *price)
+            .filter(|price| (best_ask..=best_bid).contains(price))
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        let sum = |levels: &[(Price, Quantity)], include: &dyn Fn(Price) -> bool| {
+            levels
+                .iter()
+                .filter(|(price, _)| include(*price))
+                .fold(Quantity::ZERO, |total, (_, units)| {
+                    Quantity(total.0.saturating_add(units.0))
+                })
+        };
+        let clearings: Vec<Clearing> = candidates
+            .into_iter()
+            .map(|price| {
+                let demand = sum(&bids, &|bid| bid >= price);
+                let supply = sum(&asks, &|ask| ask <= price);
+                let (surplus, surplus_side) = match demand.cmp(&supply) {
+                    Ordering::Greater => (demand - supply, Some(Side::Buy)),
+                    Ordering::Less => (supply - demand, Some(Side::Sell)),
+                    Ordering::Equal => (Quantity::ZERO, None),
+                };
+                // Self-matches are skipped, so the volume is what an uncross at the price executes
+                let volume = self
+                    .crosses(price)
+                    .iter()
+                    .fold(Quantity::ZERO, |total, (_, _, amount)| total + *amount);
+                Clearing {
+                    price,
+                    volume,
+                    surplus,
+                    surplus_side,
                 }
-                receipt
-            }
-            Side::Sell => {
-                // Fetch all orders in the expected price range from this side of the orderbook
-                let orderbook_entry = self.bids.range_mut(partial.price..=u64::MAX);
-
-                let receipt = MatchingEngine::match_order(&partial, orderbook_entry, ordinal)?;
-                let matched_amount: u64 = receipt.matches.iter().map(|m| m.amount).sum();
-
-                // The order wasn't fully matched
-                if matched_amount < original_amount {
-                    partial.amount = original_amount - matched_amount;
-                    let price = partial.price;
-                    let bids = self.asks.entry(price).or_insert(vec![].into());
-                    bids.push(partial);
+            })
+            .collect();
+
+        // Ties go to the smaller surplus, then to the highest price if the surplus is on the buy side everywhere or the
+        // lowest if it is on the sell side, and finally to the price closest to the last trade
+        // A book that is only crossed by orders of the same signer has nothing to execute
+        let volume = clearings
+            .iter()
+            .map(|c| c.volume)
+            .max()
+            .filter(|volume| !volume.is_zero())?;
+        let surplus = clearings
+            .iter()
+            .filter(|c| c.volume == volume)
+            .map(|c| c.surplus)
+            .min()?;
+        let mut tied: Vec<Clearing> = clearings
+            .into_iter()
+            .filter(|c| c.volume == volume && c.surplus == surplus)
+            .collect();
+        if tied.iter().all(|c| c.surplus_side == Some(Side::Buy)) {
+            return tied.pop();
+        }
+        if tied.iter().all(|c| c.surplus_side == Some(Side::Sell)) {
+            return tied.into_iter().next();
+        }
+        let Some(reference) = self.last_price() else {
+            return tied.into_iter().next();
+        };
+        let distance = |c: &Clearing| {
+            let (low, high) = (c.price.min(reference), c.price.max(reference));
+            high.0.checked_sub(low.0)
+        };
+        // `min_by_key` keeps the first, and lowest, of equally close prices
+        tied.into_iter().min_by_key(distance)
+    }
+
+    /// The matches of an uncross at `price`, as the keys of the bid and the ask and the units traded
+    fn crosses(&self, price: Price) -> Vec<(usize, usize, Quantity)> {
+        let bids: Vec<usize> = self
+            .bids
+            .range(price..)
+            .rev()
+            .flat_map(|(_, level)| self.keys(level.head))
+            .collect();
+        let asks: Vec<usize> = self
+            .asks
+            .range(..=price)
+            .flat_map(|(_, level)| self.keys(level.head))
+            .collect();
+
+        // Bids from the highest and asks from the lowest price, each level oldest first, and never of the same signer
+        let mut remaining: Vec<Quantity> =
+            asks.iter().map(|&ask| self.orders[ask].remaining).collect();
+        let mut crosses = vec![];
+        // Asks before this one are filled
+        let mut first = 0;
+        for &bid in &bids {
+            let mut left = self.orders[bid].remaining;
+            for (i, &ask) in asks.iter().enumerate().skip(first) {
+                if left.is_zero() {
+                    break;
+                }
+                if remaining[i].is_zero() || self.orders[bid].signer == self.orders[ask].signer {
+                    continue;
                 }
-                receipt
+                let take = left.min(remaining[i]);
+                left -= take;
+                remaining[i] -= take;
+                crosses.push((bid, ask, take));
+            }
+            while first < asks.len() && remaining[first].is_zero() {
+                first += 1;
             }
+        }
+        crosses
+    }
+
+    /// Executes a crossed book at the [`MatchingEngine::clearing`] price and leaves the orders that aren't filled resting
+    pub fn uncross(&mut self) -> Vec<Cross> {
+        let Some(Clearing { price, .. }) = self.clearing() else {
+            return vec![];
+        };
+        let mut keys = vec![];
+        let mut crosses = vec![];
+        for (bid, ask, take) in self.crosses(price) {
+            self.orders[bid].remaining -= take;
+            self.orders[ask].remaining -= take;
+            keys.extend([bid, ask]);
+
+            let fill = Fill {
+                ordinal: self.orders[bid].ordinal,
+                maker_ordinal: self.orders[ask].ordinal,
+                maker: self.orders[ask].signer,
+                price,
+                amount: take,
+                remaining: self.orders[ask].remaining,
+                side: Side::Sell,
+            };
+            crosses.push(Cross {
+                bid: fill.ordinal,
+                buyer: self.signers.name(self.orders[bid].signer).to_string(),
+                ask: fill.maker_ordinal,
+                seller: self.signers.name(fill.maker).to_string(),
+                price,
+                amount: take,
+            });
+            self.history.push(fill);
+        }
+
+        // Filled orders leave the book like cancelled ones
+        keys.sort_unstable();
+        keys.dedup();
+        for key in keys {
+            if self.orders[key].remaining.is_zero() {
+                let ordinal = self.orders[key].ordinal;
+                self.cancel(ordinal);
+            }
+        }
+        for cross in &crosses {
+            tracing::debug!(
+                bid = cross.bid,
+                ask = cross.ask,
+                price = %cross.price,
+                amount = %cross.amount,
+                "uncrossed"
+            );
+        }
+        crosses
+    }
+
+    /// Removes all resting orders of `signer`, or only those on `side`, oldest first
+    pub fn cancel_all(&mut self, signer: &str, side: Option<&Side>) -> Vec<PartialOrder> {
+        let Some(&id) = self.signers.ids.get(signer) else {
+            return vec![];
         };
+        let mut ordinals: Vec<u64> = self
+            .orders
+            .iter()
+            .filter(|(_, node)| node.signer == id && side.is_none_or(|side| &node.side == side))
+            .map(|(_, node)| node.ordinal)
+            .collect();
+        ordinals.sort_unstable();
+        ordinals
+            .into_iter()
+            .filter_map(|ordinal| self.cancel(ordinal))
+            .collect()
+    }
 
-        // Cleanup: Remove price entries without orders from the orderbook
-        self.asks.retain(|_, orders| !orders.is_empty());
-        self.bids.retain(|_, orders| !orders.is_empty());
+    /// The last sequence number
+    pub fn ordinal(&self) -> u64 {
+        self.ordinal
+    }
+
+    /// The number of resting orders of `signer`
+    pub fn open_orders(&self, signer: &str) -> usize {
+        self.signers
+            .ids
+            .get(signer)
+            .and_then(|id| self.open.get(id))
+            .map_or(0, HashSet::len)
+    }
+
+    /// The resting orders of `signer`, in no particular order
+    pub fn orders_of<'a>(&'a self, signer: &str) -> impl Iterator<Item = PartialOrder> + 'a {
+        self.signers
+            .ids
+            .get(signer)
+            .and_then(|id| self.open.get(id))
+            .into_iter()
+            .flatten()
+            .map(|&key| self.partial_order(&self.orders[key]))
+    }
+
+    /// The best price of a side of the book: the highest bid or the lowest ask
+    pub fn best(&self, side: &Side) -> Option<Price> {
+        match side {
+            Side::Buy => self.bids.keys().next_back().copied(),
+            Side::Sell => self.asks.keys().next().copied(),
+        }
+    }
+
+    /// The price of the most recent match
+    pub fn last_price(&self) -> Option<Price> {
+        self.history.last().map(|fill| fill.price)
+    }
 
-        // Keep a log of matches
-        self.history.push(receipt.clone());
-        Ok(receipt)
+    /// The number of price levels on a side of the book
+    pub fn levels(&self, side: &Side) -> usize {
+        self.book(side).len()
     }
 
-    /// Matches an order to the provided order book side.
-    /// # Parameters
-    /// - `orderbook_entry`: a pre-filtered iterator for order book_entry in the correct price range
-    /// - `ordinal` the next ordinal number to use if a position is opened
-    fn match_order<'a, T>(
-        order: &PartialOrder,
-        mut orderbook_entry: T,
+    /// The resting orders at a price, oldest first
+    pub fn level(&self, side: &Side, price: Price) -> impl Iterator<Item = PartialOrder> + '_ {
+        let head = self.book(side).get(&price).map_or(NIL, |level| level.head);
+        self.queue(head)
+    }
+
+    /// All resting orders: the asks, then the bids, each by ascending price and oldest first
+    pub fn orders(&self) -> impl Iterator<Item = PartialOrder> + '_ {
+        self.asks
+            .values()
+            .chain(self.bids.values())
+            .flat_map(|level| self.queue(level.head))
+    }
+
+    /// A match of the history in the shape of [`Receipt::matches`]
+    pub fn matched_order(&self, fill: &Fill) -> PartialOrder {
+        PartialOrder {
+            price: fill.price,
+            amount: fill.amount,
+            remaining: fill.remaining,
+            side: fill.side.clone(),
+            signer: self.signers.name(fill.maker).to_string(),
+            ordinal: fill.maker_ordinal,
+        }
+    }
+
+    /// Matches an order against the opposite side of the book, returns the matches and the units left unmatched.
+    /// Like the original engine, both sides are walked from the lowest eligible price up.
+    fn match_order(
+        &mut self,
         ordinal: u64,
-    ) -> Result<Receipt, ApplicationError>
-    where
-        T: Iterator<Item = (&'a u64, &'a mut BinaryHeap<PartialOrder>)>,
-    {
-        let mut remaining_amount = order.amount;
+        signer: SignerId,
+        side: &Side,
+        limit: Price,
+        amount: Quantity,
+    ) -> (Vec<PartialOrder>, Quantity) {
+        let MatchingEngine {
+            bids,
+            asks,
+            orders,
+            index,
+            signers,
+            open,
+            history,
+            allocation,
+            lot_size,
+            ..
+        } = self;
+        let (book, mut from, to) = match side {
+            Side::Buy => (asks, Bound::Unbounded, Bound::Included(limit)),
+            Side::Sell => (bids, Bound::Included(limit), Bound::Unbounded),
+        };
+
+        let mut remaining = amount;
         let mut matches = vec![];
+        while !remaining.is_zero() {
+            // Nothing left to match with
+            let Some((&price, level)) = book.range_mut((from, to)).next() else {
+                break;
+            };
+            from = Bound::Excluded(price);
 
-        // Each matching position's amount is subtraced
-        'outer: while remaining_amount > 0 {
-            // The iterator contains all orderbook_entry of a price point
-            match orderbook_entry.next() {
-                Some((price, orderbook_entry)) => {
-                    // Self-matches are illegal
-                    let mut self_matches = vec![];
-                    // pop a position off the heap
-                    'ask_loop: while let Some(mut pos) = orderbook_entry.pop() {
-                        // A self-match is illegal so we keep the order and skip the matching for it
-                        if pos.signer == order.signer {
-                            self_matches.push(pos);
-                            continue 'ask_loop;
-                        }
-
-                        // Using checked_sub guards against overlow errors
-                        match pos.remaining.checked_sub(remaining_amount) {
-                            Some(_) => {
-                                matches.push(PartialOrder::take_from(
-                                    &mut pos,
-                                    remaining_amount,
-                                    *price,
-                                ));
-                                if pos.remaining > 0 {
-                                    orderbook_entry.push(pos);
-                                }
-                                break 'ask_loop;
-                            }
-                            None => {
-                                remaining_amount -= pos.remaining;
-                                pos.remaining = 0;
-                                matches.push(pos);
-                            }
-                        }
-                    }
+            // Without pro-rata shares, or if they can't be computed, the oldest order is filled first
+            let pro_rata = match *allocation {
+                Allocation::Fifo => None,
+                Allocation::ProRata { min_allocation } => Some((min_allocation, false)),
+                Allocation::TopOrder { min_allocation } => Some((min_allocation, true)),
+            };
+            let shares = pro_rata.and_then(|(min_allocation, top)| {
+                shares(
+                    orders,
+                    level,
+                    signer,
+                    remaining,
+                    *lot_size,
+                    min_allocation,
+                    top,
+                )
+            });
+            let mut key = level.head;
+            let mut position = 0;
+            while key != NIL && !remaining.is_zero() {
+                let maker = &mut orders[key];
+                let next = maker.next;
+                let take = match &shares {
+                    Some(shares) => shares[position],
+                    // Self-matches are illegal, the order keeps its place in the queue
+                    None if maker.signer == signer => Quantity::ZERO,
+                    None => remaining.min(maker.remaining),
+                };
+                position += 1;
+                if !take.is_zero() {
+                    maker.remaining -= take;
+                    remaining -= take;
+
+                    let fill = Fill {
+                        ordinal,
+                        maker_ordinal: maker.ordinal,
+                        maker: maker.signer,
+                        price,
+                        amount: take,
+                        remaining: maker.remaining,
+                        side: maker.side.clone(),
+                    };
+                    matches.push(PartialOrder {
+                        price,
+                        amount: take,
+                        remaining: fill.remaining,
+                        side: fill.side.clone(),
+                        signer: signers.name(fill.maker).to_string(),
+                        ordinal: fill.maker_ordinal,
+                    });
+                    history.push(fill);
 
-                    // Return the self-matched orders at their price in the book. To avoid an infinite loop, we put the orders back after finishing the matching for this price point
-                    self_matches
-                        .into_iter()
-                        .for_each(|m| orderbook_entry.push(m));
+                    if maker.remaining.is_zero() {
+                        index.remove(&maker.ordinal);
+                        close(open, maker.signer, key);
+                        level.unlink(orders, key);
+                        orders.remove(key);
+                    }
                 }
-                // Nothing left to match with
-                None => break 'outer,
+                key = next;
+            }
+
+            // Only levels that were matched against can have become empty
+            if level.is_empty() {
+                book.remove(&price);
+            }
+        }
+        (matches, remaining)
+    }
+
+    /// Queues an order at the back of its price level, as its `top` order if it bettered the best price
+    fn rest(&mut self, node: Node, top: bool) {
+        let book = match node.side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let (price, ordinal) = (node.price, node.ordinal);
+        let signer = node.signer;
+        let key = self.orders.insert(node);
+        self.open.entry(signer).or_default().insert(key);
+        self.index.insert(ordinal, key);
+        let level = book.entry(price).or_insert(Level::EMPTY);
+        level.push_back(&mut self.orders, key);
+        if top {
+            level.top = key;
+        }
+    }
+
+    fn book(&self, side: &Side) -> &BTreeMap<Price, Level> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    /// The orders of the queue starting at `head`
+    fn queue(&self, head: usize) -> impl Iterator<Item = PartialOrder> + '_ {
+        self.keys(head)
+            .map(|key| self.partial_order(&self.orders[key]))
+    }
+
+    /// The slab keys of the queue starting at `head`
+    fn keys(&self, head: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors((head != NIL).then_some(head), |&key| {
+            let next = self.orders[key].next;
+            (next != NIL).then_some(next)
+        })
+    }
+
+    fn partial_order(&self, node: &Node) -> PartialOrder {
+        PartialOrder {
+            price: node.price,
+            amount: node.amount,
+            remaining: node.remaining,
+            side: node.side.clone(),
+            signer: self.signers.name(node.signer).to_string(),
+            ordinal: node.ordinal,
+        }
+    }
+}
+
+/// Forgets the resting order of `signer` at `key`
+fn close(open: &mut HashMap<SignerId, HashSet<usize>>, signer: SignerId, key: usize) {
+    if let Some(keys) = open.get_mut(&signer) {
+        keys.remove(&key);
+        if keys.is_empty() {
+            open.remove(&signer);
+        }
+    }
+}
+
+/// The serialized form of a [`MatchingEngine`], in the heap-based engine's format so older snapshots still restore
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    ordinal: u64,
+    bids: BTreeMap<Price, Vec<PartialOrder>>,
+    asks: BTreeMap<Price, Vec<PartialOrder>>,
+    history: Vec<Receipt>,
+    /// Ordinals of the levels' top orders, which older snapshots don't have
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    top: Vec<u64>,
+}
+
+impl From<&MatchingEngine> for Snapshot {
+    fn from(engine: &MatchingEngine) -> Self {
+        let book = |book: &BTreeMap<Price, Level>| {
+            book.iter()
+                .map(|(price, level)| (*price, engine.queue(level.head).collect()))
+                .collect()
+        };
+
+        // Orders without matches don't leave a trace in the history
+        let mut history: Vec<Receipt> = vec![];
+        for fill in &engine.history {
+            let matched = engine.matched_order(fill);
+            match history.last_mut() {
+                Some(receipt) if receipt.ordinal == fill.ordinal => receipt.matches.push(matched),
+                _ => history.push(Receipt {
+                    ordinal: fill.ordinal,
+                    matches: vec![matched],
+                    fees: vec![],
+                    queued: false,
+                }),
             }
         }
 
-        Ok(Receipt { ordinal, matches })
+        let top = engine
+            .asks
+            .values()
+            .chain(engine.bids.values())
+            .filter(|level| level.top != NIL)
+            .map(|level| engine.orders[level.top].ordinal)
+            .collect();
+
+        Snapshot {
+            ordinal: engine.ordinal,
+            bids: book(&engine.bids),
+            asks: book(&engine.asks),
+            history,
+            top,
+        }
+    }
+}
+
+impl From<Snapshot> for MatchingEngine {
+    fn from(snapshot: Snapshot) -> Self {
+        let mut engine = MatchingEngine {
+            ordinal: snapshot.ordinal,
+            ..Default::default()
+        };
+
+        let top: HashSet<u64> = snapshot.top.into_iter().collect();
+        for mut level in snapshot
+            .asks
+            .into_values()
+            .chain(snapshot.bids.into_values())
+        {
+            // The heap-based engine didn't store its levels in time order
+            level.sort_unstable_by_key(|order| order.ordinal);
+            for order in level {
+                let signer = engine.signers.intern(order.signer);
+                let top = top.contains(&order.ordinal);
+                engine.rest(
+                    Node {
+                        ordinal: order.ordinal,
+                        signer,
+                        price: order.price,
+                        amount: order.amount,
+                        remaining: order.remaining,
+                        side: order.side,
+                        prev: NIL,
+                        next: NIL,
+                    },
+                    top,
+                );
+            }
+        }
+
+        for receipt in snapshot.history {
+            for m in receipt.matches {
+                let maker = engine.signers.intern(m.signer);
+                engine.history.push(Fill {
+                    ordinal: receipt.ordinal,
+                    maker_ordinal: m.ordinal,
+                    maker,
+                    price: m.price,
+                    amount: m.amount,
+                    remaining: m.remaining,
+                    side: m.side,
+                });
+            }
+        }
+        engine
+    }
+}
+
+impl Serialize for MatchingEngine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Snapshot::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MatchingEngine {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Snapshot::deserialize(deserializer).map(MatchingEngine::from)
     }
 }
 
@@ -155,6 +956,8 @@ mod tests {
     #![allow(non_snake_case)]
 
     use super::*;
+    use crate::core::reference;
+    use octopus_common::core::types::{Price, Quantity};
 
     #[test]
     fn test_MatchingEngine_process_partially_match_order() {
@@ -162,8 +965,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -173,8 +976,8 @@ mod tests {
 
         let bob_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -183,16 +986,16 @@ mod tests {
         assert_eq!(
             bob_receipt.matches,
             vec![PartialOrder {
-                price: 10,
-                amount: 1,
-                remaining: 0,
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                remaining: Quantity::from(0),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
                 ordinal: 1
             }]
         );
-        assert!(matching_engine.asks.is_empty());
-        assert_eq!(matching_engine.bids.len(), 1);
+        assert!(matching_engine.levels(&Side::Sell) == 0);
+        assert_eq!(matching_engine.levels(&Side::Buy), 1);
     }
 
     #[test]
@@ -201,8 +1004,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -212,8 +1015,8 @@ mod tests {
 
         let bob_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -222,9 +1025,9 @@ mod tests {
         assert_eq!(
             bob_receipt.matches,
             vec![PartialOrder {
-                price: 10,
-                amount: 2,
-                remaining: 0,
+                price: Price::from(10),
+                amount: Quantity::from(2),
+                remaining: Quantity::from(0),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
                 ordinal: 1
@@ -232,8 +1035,8 @@ mod tests {
         );
 
         // A fully matched order doesn't remain in the book
-        assert!(matching_engine.asks.is_empty());
-        assert!(matching_engine.bids.is_empty());
+        assert!(matching_engine.levels(&Side::Sell) == 0);
+        assert!(matching_engine.levels(&Side::Buy) == 0);
     }
 
     #[test]
@@ -242,8 +1045,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -253,8 +1056,8 @@ mod tests {
 
         let charlie_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
             })
@@ -264,8 +1067,8 @@ mod tests {
 
         let bob_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -275,17 +1078,17 @@ mod tests {
             bob_receipt.matches,
             vec![
                 PartialOrder {
-                    price: 10,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(10),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Sell,
                     signer: "ALICE".to_string(),
                     ordinal: 1
                 },
                 PartialOrder {
-                    price: 10,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(10),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Sell,
                     signer: "CHARLIE".to_string(),
                     ordinal: 2
@@ -293,8 +1096,8 @@ mod tests {
             ]
         );
         // A fully matched order doesn't remain in the book
-        assert!(matching_engine.asks.is_empty());
-        assert!(matching_engine.bids.is_empty());
+        assert!(matching_engine.levels(&Side::Sell) == 0);
+        assert!(matching_engine.levels(&Side::Buy) == 0);
     }
 
     #[test]
@@ -303,8 +1106,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 11,
-                amount: 1,
+                price: Price::from(11),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -314,8 +1117,8 @@ mod tests {
 
         let charlie_receipt = matching_engine
             .process(Order {
-                price: 9,
-                amount: 1,
+                price: Price::from(9),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
             })
@@ -325,8 +1128,8 @@ mod tests {
 
         let bob_receipt = matching_engine
             .process(Order {
-                price: 15,
-                amount: 2,
+                price: Price::from(15),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -336,17 +1139,17 @@ mod tests {
             bob_receipt.matches,
             vec![
                 PartialOrder {
-                    price: 9,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(9),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Sell,
                     signer: "CHARLIE".to_string(),
                     ordinal: 2
                 },
                 PartialOrder {
-                    price: 11,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(11),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Sell,
                     signer: "ALICE".to_string(),
                     ordinal: 1
@@ -354,8 +1157,8 @@ mod tests {
             ]
         );
         // A fully matched order doesn't remain in the book
-        assert!(matching_engine.asks.is_empty());
-        assert!(matching_engine.bids.is_empty());
+        assert!(matching_engine.levels(&Side::Sell) == 0);
+        assert!(matching_engine.levels(&Side::Buy) == 0);
     }
 
     #[test]
@@ -364,8 +1167,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Buy,
                 signer: "ALICE".to_string(),
             })
@@ -375,8 +1178,8 @@ mod tests {
 
         let charlie_receipt = matching_engine
             .process(Order {
-                price: 5,
-                amount: 1,
+                price: Price::from(5),
+                amount: Quantity::from(1),
                 side: Side::Buy,
                 signer: "CHARLIE".to_string(),
             })
@@ -386,8 +1189,8 @@ mod tests {
 
         let bob_receipt = matching_engine
             .process(Order {
-                price: 4,
-                amount: 2,
+                price: Price::from(4),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "BOB".to_string(),
             })
@@ -397,17 +1200,17 @@ mod tests {
             bob_receipt.matches,
             vec![
                 PartialOrder {
-                    price: 5,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(5),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Buy,
                     signer: "CHARLIE".to_string(),
                     ordinal: 2
                 },
                 PartialOrder {
-                    price: 10,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(10),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Buy,
                     signer: "ALICE".to_string(),
                     ordinal: 1
@@ -415,8 +1218,8 @@ mod tests {
             ]
         );
         // A fully matched order doesn't remain in the book
-        assert!(matching_engine.asks.is_empty());
-        assert!(matching_engine.bids.is_empty());
+        assert!(matching_engine.levels(&Side::Sell) == 0);
+        assert!(matching_engine.levels(&Side::Buy) == 0);
     }
 
     #[test]
@@ -425,8 +1228,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -436,8 +1239,8 @@ mod tests {
 
         let charlie_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
             })
@@ -447,8 +1250,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "ALICE".to_string(),
             })
@@ -457,17 +1260,17 @@ mod tests {
         assert_eq!(
             alice_receipt.matches,
             vec![PartialOrder {
-                price: 10,
-                amount: 1,
-                remaining: 0,
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                remaining: Quantity::from(0),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
                 ordinal: 2
             }]
         );
         // A fully matched order doesn't remain in the book
-        assert_eq!(matching_engine.asks.len(), 1);
-        assert_eq!(matching_engine.bids.len(), 1);
+        assert_eq!(matching_engine.levels(&Side::Sell), 1);
+        assert_eq!(matching_engine.levels(&Side::Buy), 1);
     }
 
     #[test]
@@ -476,8 +1279,8 @@ mod tests {
 
         let alice_receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -487,50 +1290,655 @@ mod tests {
 
         let bob_receipt = matching_engine
             .process(Order {
-                price: 11,
-                amount: 2,
+                price: Price::from(11),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "BOB".to_string(),
             })
             .unwrap();
 
         assert_eq!(bob_receipt.matches, vec![]);
-        assert_eq!(matching_engine.asks.len(), 2);
+        assert_eq!(matching_engine.levels(&Side::Sell), 2);
     }
 
     #[test]
     fn test_MatchingEngine_process_increment_ordinal_matching_engine() {
         let mut matching_engine = MatchingEngine::new();
-        assert_eq!(matching_engine.ordinal, 0);
+        assert_eq!(matching_engine.ordinal(), 0);
         let receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Buy,
                 signer: "ALICE".to_string(),
             })
             .unwrap();
-        assert_eq!(receipt.ordinal, matching_engine.ordinal);
+        assert_eq!(receipt.ordinal, matching_engine.ordinal());
 
         let receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
             .unwrap();
-        assert_eq!(receipt.ordinal, matching_engine.ordinal);
+        assert_eq!(receipt.ordinal, matching_engine.ordinal());
 
         let receipt = matching_engine
             .process(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Buy,
                 signer: "CHARLIE".to_string(),
             })
             .unwrap();
-        assert_eq!(receipt.ordinal, matching_engine.ordinal);
-        assert_eq!(matching_engine.ordinal, 3);
+        assert_eq!(receipt.ordinal, matching_engine.ordinal());
+        assert_eq!(matching_engine.ordinal(), 3);
+    }
+
+    #[test]
+    fn test_MatchingEngine_cancel_removes_resting_order() {
+        let mut matching_engine = MatchingEngine::new();
+
+        for (amount, side) in [(1, Side::Sell), (2, Side::Sell), (3, Side::Buy)] {
+            matching_engine
+                .process(Order {
+                    price: if side == Side::Sell {
+                        Price::from(10)
+                    } else {
+                        Price::from(5)
+                    },
+                    amount: Quantity::from(amount),
+                    side,
+                    signer: "ALICE".to_string(),
+                })
+                .unwrap();
+        }
+
+        let cancelled = matching_engine.cancel(1).unwrap();
+        assert_eq!(cancelled.amount, Quantity::from(1));
+        assert_eq!(
+            matching_engine.level(&Side::Sell, Price::from(10)).count(),
+            1
+        );
+
+        assert_eq!(matching_engine.cancel(3).unwrap().side, Side::Buy);
+        assert!(matching_engine.levels(&Side::Buy) == 0);
+
+        assert_eq!(matching_engine.cancel(1), None);
+        assert_eq!(matching_engine.cancel(42), None);
+    }
+
+    #[test]
+    fn test_MatchingEngine_cancel_all_removes_the_orders_of_a_signer() {
+        let mut matching_engine = MatchingEngine::new();
+        for (signer, side, price) in [
+            ("ALICE", Side::Sell, 10),
+            ("BOB", Side::Sell, 10),
+            ("ALICE", Side::Buy, 5),
+            ("ALICE", Side::Sell, 11),
+        ] {
+            matching_engine
+                .process(order(signer, side, price, 1))
+                .unwrap();
+        }
+
+        let ordinals = |orders: Vec<PartialOrder>| {
+            orders.iter().map(|order| order.ordinal).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ordinals(matching_engine.cancel_all("ALICE", Some(&Side::Sell))),
+            vec![1, 4]
+        );
+        assert_eq!(matching_engine.open_orders("ALICE"), 1);
+        assert_eq!(ordinals(matching_engine.cancel_all("ALICE", None)), vec![3]);
+        assert!(matching_engine.cancel_all("ALICE", None).is_empty());
+        assert!(matching_engine.cancel_all("CHARLIE", None).is_empty());
+        assert_eq!(ordinals(matching_engine.orders().collect()), vec![2]);
+    }
+
+    #[test]
+    fn test_MatchingEngine_uncross_executes_the_most_volume_at_one_price() {
+        let mut matching_engine = MatchingEngine::new();
+        for (signer, side, price, amount) in [
+            ("ALICE", Side::Buy, 12, 5),
+            ("ALICE", Side::Buy, 11, 5),
+            ("ALICE", Side::Buy, 10, 10),
+            ("BOB", Side::Sell, 9, 10),
+            ("BOB", Side::Sell, 10, 5),
+            ("BOB", Side::Sell, 12, 10),
+        ] {
+            let receipt = matching_engine.collect(order(signer, side, price, amount));
+            assert!(receipt.matches.is_empty());
+        }
+
+        // 10 at 9, 15 at 10, 10 at 11 and 5 at 12
+        assert_eq!(
+            matching_engine.clearing(),
+            Some(Clearing {
+                price: Price::from(10),
+                volume: Quantity::from(15),
+                surplus: Quantity::from(5),
+                surplus_side: Some(Side::Buy),
+            })
+        );
+
+        let cross = |bid, ask, amount| Cross {
+            bid,
+            buyer: "ALICE".to_string(),
+            ask,
+            seller: "BOB".to_string(),
+            price: Price::from(10),
+            amount: Quantity::from(amount),
+        };
+        assert_eq!(
+            matching_engine.uncross(),
+            vec![cross(1, 4, 5), cross(2, 4, 5), cross(3, 5, 5)]
+        );
+        // The rest is left for continuous trading
+        assert_eq!(matching_engine.clearing(), None);
+        assert_eq!(matching_engine.best(&Side::Buy), Some(Price::from(10)));
+        assert_eq!(matching_engine.best(&Side::Sell), Some(Price::from(12)));
+        let resting: Vec<_> = matching_engine
+            .orders()
+            .map(|order| (order.ordinal, order.remaining))
+            .collect();
+        assert_eq!(
+            resting,
+            vec![(6, Quantity::from(10)), (3, Quantity::from(5))]
+        );
+        assert_eq!(matching_engine.last_price(), Some(Price::from(10)));
+        assert!(matching_engine.uncross().is_empty());
+    }
+
+    #[test]
+    fn test_MatchingEngine_clearing_breaks_ties() {
+        let clearing = |orders: &[(&str, Side, u64, u64)], last: Option<u64>| {
+            let mut matching_engine = MatchingEngine::new();
+            if let Some(last) = last {
+                matching_engine
+                    .process(order("ALICE", Side::Sell, last, 1))
+                    .unwrap();
+                matching_engine
+                    .process(order("BOB", Side::Buy, last, 1))
+                    .unwrap();
+            }
+            for (signer, side, price, amount) in orders {
+                matching_engine.collect(order(signer, side.clone(), *price, *amount));
+            }
+            matching_engine.clearing().map(|c| c.price)
+        };
+
+        // More buyers than sellers at either price
+        let pressure = [("ALICE", Side::Buy, 11, 20), ("BOB", Side::Sell, 10, 10)];
+        assert_eq!(clearing(&pressure, None), Some(Price::from(11)));
+        // More sellers than buyers at either price
+        let pressure = [("ALICE", Side::Buy, 11, 10), ("BOB", Side::Sell, 10, 20)];
+        assert_eq!(clearing(&pressure, None), Some(Price::from(10)));
+        // Even at either price
+        let even = [("ALICE", Side::Buy, 11, 10), ("BOB", Side::Sell, 10, 10)];
+        assert_eq!(clearing(&even, None), Some(Price::from(10)));
+        assert_eq!(clearing(&even, Some(11)), Some(Price::from(11)));
+        assert_eq!(clearing(&even, Some(9)), Some(Price::from(10)));
+        // Equally close to the last trade
+        let wide = [("ALICE", Side::Buy, 12, 10), ("BOB", Side::Sell, 10, 10)];
+        assert_eq!(clearing(&wide, Some(11)), Some(Price::from(10)));
+        // Not crossed
+        let apart = [("ALICE", Side::Buy, 9, 10), ("BOB", Side::Sell, 10, 10)];
+        assert_eq!(clearing(&apart, None), None);
+    }
+
+    #[test]
+    fn test_MatchingEngine_uncross_skips_self_matches() {
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine.collect(order("ALICE", Side::Buy, 11, 10));
+        matching_engine.collect(order("ALICE", Side::Sell, 10, 5));
+        matching_engine.collect(order("BOB", Side::Sell, 10, 5));
+
+        // ALICE's ask can't fill ALICE's bid, so only BOB's ask counts
+        assert_eq!(
+            matching_engine.clearing().map(|clearing| clearing.volume),
+            Some(Quantity::from(5))
+        );
+        assert_eq!(
+            matching_engine.uncross(),
+            vec![Cross {
+                bid: 1,
+                buyer: "ALICE".to_string(),
+                ask: 3,
+                seller: "BOB".to_string(),
+                price: Price::from(10),
+                amount: Quantity::from(5),
+            }]
+        );
+        assert_eq!(matching_engine.open_orders("ALICE"), 2);
+        assert_eq!(matching_engine.open_orders("BOB"), 0);
+        // ALICE's orders still cross each other, but there is nothing left to execute
+        assert_eq!(matching_engine.clearing(), None);
+        assert!(matching_engine.uncross().is_empty());
+    }
+
+    /// The units each resting ask of 10, 20 and 30 at the same price gets of a buy of `amount`, by ordinal
+    fn allocate(allocation: Allocation, lot_size: u64, amount: u64) -> Vec<(u64, Quantity)> {
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine.set_allocation(allocation, Quantity::from(lot_size));
+        for (signer, amount) in [("ALICE", 10), ("BOB", 20), ("CHARLIE", 30)] {
+            matching_engine
+                .process(order(signer, Side::Sell, 10, amount))
+                .unwrap();
+        }
+        let receipt = matching_engine
+            .process(order("DAVE", Side::Buy, 10, amount))
+            .unwrap();
+        receipt
+            .matches
+            .iter()
+            .map(|m| (m.ordinal, m.amount))
+            .collect()
+    }
+
+    fn shares(shares: &[(u64, u64)]) -> Vec<(u64, Quantity)> {
+        shares
+            .iter()
+            .map(|(ordinal, amount)| (*ordinal, Quantity::from(*amount)))
+            .collect()
+    }
+
+    #[test]
+    fn test_MatchingEngine_pro_rata_allocates_by_size() {
+        let pro_rata = Allocation::ProRata {
+            min_allocation: Quantity::ZERO,
+        };
+        // Exactly a sixth, a third and a half
+        assert_eq!(
+            allocate(pro_rata, 1, 30),
+            shares(&[(1, 5), (2, 10), (3, 15)])
+        );
+        // 1.67, 3.33 and 5 are rounded down, and the unit that leaves over goes to the oldest order
+        assert_eq!(allocate(pro_rata, 1, 10), shares(&[(1, 2), (2, 3), (3, 5)]));
+        // 4.17, 8.33 and 12.5 are rounded down to lots of 5, and the oldest order gets the 10 left over
+        assert_eq!(
+            allocate(pro_rata, 5, 25),
+            shares(&[(1, 10), (2, 5), (3, 10)])
+        );
+        // More than the level has fills it entirely
+        assert_eq!(
+            allocate(pro_rata, 1, 100),
+            shares(&[(1, 10), (2, 20), (3, 30)])
+        );
+    }
+
+    #[test]
+    fn test_MatchingEngine_pro_rata_skips_allocations_below_the_minimum() {
+        let pro_rata = |min_allocation| Allocation::ProRata {
+            min_allocation: Quantity::from(min_allocation),
+        };
+        // 1.17, 2.33 and 3.5 are rounded down to 1, 2 and 3, the unit left over goes to the oldest order
+        assert_eq!(
+            allocate(pro_rata(0), 1, 7),
+            shares(&[(1, 2), (2, 2), (3, 3)])
+        );
+        // Only 3 is at least the minimum, and what is left over is filled oldest first
+        assert_eq!(allocate(pro_rata(3), 1, 7), shares(&[(1, 4), (3, 3)]));
+        // Nobody gets the minimum, so it is all oldest first
+        assert_eq!(allocate(pro_rata(10), 1, 7), shares(&[(1, 7)]));
+    }
+
+    #[test]
+    fn test_MatchingEngine_top_order_is_filled_first() {
+        let top_order = Allocation::TopOrder {
+            min_allocation: Quantity::ZERO,
+        };
+        // ALICE's order opened the level, the rest is shared 6.4 and 9.6 between 20 and 30 and the unit left over goes to BOB
+        assert_eq!(
+            allocate(top_order, 1, 26),
+            shares(&[(1, 10), (2, 7), (3, 9)])
+        );
+        assert_eq!(allocate(top_order, 1, 4), shares(&[(1, 4)]));
+
+        // Orders that rest behind the best price aren't top orders, even once their price becomes the best
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine.set_allocation(top_order, Quantity::from(1));
+        for (signer, price) in [("BOB", 10), ("ALICE", 11), ("CHARLIE", 11)] {
+            matching_engine
+                .process(order(signer, Side::Sell, price, 10))
+                .unwrap();
+        }
+        matching_engine.cancel(1);
+        let receipt = matching_engine
+            .process(order("DAVE", Side::Buy, 11, 10))
+            .unwrap();
+        let fills: Vec<_> = receipt
+            .matches
+            .iter()
+            .map(|m| (m.ordinal, m.amount))
+            .collect();
+        assert_eq!(fills, shares(&[(2, 5), (3, 5)]));
+    }
+
+    #[test]
+    fn test_MatchingEngine_restores_top_orders_from_snapshots() {
+        let top_order = Allocation::TopOrder {
+            min_allocation: Quantity::ZERO,
+        };
+        let mut matching_engine = MatchingEngine::new();
+        for (signer, price) in [("ALICE", 10), ("BOB", 10), ("CHARLIE", 10)] {
+            matching_engine
+                .process(order(signer, Side::Sell, price, 10))
+                .unwrap();
+        }
+
+        let json = serde_json::to_string(&matching_engine).unwrap();
+        let mut restored: MatchingEngine = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        // ALICE's order still opened the level and is filled first
+        restored.set_allocation(top_order, Quantity::from(1));
+        let receipt = restored.process(order("DAVE", Side::Buy, 10, 12)).unwrap();
+        let fills: Vec<_> = receipt
+            .matches
+            .iter()
+            .map(|m| (m.ordinal, m.amount))
+            .collect();
+        assert_eq!(fills, shares(&[(1, 10), (2, 1), (3, 1)]));
+    }
+
+    #[test]
+    fn test_MatchingEngine_pro_rata_skips_self_matches() {
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine.set_allocation(
+            Allocation::ProRata {
+                min_allocation: Quantity::ZERO,
+            },
+            Quantity::from(1),
+        );
+        for (signer, amount) in [("ALICE", 10), ("BOB", 10), ("CHARLIE", 30)] {
+            matching_engine
+                .process(order(signer, Side::Sell, 10, amount))
+                .unwrap();
+        }
+        let receipt = matching_engine
+            .process(order("BOB", Side::Buy, 10, 8))
+            .unwrap();
+        let fills: Vec<_> = receipt
+            .matches
+            .iter()
+            .map(|m| (m.ordinal, m.amount))
+            .collect();
+        // 2 and 6 of ALICE's 10 and CHARLIE's 30, BOB's own order keeps its place
+        assert_eq!(fills, shares(&[(1, 2), (3, 6)]));
+        assert_eq!(matching_engine.open_orders("BOB"), 1);
+    }
+
+    fn order(signer: &str, side: Side, price: u64, amount: u64) -> Order {
+        Order {
+            price: Price::from(price),
+            amount: Quantity::from(amount),
+            side,
+            signer: signer.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_MatchingEngine_process_does_not_overfill_across_levels() {
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine
+            .process(order("ALICE", Side::Sell, 10, 2))
+            .unwrap();
+        matching_engine
+            .process(order("CHARLIE", Side::Sell, 12, 1))
+            .unwrap();
+
+        let receipt = matching_engine
+            .process(order("BOB", Side::Buy, 20, 1))
+            .unwrap();
+        assert_eq!(receipt.matches.len(), 1);
+        assert_eq!(receipt.matches[0].amount, Quantity::from(1));
+        assert_eq!(receipt.matches[0].remaining, Quantity::from(1));
+        assert_eq!(
+            matching_engine.level(&Side::Sell, Price::from(10)).count(),
+            1
+        );
+        assert_eq!(
+            matching_engine.level(&Side::Sell, Price::from(12)).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_MatchingEngine_process_matches_only_what_is_left() {
+        let mut matching_engine = MatchingEngine::new();
+        matching_engine
+            .process(order("ALICE", Side::Sell, 10, 5))
+            .unwrap();
+        matching_engine
+            .process(order("BOB", Side::Buy, 10, 2))
+            .unwrap();
+
+        // ALICE's order has 3 units left, BOB's remainder rests with 7
+        let receipt = matching_engine
+            .process(order("BOB", Side::Buy, 10, 10))
+            .unwrap();
+        assert_eq!(receipt.matches[0].amount, Quantity::from(3));
+        assert_eq!(receipt.matches[0].remaining, Quantity::from(0));
+        let resting: Vec<_> = matching_engine.level(&Side::Buy, Price::from(10)).collect();
+        assert_eq!(
+            (resting[0].amount, resting[0].remaining),
+            (Quantity::from(7), Quantity::from(7))
+        );
+
+        // The remainder can't be filled beyond what is left of it
+        let receipt = matching_engine
+            .process(order("CHARLIE", Side::Sell, 10, 9))
+            .unwrap();
+        assert_eq!(receipt.matches[0].amount, Quantity::from(7));
+        assert_eq!(
+            matching_engine
+                .level(&Side::Sell, Price::from(10))
+                .next()
+                .unwrap()
+                .amount,
+            Quantity::from(2)
+        );
+    }
+
+    #[test]
+    fn test_MatchingEngine_self_matches_keep_their_place() {
+        let mut matching_engine = MatchingEngine::new();
+        for signer in ["ALICE", "BOB", "ALICE"] {
+            matching_engine
+                .process(order(signer, Side::Sell, 10, 1))
+                .unwrap();
+        }
+
+        let receipt = matching_engine
+            .process(order("ALICE", Side::Buy, 10, 5))
+            .unwrap();
+        assert_eq!(receipt.matches.len(), 1);
+        assert_eq!(receipt.matches[0].signer, "BOB");
+        assert_eq!(
+            matching_engine
+                .level(&Side::Sell, Price::from(10))
+                .map(|order| order.ordinal)
+                .collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            matching_engine.level(&Side::Buy, Price::from(10)).count(),
+            1
+        );
+    }
+
+    /// A xorshift generator, so the differential test is reproducible without extra dependencies
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+    }
+
+    #[test]
+    fn test_MatchingEngine_tracks_open_orders_and_prices() {
+        let mut matching_engine = MatchingEngine::new();
+        assert_eq!(matching_engine.best(&Side::Buy), None);
+        assert_eq!(matching_engine.last_price(), None);
+
+        for (signer, side, price, amount) in [
+            ("ALICE", Side::Sell, 11, 1),
+            ("ALICE", Side::Sell, 12, 1),
+            ("ALICE", Side::Sell, 13, 1),
+            ("BOB", Side::Buy, 9, 1),
+        ] {
+            matching_engine
+                .process(order(signer, side, price, amount))
+                .unwrap();
+        }
+        assert_eq!(matching_engine.open_orders("ALICE"), 3);
+        assert_eq!(matching_engine.open_orders("BOB"), 1);
+        assert_eq!(matching_engine.open_orders("CHARLIE"), 0);
+        assert_eq!(matching_engine.best(&Side::Buy), Some(Price::from(9)));
+        assert_eq!(matching_engine.best(&Side::Sell), Some(Price::from(11)));
+
+        // Filled and cancelled orders are gone, partially filled ones still count
+        matching_engine
+            .process(order("BOB", Side::Buy, 12, 2))
+            .unwrap();
+        matching_engine.cancel(3).unwrap();
+        assert_eq!(matching_engine.open_orders("ALICE"), 0);
+        assert_eq!(matching_engine.last_price(), Some(Price::from(12)));
+        assert_eq!(matching_engine.best(&Side::Sell), None);
+
+        matching_engine
+            .process(order("CHARLIE", Side::Sell, 9, 1))
+            .unwrap();
+        assert_eq!(matching_engine.open_orders("BOB"), 0);
+        assert_eq!(matching_engine.last_price(), Some(Price::from(9)));
+    }
+
+    fn sorted_by_ordinal(mut orders: Vec<PartialOrder>) -> Vec<PartialOrder> {
+        orders.sort_unstable_by_key(|order| order.ordinal);
+        orders
+    }
+
+    fn reference_book(reference: &reference::MatchingEngine) -> Vec<PartialOrder> {
+        let orders = reference
+            .asks
+            .values()
+            .chain(reference.bids.values())
+            .flat_map(|level| level.iter().cloned())
+            .collect();
+        sorted_by_ordinal(orders)
+    }
+
+    fn trades(matching_engine: &MatchingEngine) -> Vec<(u64, PartialOrder)> {
+        matching_engine
+            .history
+            .iter()
+            .map(|fill| (fill.ordinal, matching_engine.matched_order(fill)))
+            .collect()
+    }
+
+    fn reference_trades(reference: &reference::MatchingEngine) -> Vec<(u64, PartialOrder)> {
+        reference
+            .history
+            .iter()
+            .flat_map(|receipt| receipt.matches.iter().map(|m| (receipt.ordinal, m.clone())))
+            .collect()
+    }
+
+    /// Runs random orders and cancels through the reference engine too and expects the same receipts and books
+    #[test]
+    fn test_MatchingEngine_matches_reference_engine() {
+        let signers = ["ALICE", "BOB", "CHARLIE", "DAVE"];
+        for seed in 1..=200u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut matching_engine = MatchingEngine::new();
+            let mut reference = reference::MatchingEngine::new();
+
+            for step in 0..300 {
+                if rng.below(10) < 7 {
+                    let side = if rng.below(2) == 0 {
+                        Side::Buy
+                    } else {
+                        Side::Sell
+                    };
+                    let order = order(
+                        signers[rng.below(4) as usize],
+                        side,
+                        95 + rng.below(11),
+                        1 + rng.below(10),
+                    );
+                    assert_eq!(
+                        matching_engine.process(order.clone()),
+                        reference.process(order),
+                        "seed {} step {}",
+                        seed,
+                        step
+                    );
+                } else {
+                    let ordinal = 1 + rng.below(matching_engine.ordinal() + 2);
+                    assert_eq!(
+                        matching_engine.cancel(ordinal),
+                        reference.cancel(ordinal),
+                        "seed {} step {}",
+                        seed,
+                        step
+                    );
+                }
+
+                assert_eq!(matching_engine.levels(&Side::Buy), reference.bids.len());
+                assert_eq!(matching_engine.levels(&Side::Sell), reference.asks.len());
+            }
+
+            assert_eq!(matching_engine.ordinal(), reference.ordinal);
+            assert_eq!(
+                sorted_by_ordinal(matching_engine.orders().collect()),
+                reference_book(&reference),
+                "seed {}",
+                seed
+            );
+            assert_eq!(trades(&matching_engine), reference_trades(&reference));
+        }
+    }
+
+    #[test]
+    fn test_MatchingEngine_restores_snapshots_of_the_reference_engine() {
+        let mut reference = reference::MatchingEngine::new();
+        let mut matching_engine = MatchingEngine::new();
+        for (signer, side, price, amount) in [
+            ("ALICE", Side::Sell, 10, 3),
+            ("BOB", Side::Sell, 10, 1),
+            ("CHARLIE", Side::Sell, 11, 1),
+            ("DAVE", Side::Buy, 10, 2),
+            ("DAVE", Side::Buy, 9, 4),
+            ("ALICE", Side::Buy, 8, 1),
+        ] {
+            let order = order(signer, side, price, amount);
+            reference.process(order.clone()).unwrap();
+            matching_engine.process(order).unwrap();
+        }
+
+        let restored: MatchingEngine =
+            serde_json::from_str(&serde_json::to_string(&reference).unwrap()).unwrap();
+        assert_eq!(
+            restored.orders().collect::<Vec<_>>(),
+            matching_engine.orders().collect::<Vec<_>>()
+        );
+        assert_eq!(trades(&restored), trades(&matching_engine));
+        assert_eq!(restored.ordinal(), 6);
+
+        // Writing and restoring keeps the queues in time order
+        let json = serde_json::to_string(&matching_engine).unwrap();
+        let restored: MatchingEngine = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.orders().collect::<Vec<_>>(),
+            matching_engine.orders().collect::<Vec<_>>()
+        );
+        assert_eq!(trades(&restored), trades(&matching_engine));
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
     }
 }
@@ -1 +1,3 @@
 pub mod matching;
+#[cfg(test)]
+mod reference;
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BinaryHeap};
+
+use octopus_common::{
+    core::types::{Order, PartialOrder, Price, Quantity, Receipt, Side},
+    errors::ApplicationError,
+};
+use serde::{Deserialize, Serialize};
+
+/// The original matching engine with a heap per price level, kept as the reference that [`super::matching::MatchingEngine`] is tested against
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct MatchingEngine {
+    /// The last sequence number
+    pub ordinal: u64,
+
+    /// The "Bid" or "Buy" side of the order book. Ordered by ordinal number.
+    pub bids: BTreeMap<Price, BinaryHeap<PartialOrder>>,
+    /// The "Ask" or "Sell" side of the order book. Ordered by ordinal number.
+    pub asks: BTreeMap<Price, BinaryHeap<PartialOrder>>,
+    /// Previous matches for record keeping
+    pub history: Vec<Receipt>,
+}
+
+impl MatchingEngine {
+    /// Creates a new [`MatchingEngine`] with an ordinal of 0 and empty books
+    pub fn new() -> Self {
+        MatchingEngine {
+            ordinal: 0,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Processes an [`Order`] and returns a [`Receipt`]
+    /// This includes matching the order to whatever is in the current books and adding the remainder (if any) to the book for future matching.
+    pub fn process(&mut self, order: Order) -> Result<Receipt, ApplicationError> {
+        // Increment the ordinal number for this order
+        self.ordinal += 1;
+        let ordinal = self.ordinal;
+
+        let original_amount = order.amount;
+        let mut partial = order.into_partial_order(ordinal, original_amount);
+
+        // Orders are matched to the opposite side
+        let receipt = match &partial.side {
+            Side::Buy => {
+                // Fetch all orders in the expected price range from this side of the orderbook
+                let orderbook_entry = self.asks.range_mut(Price::from(u64::MIN)..=partial.price);
+
+                let receipt = MatchingEngine::match_order(&partial, orderbook_entry, ordinal)?;
+                let matched_amount = receipt
+                    .matches
+                    .iter()
+                    .fold(Quantity::ZERO, |sum, m| sum + m.amount);
+
+                // The order wasn't fully matched
+                if matched_amount < original_amount {
+                    partial.amount = original_amount - matched_amount;
+                    partial.remaining = partial.amount;
+                    let price = partial.price;
+                    let bids = self.bids.entry(price).or_insert(vec![].into());
+                    bids.push(partial);
+                }
+                receipt
+            }
+            Side::Sell => {
+                // Fetch all orders in the expected price range from this side of the orderbook
+                let orderbook_entry = self.bids.range_mut(partial.price..=Price::from(u64::MAX));
+
+                let receipt = MatchingEngine::match_order(&partial, orderbook_entry, ordinal)?;
+                let matched_amount = receipt
+                    .matches
+                    .iter()
+                    .fold(Quantity::ZERO, |sum, m| sum + m.amount);
+
+                // The order wasn't fully matched
+                if matched_amount < original_amount {
+                    partial.amount = original_amount - matched_amount;
+                    partial.remaining = partial.amount;
+                    let price = partial.price;
+                    let bids = self.asks.entry(price).or_insert(vec![].into());
+                    bids.push(partial);
+                }
+                receipt
+            }
+        };
+
+        for m in &receipt.matches {
+            tracing::debug!(
+                ordinal,
+                maker_ordinal = m.ordinal,
+                maker = %m.signer,
+                price = %m.price,
+                amount = %m.amount,
+                "matched"
+            );
+        }
+
+        // Cleanup: Remove price entries without orders from the orderbook
+        self.asks.retain(|_, orders| !orders.is_empty());
+        self.bids.retain(|_, orders| !orders.is_empty());
+
+        // Keep a log of matches
+        self.history.push(receipt.clone());
+        Ok(receipt)
+    }
+
+    /// Removes the resting order with `ordinal` from the books, if it is still there
+    pub fn cancel(&mut self, ordinal: u64) -> Option<PartialOrder> {
+        for book in [&mut self.bids, &mut self.asks] {
+            let found = book.iter().find_map(|(price, orders)| {
+                orders
+                    .iter()
+                    .find(|order| order.ordinal == ordinal)
+                    .map(|order| (*price, order.clone()))
+            });
+            if let Some((price, order)) = found {
+                let orders = book.get_mut(&price).expect("the level was just found");
+                orders.retain(|order| order.ordinal != ordinal);
+                if orders.is_empty() {
+                    book.remove(&price);
+                }
+                return Some(order);
+            }
+        }
+        None
+    }
+
+    /// Matches an order to the provided order book side.
+    /// # Parameters
+    /// - `orderbook_entry`: a pre-filtered iterator for order book_entry in the correct price range
+    /// - `ordinal` the next ordinal number to use if a position is opened
+    fn match_order<'a, T>(
+        order: &PartialOrder,
+        mut orderbook_entry: T,
+        ordinal: u64,
+    ) -> Result<Receipt, ApplicationError>
+    where
+        T: Iterator<Item = (&'a Price, &'a mut BinaryHeap<PartialOrder>)>,
+    {
+        let mut remaining_amount = order.amount;
+        let mut matches = vec![];
+
+        // Each matching position's amount is subtraced
+        'outer: while !remaining_amount.is_zero() {
+            // The iterator contains all orderbook_entry of a price point
+            match orderbook_entry.next() {
+                Some((price, orderbook_entry)) => {
+                    // Self-matches are illegal
+                    let mut self_matches = vec![];
+                    // pop a position off the heap
+                    'ask_loop: while let Some(mut pos) = orderbook_entry.pop() {
+                        // A self-match is illegal so we keep the order and skip the matching for it
+                        if pos.signer == order.signer {
+                            self_matches.push(pos);
+                            continue 'ask_loop;
+                        }
+
+                        // Using checked_sub guards against overlow errors
+                        match pos.remaining.checked_sub(remaining_amount) {
+                            Some(_) => {
+                                matches.push(PartialOrder::take_from(
+                                    &mut pos,
+                                    remaining_amount,
+                                    *price,
+                                ));
+                                if !pos.remaining.is_zero() {
+                                    orderbook_entry.push(pos);
+                                }
+                                remaining_amount = Quantity::ZERO;
+                                break 'ask_loop;
+                            }
+                            None => {
+                                remaining_amount -= pos.remaining;
+                                pos.amount = pos.remaining;
+                                pos.remaining = Quantity::ZERO;
+                                matches.push(pos);
+                            }
+                        }
+                    }
+
+                    // Return the self-matched orders at their price in the book. To avoid an infinite loop, we put the orders back after finishing the matching for this price point
+                    self_matches
+                        .into_iter()
+                        .for_each(|m| orderbook_entry.push(m));
+                }
+                // Nothing left to match with
+                None => break 'outer,
+            }
+        }
+
+        Ok(Receipt {
+            ordinal,
+            matches,
+            fees: vec![],
+            queued: false,
+        })
+    }
+}
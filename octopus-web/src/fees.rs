@@ -0,0 +1,240 @@
+use octopus_common::core::{decimal::Decimal, types::Notional};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+
+/// How far back the trading volume that decides an account's fee tier reaches, in milliseconds
+pub const VOLUME_WINDOW: u64 = 30 * 24 * 60 * 60 * 1000;
+/// The account fees are credited to unless configured otherwise
+pub const DEFAULT_FEE_ACCOUNT: &str = "FEES";
+/// Basis points in a whole, the most a rate can be
+const MAX_BPS: u32 = 10_000;
+
+/// The maker and taker rates of accounts that traded at least `min_volume` within the [`VOLUME_WINDOW`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeTier {
+    pub min_volume: Notional,
+    pub maker_bps: u32,
+    pub taker_bps: u32,
+}
+
+/// Fee tiers by volume. An account pays the rates of the highest tier its volume reaches, nothing if it doesn't reach any.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeeSchedule {
+    tiers: Vec<FeeTier>,
+}
+
+impl FeeSchedule {
+    /// A schedule of `tiers` in any order
+    ///
+    /// # Errors
+    /// A rate is more than 10000 basis points, a maker rate is above its taker rate or two tiers start at the same volume
+    pub fn new(mut tiers: Vec<FeeTier>) -> Result<Self, String> {
+        if let Some(tier) = tiers
+            .iter()
+            .find(|tier| tier.maker_bps > MAX_BPS || tier.taker_bps > MAX_BPS)
+        {
+            return Err(format!(
+                "the rates of the tier from {} can be {} basis points at most",
+                tier.min_volume, MAX_BPS
+            ));
+        }
+        // A maker adds liquidity, so it never pays more than a taker of the same tier
+        if let Some(tier) = tiers.iter().find(|tier| tier.maker_bps > tier.taker_bps) {
+            return Err(format!(
+                "the maker rate of the tier from {} is above its taker rate",
+                tier.min_volume
+            ));
+        }
+        tiers.sort_by_key(|tier| tier.min_volume);
+        if let Some(pair) = tiers
+            .windows(2)
+            .find(|pair| pair[0].min_volume == pair[1].min_volume)
+        {
+            return Err(format!("two tiers start at {}", pair[0].min_volume));
+        }
+        Ok(FeeSchedule { tiers })
+    }
+
+    /// The tiers, ordered by volume
+    pub fn tiers(&self) -> &[FeeTier] {
+        &self.tiers
+    }
+
+    /// The highest maker rate of any tier, what a resting order may have to pay whatever its account trades meanwhile
+    pub fn max_maker_bps(&self) -> u32 {
+        self.tiers
+            .iter()
+            .map(|tier| tier.maker_bps)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The tier of an account that traded `volume`
+    pub fn tier(&self, volume: Notional) -> FeeTier {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| tier.min_volume <= volume)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for FeeSchedule {
+    type Err = String;
+
+    /// Comma-separated tiers of `min_volume:maker_bps:taker_bps`, e.g. `0:10:20,100000:5:15`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tiers = s
+            .split(',')
+            .filter(|tier| !tier.trim().is_empty())
+            .map(|tier| {
+                let invalid = || format!("'{}' isn't a tier like 100000:5:15", tier);
+                let mut parts = tier.trim().split(':');
+                let (Some(min_volume), Some(maker_bps), Some(taker_bps), None) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(invalid());
+                };
+                Ok(FeeTier {
+                    min_volume: min_volume.parse().map_err(|_| invalid())?,
+                    maker_bps: maker_bps.parse().map_err(|_| invalid())?,
+                    taker_bps: taker_bps.parse().map_err(|_| invalid())?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        FeeSchedule::new(tiers)
+    }
+}
+
+/// A fee of `bps` basis points of `notional`, rounded down to `scale` decimal places like the notional itself
+pub fn fee(notional: Notional, bps: u32, scale: u8) -> Notional {
+    notional
+        .0
+        .checked_mul(Decimal::new(u64::from(bps.min(MAX_BPS)), 4), scale)
+        .map(Notional)
+        .expect("a fee is at most the notional")
+}
+
+/// The trading volume of each account within the [`VOLUME_WINDOW`], as the notionals of its trades by time
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Volumes {
+    trades: HashMap<String, VecDeque<(u64, Notional)>>,
+}
+
+impl Volumes {
+    /// Adds a trade of `signer` worth `notional` at `timestamp` in milliseconds and forgets the trades that left the window
+    pub fn record(&mut self, signer: &str, timestamp: u64, notional: Notional) {
+        let trades = self.trades.entry(signer.to_string()).or_default();
+        let start = timestamp.saturating_sub(VOLUME_WINDOW);
+        while trades.front().is_some_and(|(at, _)| *at <= start) {
+            trades.pop_front();
+        }
+        match trades.back_mut() {
+            // Trades of the same millisecond are kept together
+            Some((at, volume)) if *at == timestamp => {
+                *volume = Notional(volume.0.saturating_add(notional.0));
+            }
+            _ => trades.push_back((timestamp, notional)),
+        }
+    }
+
+    /// What `signer` traded within the window that ends at `now`
+    pub fn volume(&self, signer: &str, now: u64) -> Notional {
+        self.volume_since(signer, now.saturating_sub(VOLUME_WINDOW) + 1)
+    }
+
+    /// What `signer` traded from `timestamp` on, which can't be further back than the window
+    pub fn volume_since(&self, signer: &str, timestamp: u64) -> Notional {
+        self.trades
+            .get(signer)
+            .into_iter()
+            .flatten()
+            .filter(|(at, _)| *at >= timestamp)
+            .fold(Notional::ZERO, |total, (_, notional)| {
+                Notional(total.0.saturating_add(notional.0))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60 * 1000;
+
+    #[test]
+    fn test_FeeSchedule_picks_the_highest_tier_reached() {
+        let schedule: FeeSchedule = "100000:5:15, 0:10:20,1000000:0:10".parse().unwrap();
+        let rates = |volume: u64| {
+            let tier = schedule.tier(volume.into());
+            (tier.maker_bps, tier.taker_bps)
+        };
+
+        assert_eq!(rates(0), (10, 20));
+        assert_eq!(rates(99_999), (10, 20));
+        assert_eq!(rates(100_000), (5, 15));
+        assert_eq!(rates(u64::MAX), (0, 10));
+
+        // Below the first tier, trading is free
+        let schedule: FeeSchedule = "1000:10:20".parse().unwrap();
+        assert_eq!(schedule.tier(Notional::from(999)), FeeTier::default());
+        assert_eq!("".parse::<FeeSchedule>(), Ok(FeeSchedule::default()));
+    }
+
+    #[test]
+    fn test_FeeSchedule_rejects_invalid_tiers() {
+        for tiers in [
+            "0:10",
+            "0:10:20:30",
+            "x:10:20",
+            "0:-1:20",
+            "0:10001:0",
+            "0:20:10",
+            "0:1:1,0:2:2",
+        ] {
+            assert!(tiers.parse::<FeeSchedule>().is_err(), "{}", tiers);
+        }
+    }
+
+    #[test]
+    fn test_fee_rounds_down() {
+        // 10 basis points of 12.34 are 0.01234
+        assert_eq!(
+            fee("12.34".parse().unwrap(), 10, 2),
+            "0.01".parse().unwrap()
+        );
+        assert_eq!(fee("9.99".parse().unwrap(), 10, 2), Notional::ZERO);
+        assert_eq!(
+            fee(Notional(Decimal::new(u64::MAX, 2)), 10_000, 2),
+            Notional(Decimal::new(u64::MAX, 2))
+        );
+    }
+
+    #[test]
+    fn test_Volumes_only_counts_the_window() {
+        let mut volumes = Volumes::default();
+        volumes.record("ALICE", DAY, 100.into());
+        volumes.record("ALICE", DAY, 50.into());
+        volumes.record("ALICE", 10 * DAY, 10.into());
+        volumes.record("BOB", 10 * DAY, 1.into());
+
+        assert_eq!(volumes.volume("ALICE", 10 * DAY), 160.into());
+        assert_eq!(volumes.volume("ALICE", 31 * DAY - 1), 160.into());
+        assert_eq!(volumes.volume("ALICE", 31 * DAY), 10.into());
+        assert_eq!(volumes.volume("ALICE", 40 * DAY), Notional::ZERO);
+        assert_eq!(volumes.volume("CHARLIE", DAY), Notional::ZERO);
+        assert_eq!(volumes.volume_since("ALICE", DAY + 1), 10.into());
+
+        // Recording forgets what left the window
+        volumes.record("ALICE", 35 * DAY, 1.into());
+        assert_eq!(volumes.trades["ALICE"].len(), 2);
+        assert_eq!(volumes.volume("ALICE", 35 * DAY), 11.into());
+    }
+}
@@ -1,57 +1,338 @@
-use octopus_common::core::types::Order;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use octopus_common::core::types::{
+    AccountUpdateRequest, AccountsQuery, AmountRequest, AuditQuery, CancelOrdersQuery,
+    OrderbookQuery, TradesQuery, TxLogQuery,
+};
+use percent_encoding::percent_decode_str;
+use serde::de::DeserializeOwned;
+use std::{
+    convert::Infallible,
+    sync::{atomic::AtomicBool, Arc},
+};
 use warp::Filter;
 
-use crate::{handlers, trading_platform::TradingPlatform};
+use crate::{handlers, metrics::Metrics, sequencer::Sequencer};
+
+/// Route templates that get their own latency series
+const ROUTES: &[&str] = &[
+    "/v1/accounts/{id}/deposit",
+    "/v1/accounts/{id}/withdraw",
+    "/v1/accounts/{id}/balance",
+    "/v1/accounts/{id}/limits",
+    "/v1/accounts",
+    "/v1/transfers",
+    "/v1/orders",
+    "/v1/orders/{ordinal}",
+    "/v1/orderbook",
+    "/v1/instruments",
+    "/v1/market",
+    "/v1/auction",
+    "/v1/trades",
+    "/v1/txlog",
+    "/v1/openapi.json",
+    "/admin/accounts/{id}/disable",
+    "/admin/accounts/{id}/enable",
+    "/admin/accounts/{id}/limits",
+    "/admin/market/state",
+    "/admin/instruments/{symbol}/state",
+    "/admin/audit",
+    "/account/deposit",
+    "/account/withdraw",
+    "/account/send",
+    "/order",
+    "/balance",
+    "/orderbook",
+    "/txlog",
+    "/accounts",
+    "/healthz",
+    "/readyz",
+    "/metrics",
+];
 
 pub fn routes(
-    trading_platform: Arc<Mutex<TradingPlatform>>,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+    ready: Arc<AtomicBool>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Infallible> + Clone {
+    let healthz = warp::get()
+        .and(warp::path!("healthz"))
+        .and_then(handlers::healthz);
+
+    let readyz = warp::get()
+        .and(warp::path!("readyz"))
+        .and(warp::any().map(move || ready.clone()))
+        .and(with_sequencer(sequencer.clone()))
+        .and_then(handlers::readyz);
+
+    let metrics_endpoint = warp::get()
+        .and(warp::path!("metrics"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::metrics);
+
+    warp::path("v1")
+        .and(v1(sequencer.clone(), metrics.clone()))
+        .or(warp::path("admin").and(admin(sequencer.clone(), metrics.clone())))
+        .or(legacy(sequencer, metrics.clone()))
+        .or(healthz)
+        .or(readyz)
+        .or(metrics_endpoint)
+        .recover(handlers::handle_rejection)
+        .with(warp::log::custom(move |info| {
+            metrics.observe_request(route_label(info.path()), info.elapsed());
+        }))
+}
+
+/// The current API, mounted under `/v1`
+fn v1(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     let deposit = warp::post()
-        .and(warp::path!("account" / "deposit"))
-        .and(warp::body::json())
-        .and(with_platform(trading_platform.clone()))
+        .and(account_path("deposit"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
         .and_then(handlers::deposit);
 
     let withdraw = warp::post()
-        .and(warp::path!("account" / "withdraw"))
-        .and(warp::body::json())
-        .and(with_platform(trading_platform.clone()))
+        .and(account_path("withdraw"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
         .and_then(handlers::withdraw);
 
+    let balance = warp::get()
+        .and(account_path("balance"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::balance);
+
+    let limits = warp::get()
+        .and(account_path("limits"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::limits);
+
+    let accounts = warp::get()
+        .and(warp::path!("accounts"))
+        .and(warp::query::<AccountsQuery>())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::accounts);
+
     let send = warp::post()
-        .and(warp::path!("account" / "send"))
-        .and(warp::body::json())
-        .and(with_platform(trading_platform.clone()))
+        .and(warp::path!("transfers"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
         .and_then(handlers::send);
 
     let order = warp::post()
-        .and(warp::path!("order"))
+        .and(warp::path!("orders"))
         .and(json_body())
-        .and(with_platform(trading_platform.clone()))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
         .and_then(handlers::order);
 
-    let balance = warp::post()
-        .and(warp::path!("balance"))
-        .and(warp::body::json())
-        .and(with_platform(trading_platform.clone()))
-        .and_then(handlers::account);
+    let cancel = warp::delete()
+        .and(warp::path!("orders" / u64))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::cancel);
+
+    let cancel_orders = warp::delete()
+        .and(warp::path!("orders"))
+        .and(warp::query::<CancelOrdersQuery>())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::cancel_orders);
 
     let orderbook = warp::get()
         .and(warp::path!("orderbook"))
-        .and(with_platform(trading_platform.clone()))
+        .and(warp::query::<OrderbookQuery>())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
         .and_then(handlers::orderbook);
 
+    let instruments = warp::get()
+        .and(warp::path!("instruments"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::instruments);
+
+    let market = warp::get()
+        .and(warp::path!("market"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::market);
+
+    let auction = warp::get()
+        .and(warp::path!("auction"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::auction);
+
+    let trades = warp::get()
+        .and(warp::path!("trades"))
+        .and(warp::query::<TradesQuery>())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::trades);
+
     let txlog = warp::get()
         .and(warp::path!("txlog"))
-        .and(with_platform(trading_platform.clone()))
+        .and(warp::query::<TxLogQuery>())
+        .and(with_sequencer(sequencer))
+        .and(with_metrics(metrics))
         .and_then(handlers::txlog);
 
+    let openapi = warp::get()
+        .and(warp::path!("openapi.json"))
+        .and_then(handlers::openapi);
+
+    deposit
+        .or(withdraw)
+        .or(balance)
+        .or(limits)
+        .or(accounts)
+        .or(send)
+        .or(order)
+        .or(cancel)
+        .or(cancel_orders)
+        .or(orderbook)
+        .or(instruments)
+        .or(market)
+        .or(auction)
+        .or(trades)
+        .or(txlog)
+        .or(openapi)
+}
+
+/// Operations, mounted under `/admin`
+fn admin(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let disable = warp::post()
+        .and(account_path("disable"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::disable);
+
+    let enable = warp::post()
+        .and(account_path("enable"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::enable);
+
+    let set_limits = warp::put()
+        .and(account_path("limits"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::set_limits);
+
+    let set_market_state = warp::put()
+        .and(warp::path!("market" / "state"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::set_market_state);
+
+    let set_instrument_state = warp::put()
+        .and(warp::path("instruments"))
+        .and(decoded_param())
+        .and(warp::path("state"))
+        .and(warp::path::end())
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::set_instrument_state);
+
+    let audit = warp::get()
+        .and(warp::path!("audit"))
+        .and(warp::query::<AuditQuery>())
+        .and(with_sequencer(sequencer))
+        .and(with_metrics(metrics))
+        .and_then(handlers::audit);
+
+    disable
+        .or(enable)
+        .or(set_limits)
+        .or(set_market_state)
+        .or(set_instrument_state)
+        .or(audit)
+}
+
+/// The unversioned routes, kept as deprecated aliases of their `/v1` successors
+fn legacy(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let deposit = warp::post()
+        .and(warp::path!("account" / "deposit"))
+        .and(json_body())
+        .map(split_update_request)
+        .untuple_one()
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::deposit)
+        .map(|reply| deprecated(reply, "/v1/accounts/{id}/deposit"));
+
+    let withdraw = warp::post()
+        .and(warp::path!("account" / "withdraw"))
+        .and(json_body())
+        .map(split_update_request)
+        .untuple_one()
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::withdraw)
+        .map(|reply| deprecated(reply, "/v1/accounts/{id}/withdraw"));
+
+    let send = warp::post()
+        .and(warp::path!("account" / "send"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::send)
+        .map(|reply| deprecated(reply, "/v1/transfers"));
+
+    let order = warp::post()
+        .and(warp::path!("order"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::order)
+        .map(|reply| deprecated(reply, "/v1/orders"));
+
+    let balance = warp::post()
+        .and(warp::path!("balance"))
+        .and(json_body())
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::account)
+        .map(|reply| deprecated(reply, "/v1/accounts/{id}/balance"));
+
+    let orderbook = warp::get()
+        .and(warp::path!("orderbook"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::orderbook_legacy)
+        .map(|reply| deprecated(reply, "/v1/orderbook"));
+
+    let txlog = warp::get()
+        .and(warp::path!("txlog"))
+        .and(with_sequencer(sequencer.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and_then(handlers::txlog_legacy)
+        .map(|reply| deprecated(reply, "/v1/txlog"));
+
     let accounts = warp::get()
         .and(warp::path!("accounts"))
-        .and(with_platform(trading_platform))
-        .and_then(handlers::accounts);
+        .and(with_sequencer(sequencer))
+        .and(with_metrics(metrics))
+        .and_then(handlers::accounts_legacy)
+        .map(|reply| deprecated(reply, "/v1/accounts"));
 
     deposit
         .or(withdraw)
@@ -63,15 +344,925 @@ pub fn routes(
         .or(accounts)
 }
 
-fn json_body() -> impl Filter<Extract = (Order,), Error = warp::Rejection> + Clone {
+/// Marks a reply as coming from a deprecated route and points at its replacement
+fn deprecated(reply: impl warp::Reply, successor: &str) -> impl warp::Reply {
+    warp::reply::with_header(
+        warp::reply::with_header(reply, "deprecation", "true"),
+        "link",
+        format!("<{}>; rel=\"successor-version\"", successor),
+    )
+}
+
+/// Legacy requests carry the account in the body, the `/v1` routes in the path
+fn split_update_request(request: AccountUpdateRequest) -> (String, AmountRequest) {
+    (
+        request.signer,
+        AmountRequest {
+            amount: request.amount,
+        },
+    )
+}
+
+/// Maps a request path to its route template, or `unmatched` to keep the number of series bounded
+fn route_label(path: &str) -> &'static str {
+    let template = if let Some((prefix, rest)) = ["/v1/accounts/", "/admin/accounts/"]
+        .iter()
+        .find_map(|prefix| Some((prefix, path.strip_prefix(prefix)?)))
+    {
+        match rest.split_once('/') {
+            Some((_, action)) => format!("{}{{id}}/{}", prefix, action),
+            None => path.to_string(),
+        }
+    } else if path.strip_prefix("/v1/orders/").is_some() {
+        "/v1/orders/{ordinal}".to_string()
+    } else if let Some(rest) = path.strip_prefix("/admin/instruments/") {
+        match rest.split_once('/') {
+            Some((_, action)) => format!("/admin/instruments/{{symbol}}/{}", action),
+            None => path.to_string(),
+        }
+    } else {
+        path.to_string()
+    };
+    ROUTES
+        .iter()
+        .find(|route| **route == template)
+        .unwrap_or(&"unmatched")
+}
+
+/// `accounts/{id}/{action}`, extracting the percent-decoded account id
+fn account_path(
+    action: &'static str,
+) -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::path("accounts")
+        .and(decoded_param())
+        .and(warp::path(action))
+        .and(warp::path::end())
+}
+
+/// The next path segment, percent-decoded
+fn decoded_param() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::path::param::<String>().and_then(|param: String| async move {
+        percent_decode_str(&param)
+            .decode_utf8()
+            .map(|param| param.into_owned())
+            .map_err(|_| warp::reject::not_found())
+    })
+}
+
+fn json_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
     // When accepting a body, we want a JSON body
     // (and to reject huge payloads)...
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-fn with_platform(
-    platform: Arc<Mutex<TradingPlatform>>,
-) -> impl Filter<Extract = (Arc<Mutex<TradingPlatform>>,), Error = std::convert::Infallible> + Clone
-{
-    warp::any().map(move || platform.clone())
+fn with_sequencer(
+    sequencer: Sequencer,
+) -> impl Filter<Extract = (Sequencer,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || sequencer.clone())
+}
+
+fn with_metrics(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (Arc<Metrics>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || metrics.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading_platform::TradingPlatform;
+    use octopus_common::{
+        audit::{AuditEntry, AuditEvent},
+        core::types::{
+            AccountBalance, AccountStatus, ErrorResponse, Notional, Page, PartialOrder, RiskLimits,
+            Side,
+        },
+    };
+    use std::{future, sync::atomic::Ordering};
+    use warp::http::StatusCode;
+
+    fn setup() -> (
+        impl Filter<Extract = (impl warp::Reply,), Error = Infallible> + Clone,
+        Arc<AtomicBool>,
+    ) {
+        let ready = Arc::new(AtomicBool::new(false));
+        let (sequencer, _) = Sequencer::start(16, future::ready(TradingPlatform::new()));
+        let routes = routes(sequencer, Arc::new(Metrics::new()), ready.clone());
+        (routes, ready)
+    }
+
+    #[tokio::test]
+    async fn test_readyz_reflects_recovery() {
+        let (routes, ready) = setup();
+
+        let res = warp::test::request().path("/healthz").reply(&routes).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request().path("/readyz").reply(&routes).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        ready.store(true, Ordering::Release);
+        let res = warp::test::request().path("/readyz").reply(&routes).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_orders_by_outcome() {
+        let (routes, _) = setup();
+
+        for (signer, amount) in [("ALICE", 100), ("BOB", 100)] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/account/deposit")
+                .json(&serde_json::json!({ "signer": signer, "amount": amount }))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        for (signer, side) in [("ALICE", "Sell"), ("BOB", "Buy"), ("CHARLIE", "Buy")] {
+            warp::test::request()
+                .method("POST")
+                .path("/order")
+                .json(&serde_json::json!({ "price": 10, "amount": 3, "side": side, "signer": signer }))
+                .reply(&routes)
+                .await;
+        }
+        // Matches count when an auction uncrosses as well, though no order's receipt lists them
+        let state = |state| {
+            warp::test::request()
+                .method("PUT")
+                .path("/admin/instruments/OCTO/state")
+                .json(&serde_json::json!({ "state": state }))
+                .reply(&routes)
+        };
+        state("auction_only").await;
+        for (signer, side) in [("ALICE", "Sell"), ("BOB", "Buy")] {
+            warp::test::request()
+                .method("POST")
+                .path("/v1/orders")
+                .json(&serde_json::json!({ "price": 10, "amount": 2, "side": side, "signer": signer }))
+                .reply(&routes)
+                .await;
+        }
+        state("open").await;
+
+        let res = warp::test::request().path("/metrics").reply(&routes).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8(res.body().to_vec()).unwrap();
+        for line in [
+            "octopus_orders_accepted_total 4",
+            "octopus_orders_rejected_total{reason=\"account_not_found\"} 1",
+            "octopus_matches_total 2",
+            "octopus_trade_volume_total 5",
+            "octopus_book_depth{side=\"buy\"} 0",
+            "octopus_accounts 2",
+            "octopus_request_duration_seconds_count{route=\"/order\"} 3",
+        ] {
+            assert!(body.contains(line), "missing '{}' in:\n{}", line, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_sheds_requests() {
+        // The engine never gets a platform, so the one queued deposit fills the queue
+        let (sequencer, _) = Sequencer::start(1, future::pending());
+        let routes = routes(
+            sequencer,
+            Arc::new(Metrics::new()),
+            Arc::new(AtomicBool::new(false)),
+        );
+        let deposit = || {
+            warp::test::request()
+                .method("POST")
+                .path("/v1/accounts/ALICE/deposit")
+                .json(&AmountRequest { amount: 42.into() })
+        };
+        let queued = {
+            let routes = routes.clone();
+            tokio::spawn(async move { deposit().reply(&routes).await })
+        };
+        tokio::task::yield_now().await;
+
+        let res = deposit().reply(&routes).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()["retry-after"], "1");
+        let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(error.code, "overloaded");
+        queued.abort();
+    }
+
+    #[tokio::test]
+    async fn test_v1_balance_reads_account_from_path() {
+        let (routes, _) = setup();
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .json(&AmountRequest { amount: 42.into() })
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request()
+            .path("/v1/accounts/ALICE/balance")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let balance: AccountBalance = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            balance,
+            AccountBalance {
+                signer: "ALICE".to_string(),
+                balance: 42.into()
+            }
+        );
+
+        let res = warp::test::request()
+            .path("/v1/accounts/BOB/balance")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(error.code, "account_not_found");
+    }
+
+    /// Reproduced with the `routes` fuzz target: a large price made the order handler overflow
+    #[tokio::test]
+    async fn test_v1_orders_with_huge_prices_are_answered() {
+        let (routes, _) = setup();
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .json(&AmountRequest { amount: 100.into() })
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        for side in ["Sell", "Buy"] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/orders")
+                .body(format!(
+                    r#"{{"price":"184467440737095516.15","amount":"5","side":"{}","signer":"ALICE"}}"#,
+                    side
+                ))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", side);
+            let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(error.code, "notional_overflow");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_v1_orders_without_price_or_amount_are_rejected() {
+        let (routes, _) = setup();
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .json(&AmountRequest { amount: 100.into() })
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        for (price, amount) in [(0, 5), (10, 0)] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/orders")
+                .body(format!(
+                    r#"{{"price":{},"amount":{},"side":"Buy","signer":"ALICE"}}"#,
+                    price, amount
+                ))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+            let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(error.code, "invalid_order");
+        }
+
+        // Nothing reached the book
+        let res = warp::test::request()
+            .path("/v1/orderbook")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.body(), r#"{"items":[],"next":null}"#);
+    }
+
+    #[tokio::test]
+    async fn test_v1_instruments_have_their_trading_rules() {
+        let (routes, _) = setup();
+
+        let res = warp::test::request()
+            .path("/v1/instruments")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let instruments: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            instruments,
+            serde_json::json!([{
+                "symbol": "OCTO",
+                "price_scale": 2,
+                "quantity_scale": 0,
+                "tick_size": "0.01",
+                "lot_size": "1",
+                "min_quantity": "0",
+                "max_quantity": null,
+                "min_notional": "0",
+                "allocation": "fifo"
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_v1_account_ids_are_percent_decoded() {
+        let (routes, _) = setup();
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/DESK%201/deposit")
+            .json(&AmountRequest { amount: 1.into() })
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request()
+            .path("/v1/accounts/DESK%201/balance")
+            .reply(&routes)
+            .await;
+        let balance: AccountBalance = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(balance.signer, "DESK 1");
+    }
+
+    #[tokio::test]
+    async fn test_legacy_routes_are_deprecated_aliases() {
+        let (routes, _) = setup();
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/account/deposit")
+            .json(&AccountUpdateRequest {
+                signer: "ALICE".to_string(),
+                amount: 42.into(),
+            })
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["deprecation"], "true");
+        assert_eq!(
+            res.headers()["link"],
+            "</v1/accounts/{id}/deposit>; rel=\"successor-version\""
+        );
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/balance")
+            .json(&serde_json::json!({ "signer": "ALICE" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body().as_ref(), br#""42.00""#);
+    }
+
+    #[tokio::test]
+    async fn test_v1_decimals_are_strings_with_the_instrument_scale() {
+        let (routes, _) = setup();
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .body(r#"{"amount":"100.5"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/orders")
+            .body(r#"{"price":"12.5","amount":"3","side":"Buy","signer":"ALICE"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request()
+            .path("/v1/orderbook")
+            .reply(&routes)
+            .await;
+        let book: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(book["items"][0]["price"], "12.50");
+        assert_eq!(book["items"][0]["amount"], "3");
+
+        let res = warp::test::request()
+            .path("/v1/accounts/ALICE/balance")
+            .reply(&routes)
+            .await;
+        let balance: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(balance["balance"], "100.50");
+
+        // More decimal places than the instrument or balances have are rejected, not rounded
+        for (path, body, code) in [
+            (
+                "/v1/orders",
+                r#"{"price":"12.505","amount":"1","side":"Buy","signer":"ALICE"}"#,
+                "invalid_order",
+            ),
+            (
+                "/v1/accounts/ALICE/deposit",
+                r#"{"amount":"0.001"}"#,
+                "invalid_amount",
+            ),
+        ] {
+            let res = warp::test::request()
+                .method("POST")
+                .path(path)
+                .body(body)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", path);
+            let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(error.code, code);
+        }
+
+        // Floats would lose precision before they got here
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .body(r#"{"amount":0.1}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_v1_account_limits_reject_orders_with_their_own_code() {
+        let (routes, _) = setup();
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/admin/accounts/ALICE/limits")
+            .body(r#"{"max_open_orders":1}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .body(r#"{"amount":"100"}"#)
+            .reply(&routes)
+            .await;
+        // Accounts can only read their limits
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/v1/accounts/ALICE/limits")
+            .body(r#"{"max_open_orders":1,"max_notional":"50"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/admin/accounts/ALICE/limits")
+            .body(r#"{"max_open_orders":1,"max_notional":"50"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = warp::test::request()
+            .path("/v1/accounts/ALICE/limits")
+            .reply(&routes)
+            .await;
+        let limits: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            limits,
+            serde_json::json!({"max_notional": "50", "max_open_orders": 1})
+        );
+        let res = warp::test::request()
+            .path("/admin/audit?account=ALICE")
+            .reply(&routes)
+            .await;
+        let audit: Page<AuditEntry, u64> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            audit.items.last().map(|entry| &entry.event),
+            Some(&AuditEvent::RiskLimitsSet {
+                account: "ALICE".to_string(),
+                limits: RiskLimits {
+                    max_open_orders: Some(1),
+                    max_notional: Some(Notional::from(50)),
+                    ..RiskLimits::default()
+                },
+            })
+        );
+
+        for (body, code) in [
+            (
+                r#"{"price":"1","amount":"2","side":"Buy","signer":"ALICE"}"#,
+                "",
+            ),
+            (
+                r#"{"price":"1","amount":"1","side":"Buy","signer":"ALICE"}"#,
+                "open_orders_limit",
+            ),
+            (
+                r#"{"price":"51","amount":"1","side":"Sell","signer":"ALICE"}"#,
+                "order_notional_limit",
+            ),
+        ] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/orders")
+                .body(body)
+                .reply(&routes)
+                .await;
+            if code.is_empty() {
+                assert_eq!(res.status(), StatusCode::OK);
+                continue;
+            }
+            assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", code);
+            let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(error.code, code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disabled_accounts_and_mass_cancels_are_audited() {
+        let (routes, _) = setup();
+        warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .body(r#"{"amount":"10"}"#)
+            .reply(&routes)
+            .await;
+        for (price, side) in [("1", "Buy"), ("2", "Sell"), ("3", "Sell")] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/orders")
+                .body(format!(
+                    r#"{{"price":"{}","amount":"1","side":"{}","signer":"ALICE"}}"#,
+                    price, side
+                ))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        // Mass cancels can be repeated, there's just nothing left the second time
+        for cancelled in [2, 0] {
+            let res = warp::test::request()
+                .method("DELETE")
+                .path("/v1/orders?signer=ALICE&side=Sell")
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let orders: Vec<PartialOrder> = serde_json::from_slice(res.body()).unwrap();
+            assert_eq!(orders.len(), cancelled);
+        }
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/v1/orders")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        for cancelled in [1, 0] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/admin/accounts/ALICE/disable")
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let status: AccountStatus = serde_json::from_slice(res.body()).unwrap();
+            assert!(status.disabled);
+            assert_eq!(status.cancelled.len(), cancelled);
+        }
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/orders")
+            .body(r#"{"price":"1","amount":"1","side":"Buy","signer":"ALICE"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(error.code, "account_disabled");
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/admin/accounts/BOB/disable")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = warp::test::request()
+            .path("/admin/audit?account=ALICE&after=1")
+            .reply(&routes)
+            .await;
+        let audit: Page<AuditEntry, u64> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            audit
+                .items
+                .iter()
+                .map(|entry| (entry.id, &entry.event))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    2,
+                    &AuditEvent::OrdersCancelled {
+                        account: "ALICE".to_string(),
+                        side: Some(Side::Sell),
+                        cancelled: vec![],
+                    }
+                ),
+                (
+                    3,
+                    &AuditEvent::AccountDisabled {
+                        account: "ALICE".to_string(),
+                        cancelled: vec![1],
+                    }
+                ),
+                (
+                    4,
+                    &AuditEvent::AccountDisabled {
+                        account: "ALICE".to_string(),
+                        cancelled: vec![],
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_halted_instruments_reject_orders_but_stay_readable() {
+        let (routes, _) = setup();
+        warp::test::request()
+            .method("POST")
+            .path("/v1/accounts/ALICE/deposit")
+            .body(r#"{"amount":"10"}"#)
+            .reply(&routes)
+            .await;
+
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/admin/instruments/OCTO/state")
+            .body(r#"{"state":"halted","reason":"news pending"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/admin/instruments/XYZ/state")
+            .body(r#"{"state":"halted"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        // Symbols are percent-decoded like account ids
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/admin/instruments/OCT%4F/state")
+            .body(r#"{"state":"halted","reason":"news pending"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request()
+            .path("/v1/market")
+            .reply(&routes)
+            .await;
+        let status: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            status,
+            serde_json::json!({
+                "market": {"state": "open"},
+                "instruments": [{
+                    "symbol": "OCTO",
+                    "status": {"state": "halted", "reason": "news pending"},
+                    "state": "halted",
+                    "queued": 0,
+                    "queued_orders": []
+                }]
+            })
+        );
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/orders")
+            .body(r#"{"price":"1","amount":"1","side":"Buy","signer":"ALICE"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(error.code, "trading_halted");
+        let res = warp::test::request()
+            .path("/v1/orderbook")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/admin/market/state")
+            .body(r#"{"state":"open"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        warp::test::request()
+            .method("PUT")
+            .path("/admin/instruments/OCTO/state")
+            .body(r#"{"state":"open"}"#)
+            .reply(&routes)
+            .await;
+        let res = warp::test::request()
+            .method("POST")
+            .path("/v1/orders")
+            .body(r#"{"price":"1","amount":"1","side":"Buy","signer":"ALICE"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_v1_auction_publishes_the_indicative_price() {
+        let (routes, _) = setup();
+        for account in ["ALICE", "BOB"] {
+            warp::test::request()
+                .method("POST")
+                .path(&format!("/v1/accounts/{}/deposit", account))
+                .body(r#"{"amount":"100"}"#)
+                .reply(&routes)
+                .await;
+        }
+        warp::test::request()
+            .method("PUT")
+            .path("/admin/instruments/OCTO/state")
+            .body(r#"{"state":"auction_only","reason":"opening auction"}"#)
+            .reply(&routes)
+            .await;
+        for order in [
+            r#"{"price":"11","amount":"2","side":"Buy","signer":"ALICE"}"#,
+            r#"{"price":"10","amount":"3","side":"Sell","signer":"BOB"}"#,
+        ] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/v1/orders")
+                .body(order)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        let res = warp::test::request()
+            .path("/v1/auction")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let auction: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            auction,
+            serde_json::json!({
+                "symbol": "OCTO",
+                "state": "auction_only",
+                "price": "10.00",
+                "volume": "2",
+                "surplus": "1",
+                "surplus_side": "Sell"
+            })
+        );
+
+        warp::test::request()
+            .method("PUT")
+            .path("/admin/instruments/OCTO/state")
+            .body(r#"{"state":"open"}"#)
+            .reply(&routes)
+            .await;
+        let res = warp::test::request()
+            .path("/v1/trades")
+            .reply(&routes)
+            .await;
+        let trades: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(trades["items"].as_array().unwrap().len(), 1);
+        assert_eq!(trades["items"][0]["price"], "10.00");
+    }
+
+    #[tokio::test]
+    async fn test_openapi_covers_all_types() {
+        let (routes, _) = setup();
+
+        let res = warp::test::request()
+            .path("/v1/openapi.json")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let doc: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        for schema in [
+            "Side",
+            "Decimal",
+            "Price",
+            "Quantity",
+            "Notional",
+            "Instrument",
+            "Allocation",
+            "TradingState",
+            "TradingStatus",
+            "InstrumentStatus",
+            "MarketStatus",
+            "AuctionStatus",
+            "TradingStateRequest",
+            "Order",
+            "PartialOrder",
+            "Receipt",
+            "Fee",
+            "AmountRequest",
+            "AccountBalance",
+            "AccountStatus",
+            "RiskLimits",
+            "AccountUpdateRequest",
+            "AccountBalanceRequest",
+            "SendRequest",
+            "ErrorResponse",
+            "Tx",
+            "TxKind",
+            "LedgerEntry",
+            "TxLogPage",
+            "AccountsPage",
+            "OrderbookPage",
+            "Trade",
+            "TradesPage",
+            "AuditEvent",
+            "AuditEntry",
+            "AuditPage",
+        ] {
+            assert!(
+                doc["components"]["schemas"][schema].is_object(),
+                "{} is missing",
+                schema
+            );
+        }
+        for route in ROUTES.iter().filter(|r| {
+            (r.starts_with("/v1/") || r.starts_with("/admin/")) && **r != "/v1/openapi.json"
+        }) {
+            assert!(doc["paths"][route].is_object(), "{} is missing", route);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_v1_txlog_is_paginated_and_filtered() {
+        let (routes, _) = setup();
+
+        for signer in ["ALICE", "BOB", "ALICE"] {
+            let res = warp::test::request()
+                .method("POST")
+                .path(&format!("/v1/accounts/{}/deposit", signer))
+                .json(&AmountRequest { amount: 1.into() })
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        let res = warp::test::request()
+            .path("/v1/txlog?account=ALICE&kind=deposit&limit=1")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(page["items"][0]["id"], 1);
+        assert_eq!(page["next"], 1);
+
+        let res = warp::test::request()
+            .path("/v1/txlog?account=ALICE&after=1")
+            .reply(&routes)
+            .await;
+        let page: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(page["items"][0]["id"], 3);
+        assert_eq!(page["next"], serde_json::Value::Null);
+
+        let res = warp::test::request()
+            .path("/v1/txlog?limit=many")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let error: ErrorResponse = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(error.code, "invalid_query");
+
+        // the legacy alias still returns everything at once
+        let res = warp::test::request().path("/txlog").reply(&routes).await;
+        let txlog: Vec<serde_json::Value> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(txlog.len(), 3);
+    }
+
+    #[test]
+    fn test_route_label_collapses_account_ids() {
+        assert_eq!(
+            route_label("/v1/accounts/ALICE/balance"),
+            "/v1/accounts/{id}/balance"
+        );
+        assert_eq!(route_label("/v1/accounts"), "/v1/accounts");
+        assert_eq!(route_label("/v1/orders/42"), "/v1/orders/{ordinal}");
+        assert_eq!(
+            route_label("/admin/accounts/ALICE/disable"),
+            "/admin/accounts/{id}/disable"
+        );
+        assert_eq!(
+            route_label("/admin/instruments/OCTO/state"),
+            "/admin/instruments/{symbol}/state"
+        );
+        assert_eq!(route_label("/order"), "/order");
+        assert_eq!(route_label("/no/such/route"), "unmatched");
+    }
 }
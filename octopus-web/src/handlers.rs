@@ -1,64 +1,444 @@
-use crate::trading_platform::TradingPlatform;
+use crate::{
+    metrics::Metrics,
+    openapi::ApiDoc,
+    sequencer::{Sequencer, SequencerError},
+    telemetry::record_signer,
+    trading_platform::TradingPlatform,
+};
 use octopus_common::{
-    core::types::{AccountBalanceRequest, AccountUpdateRequest, Order, SendRequest},
-    errors::OctopusError,
+    core::types::{
+        AccountBalance, AccountBalanceRequest, AccountsQuery, AmountRequest, AuditQuery,
+        CancelOrdersQuery, ErrorResponse, Order, OrderbookQuery, RiskLimits, SendRequest,
+        TradesQuery, TradingStateRequest, TxLogQuery,
+    },
+    errors::{ApplicationError, OctopusError},
+};
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use utoipa::OpenApi;
+use warp::{
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    reject::Rejection,
+    Reply,
 };
-use std::{convert::Infallible, sync::Arc};
-use tokio::sync::Mutex;
 
+/// Deposit currency into an account, creating the account if necessary
+#[utoipa::path(
+    post,
+    path = "/v1/accounts/{id}/deposit",
+    params(("id" = String, Path, description = "Account signer")),
+    request_body = AmountRequest,
+    responses(
+        (status = 200, description = "Deposit booked", body = Tx),
+        (status = 422, description = "The balance would overflow", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "accounts"
+)]
 pub async fn deposit(
-    deposit: AccountUpdateRequest,
-    platform: Arc<Mutex<TradingPlatform>>,
+    signer: String,
+    deposit: AmountRequest,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut platform = platform.lock().await;
+    record_signer(&signer);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.deposit(&signer, deposit.amount)
+    })
+    .await?;
 
-    match platform.deposit(&deposit.signer, deposit.amount) {
+    match result {
         Ok(tx) => Ok(warp::reply::json(&tx)),
         Err(err) => Err(warp::reject::custom(OctopusError(err))),
     }
 }
 
+/// Withdraw currency from an account
+#[utoipa::path(
+    post,
+    path = "/v1/accounts/{id}/withdraw",
+    params(("id" = String, Path, description = "Account signer")),
+    request_body = AmountRequest,
+    responses(
+        (status = 200, description = "Withdrawal booked", body = Tx),
+        (status = 404, description = "Unknown account", body = ErrorResponse),
+        (status = 422, description = "Insufficient funds", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "accounts"
+)]
 pub async fn withdraw(
-    withdraw: AccountUpdateRequest,
-    platform: Arc<Mutex<TradingPlatform>>,
+    signer: String,
+    withdraw: AmountRequest,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut platform = platform.lock().await;
+    record_signer(&signer);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.withdraw(&signer, withdraw.amount)
+    })
+    .await?;
 
-    match platform.withdraw(&withdraw.signer, withdraw.amount) {
+    match result {
         Ok(tx) => Ok(warp::reply::json(&tx)),
         Err(err) => Err(warp::reject::custom(OctopusError(err))),
     }
 }
 
+/// Transfer currency between two accounts
+#[utoipa::path(
+    post,
+    path = "/v1/transfers",
+    request_body = SendRequest,
+    responses(
+        (status = 200, description = "The withdrawal from the sender and the deposit to the recipient", body = [Tx]),
+        (status = 404, description = "Unknown sender or recipient", body = ErrorResponse),
+        (status = 422, description = "Insufficient funds", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "accounts"
+)]
 pub async fn send(
     send: SendRequest,
-    platform: Arc<Mutex<TradingPlatform>>,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut platform = platform.lock().await;
+    record_signer(&send.sender);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.send(&send.sender, &send.recipient, send.amount)
+    })
+    .await?;
 
-    match platform.send(&send.sender, &send.recipient, send.amount) {
+    match result {
         Ok(txs) => Ok(warp::reply::json(&txs)),
         Err(err) => Err(warp::reject::custom(OctopusError(err))),
     }
 }
 
+/// Submit an order, which is matched immediately and rests in the book if not completely filled
+#[utoipa::path(
+    post,
+    path = "/v1/orders",
+    request_body = Order,
+    responses(
+        (status = 200, description = "Order accepted, resting without matching during an auction, or queued while trading is halted (`queued: true`)", body = Receipt),
+        (status = 400, description = "Zero price or amount, too many decimal places or against the instrument's trading rules", body = ErrorResponse),
+        (status = 403, description = "The signer's account is disabled", body = ErrorResponse),
+        (status = 404, description = "Unknown signer", body = ErrorResponse),
+        (status = 409, description = "Trading is closed, or halted and orders are rejected meanwhile", body = ErrorResponse),
+        (status = 422, description = "Insufficient funds, the order is worth more than the currency can hold or fails a pre-trade risk check", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "orders"
+)]
 pub async fn order(
     order: Order,
-    ledger: Arc<Mutex<TradingPlatform>>,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut platform = ledger.lock().await;
-
-    match platform.order(order) {
+    record_signer(&order.signer);
+    let result = match order.validate() {
+        Ok(()) => submit(&sequencer, &metrics, move |platform| platform.order(order)).await?,
+        Err(err) => Err(err),
+    };
+    metrics.observe_order(&result);
+    match result {
         Ok(receipt) => Ok(warp::reply::json(&receipt)),
         Err(err) => Err(warp::reject::custom(OctopusError(err))),
     }
 }
 
+/// Cancel a resting order
+#[utoipa::path(
+    delete,
+    path = "/v1/orders/{ordinal}",
+    params(("ordinal" = u64, Path, description = "Ordinal from the order's receipt")),
+    responses(
+        (status = 200, description = "The order as it was removed from the book", body = PartialOrder),
+        (status = 404, description = "No such resting order", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "orders"
+)]
+pub async fn cancel(
+    ordinal: u64,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.cancel(ordinal)
+    })
+    .await?;
+
+    match result {
+        Ok(order) => {
+            record_signer(&order.signer);
+            Ok(warp::reply::json(&order))
+        }
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Cancel all resting orders of a signer, or only those on one side of the book
+#[utoipa::path(
+    delete,
+    path = "/v1/orders",
+    params(CancelOrdersQuery),
+    responses(
+        (status = 200, description = "The orders as they were removed from the book, none if there were none left", body = [PartialOrder]),
+        (status = 404, description = "Unknown signer", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "orders"
+)]
+pub async fn cancel_orders(
+    query: CancelOrdersQuery,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    record_signer(&query.signer);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.cancel_orders(&query.signer, query.side)
+    })
+    .await?;
+
+    match result {
+        Ok(orders) => Ok(warp::reply::json(&orders)),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Fetch the balance of an account
+#[utoipa::path(
+    get,
+    path = "/v1/accounts/{id}/balance",
+    params(("id" = String, Path, description = "Account signer")),
+    responses(
+        (status = 200, description = "The current balance", body = AccountBalance),
+        (status = 404, description = "Unknown account", body = ErrorResponse),
+    ),
+    tag = "accounts"
+)]
+pub async fn balance(
+    signer: String,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    record_signer(&signer);
+    let platform = metrics.read(&sequencer).await;
+
+    match platform.balance_of(&signer) {
+        Ok(balance) => Ok(warp::reply::json(&AccountBalance {
+            balance: *balance,
+            signer,
+        })),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Fetch the pre-trade limits of an account
+#[utoipa::path(
+    get,
+    path = "/v1/accounts/{id}/limits",
+    params(("id" = String, Path, description = "Account signer")),
+    responses(
+        (status = 200, description = "The limits that apply to the account's orders", body = RiskLimits),
+        (status = 404, description = "Unknown account", body = ErrorResponse),
+    ),
+    tag = "accounts"
+)]
+pub async fn limits(
+    signer: String,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    record_signer(&signer);
+    let platform = metrics.read(&sequencer).await;
+
+    match platform.balance_of(&signer) {
+        Ok(_) => Ok(warp::reply::json(&platform.risk_limits(&signer))),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Set the pre-trade limits of an account, limits that are left out fall back to the defaults
+#[utoipa::path(
+    put,
+    path = "/admin/accounts/{id}/limits",
+    params(("id" = String, Path, description = "Account signer")),
+    request_body = RiskLimits,
+    responses(
+        (status = 200, description = "The limits that apply to the account's orders from now on", body = RiskLimits),
+        (status = 404, description = "Unknown account", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn set_limits(
+    signer: String,
+    limits: RiskLimits,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    record_signer(&signer);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.set_risk_limits(&signer, limits)
+    })
+    .await?;
+
+    match result {
+        Ok(limits) => Ok(warp::reply::json(&limits)),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Stop an account from placing orders and cancel its resting orders
+#[utoipa::path(
+    post,
+    path = "/admin/accounts/{id}/disable",
+    params(("id" = String, Path, description = "Account signer")),
+    responses(
+        (status = 200, description = "The account is disabled, with the orders this request cancelled", body = AccountStatus),
+        (status = 404, description = "Unknown account", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn disable(
+    signer: String,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    record_signer(&signer);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.disable(&signer)
+    })
+    .await?;
+
+    match result {
+        Ok(status) => Ok(warp::reply::json(&status)),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Let a disabled account place orders again
+#[utoipa::path(
+    post,
+    path = "/admin/accounts/{id}/enable",
+    params(("id" = String, Path, description = "Account signer")),
+    responses(
+        (status = 200, description = "The account is enabled", body = AccountStatus),
+        (status = 404, description = "Unknown account", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn enable(
+    signer: String,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    record_signer(&signer);
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.enable(&signer)
+    })
+    .await?;
+
+    match result {
+        Ok(status) => Ok(warp::reply::json(&status)),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Set the trading state of the whole market, e.g. to halt or resume trading
+#[utoipa::path(
+    put,
+    path = "/admin/market/state",
+    request_body = TradingStateRequest,
+    responses(
+        (status = 200, description = "The trading states from now on", body = MarketStatus),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn set_market_state(
+    request: TradingStateRequest,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    set_trading_state(None, request, sequencer, metrics).await
+}
+
+/// Set the trading state of an instrument, e.g. to halt or resume trading
+#[utoipa::path(
+    put,
+    path = "/admin/instruments/{symbol}/state",
+    params(("symbol" = String, Path, description = "Instrument symbol")),
+    request_body = TradingStateRequest,
+    responses(
+        (status = 200, description = "The trading states from now on", body = MarketStatus),
+        (status = 404, description = "Unknown instrument", body = ErrorResponse),
+        (status = 503, description = "Too many pending requests", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn set_instrument_state(
+    symbol: String,
+    request: TradingStateRequest,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    set_trading_state(Some(symbol), request, sequencer, metrics).await
+}
+
+async fn set_trading_state(
+    symbol: Option<String>,
+    request: TradingStateRequest,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let result = submit(&sequencer, &metrics, move |platform| {
+        platform.set_trading_state(symbol.as_deref(), request)
+    })
+    .await?;
+
+    match result {
+        Ok(status) => Ok(warp::reply::json(&status)),
+        Err(err) => Err(warp::reject::custom(OctopusError(err))),
+    }
+}
+
+/// Fetch the audit log of administrative and bulk actions
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    params(AuditQuery),
+    responses((status = 200, description = "A page of actions in the order they were taken", body = AuditPage)),
+    tag = "admin"
+)]
+pub async fn audit(
+    query: AuditQuery,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.query_audit(&query)))
+}
+
+/// Legacy balance lookup, replies with the bare balance
 pub async fn account(
     params: AccountBalanceRequest,
-    platform: Arc<Mutex<TradingPlatform>>,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let platform: tokio::sync::MutexGuard<'_, TradingPlatform> = platform.lock().await;
+    record_signer(&params.signer);
+    let platform = metrics.read(&sequencer).await;
 
     match platform.balance_of(&params.signer) {
         Ok(balance) => Ok(warp::reply::json(balance)),
@@ -66,27 +446,298 @@ pub async fn account(
     }
 }
 
+/// Fetch resting orders, oldest first
+#[utoipa::path(
+    get,
+    path = "/v1/orderbook",
+    params(OrderbookQuery),
+    responses((status = 200, description = "A page of resting asks and bids", body = OrderbookPage)),
+    tag = "orders"
+)]
 pub async fn orderbook(
-    platform: Arc<Mutex<TradingPlatform>>,
+    query: OrderbookQuery,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, Infallible> {
-    let platform = platform.lock().await;
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.query_orderbook(&query)))
+}
+
+/// Legacy order book, replies with all resting orders
+pub async fn orderbook_legacy(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
 
     let orderbook = platform.orderbook();
     Ok(warp::reply::json(&orderbook))
 }
 
-pub async fn txlog(platform: Arc<Mutex<TradingPlatform>>) -> Result<impl warp::Reply, Infallible> {
-    let platform = platform.lock().await;
+/// Fetch the traded instruments and their trading rules
+#[utoipa::path(
+    get,
+    path = "/v1/instruments",
+    responses((status = 200, description = "The instruments orders can be placed for", body = [Instrument])),
+    tag = "orders"
+)]
+pub async fn instruments(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&[platform.instrument()]))
+}
+
+/// Fetch the trading states of the market and its instruments
+#[utoipa::path(
+    get,
+    path = "/v1/market",
+    responses((status = 200, description = "Whether orders are matched, and why not", body = MarketStatus)),
+    tag = "orders"
+)]
+pub async fn market(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.market_status()))
+}
+
+/// Fetch the indicative price and volume of an auction
+#[utoipa::path(
+    get,
+    path = "/v1/auction",
+    responses((status = 200, description = "Where the auction would uncross if the call phase ended now", body = AuctionStatus)),
+    tag = "orders"
+)]
+pub async fn auction(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.auction_status()))
+}
+
+/// Fetch the most recent trades
+#[utoipa::path(
+    get,
+    path = "/v1/trades",
+    params(TradesQuery),
+    responses((status = 200, description = "A page of trades, newest first", body = TradesPage)),
+    tag = "orders"
+)]
+pub async fn trades(
+    query: TradesQuery,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.query_trades(&query)))
+}
+
+/// Fetch the transaction log
+#[utoipa::path(
+    get,
+    path = "/v1/txlog",
+    params(TxLogQuery),
+    responses((status = 200, description = "A page of transactions in the order they were applied", body = TxLogPage)),
+    tag = "accounts"
+)]
+pub async fn txlog(
+    query: TxLogQuery,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.query_txlog(&query)))
+}
+
+/// Legacy transaction log, replies with all transactions
+pub async fn txlog_legacy(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
 
     let txlog = platform.txlog();
     Ok(warp::reply::json(&txlog))
 }
 
+/// List accounts with their balances, ordered by signer
+#[utoipa::path(
+    get,
+    path = "/v1/accounts",
+    params(AccountsQuery),
+    responses((status = 200, description = "A page of accounts", body = AccountsPage)),
+    tag = "accounts"
+)]
 pub async fn accounts(
-    platform: Arc<Mutex<TradingPlatform>>,
+    query: AccountsQuery,
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::json(&platform.query_accounts(&query)))
+}
+
+/// Legacy account listing, replies with `(signer, balance)` tuples
+pub async fn accounts_legacy(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
 ) -> Result<impl warp::Reply, Infallible> {
-    let platform = platform.lock().await;
+    let platform = metrics.read(&sequencer).await;
 
     let accounts = platform.accounts();
     Ok(warp::reply::json(&accounts))
 }
+
+pub async fn openapi() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&ApiDoc::openapi()))
+}
+
+/// Liveness: the process is up and serving requests
+pub async fn healthz() -> Result<impl warp::Reply, Infallible> {
+    Ok("ok")
+}
+
+/// Readiness: the state has been recovered and orders can be accepted
+pub async fn readyz(
+    ready: Arc<AtomicBool>,
+    sequencer: Sequencer,
+) -> Result<impl warp::Reply, Infallible> {
+    if !sequencer.is_running() {
+        Ok(warp::reply::with_status(
+            "stopped",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else if ready.load(Ordering::Acquire) {
+        Ok(warp::reply::with_status("ready", StatusCode::OK))
+    } else {
+        Ok(warp::reply::with_status(
+            "recovering",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    }
+}
+
+pub async fn metrics(
+    sequencer: Sequencer,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Infallible> {
+    let platform = metrics.read(&sequencer).await;
+
+    Ok(warp::reply::with_header(
+        metrics.render(&platform, sequencer.queued()),
+        "content-type",
+        "text/plain; version=0.0.4",
+    ))
+}
+
+/// Hands `command` to the engine and records how long it took to be applied. A full queue rejects the request.
+async fn submit<T, F>(
+    sequencer: &Sequencer,
+    metrics: &Arc<Metrics>,
+    command: F,
+) -> Result<T, Rejection>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut TradingPlatform) -> T + Send + 'static,
+{
+    let counter = metrics.clone();
+    let command = move |platform: &mut TradingPlatform| counter.count_fills(platform, command);
+    let result = metrics.time_command(sequencer.submit(command)).await;
+    result.map_err(warp::reject::custom)
+}
+
+/// Turns rejections into an [`ErrorResponse`] with a fitting status code
+pub async fn handle_rejection(rejection: Rejection) -> Result<impl warp::Reply, Infallible> {
+    let overloaded = rejection.find() == Some(&SequencerError::Overloaded);
+    let (status, code, message) = if let Some(OctopusError(err)) = rejection.find() {
+        let status = match err {
+            ApplicationError::AccountNotFound(_)
+            | ApplicationError::OrderNotFound(_)
+            | ApplicationError::InstrumentNotFound(_) => StatusCode::NOT_FOUND,
+            ApplicationError::TradingHalted(_) | ApplicationError::TradingClosed(_) => {
+                StatusCode::CONFLICT
+            }
+            ApplicationError::AccountDisabled(_) => StatusCode::FORBIDDEN,
+            ApplicationError::AccountUnderFunded(_, _)
+            | ApplicationError::AccountOverFunded(_, _)
+            | ApplicationError::NotionalOverflow(_, _)
+            | ApplicationError::OrderQuantityLimit(_, _)
+            | ApplicationError::OrderNotionalLimit(_, _)
+            | ApplicationError::OpenOrdersLimit(_, _)
+            | ApplicationError::DailyVolumeLimit(_, _)
+            | ApplicationError::PriceOutsideCollar(_, _, _) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApplicationError::InvalidOrder(_)
+            | ApplicationError::InvalidAmount(_)
+            | ApplicationError::PriceOffTick(_, _)
+            | ApplicationError::QuantityOffLot(_, _)
+            | ApplicationError::QuantityBelowMinimum(_, _)
+            | ApplicationError::QuantityAboveMaximum(_, _)
+            | ApplicationError::NotionalBelowMinimum(_, _) => StatusCode::BAD_REQUEST,
+        };
+        (status, err.code(), err.to_string())
+    } else if let Some(err) = rejection.find::<SequencerError>() {
+        (StatusCode::SERVICE_UNAVAILABLE, err.code(), err.to_string())
+    } else if rejection.is_not_found() {
+        (
+            StatusCode::NOT_FOUND,
+            "not_found",
+            "no such route".to_string(),
+        )
+    } else if let Some(e) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, "invalid_body", e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, "invalid_query", e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::PayloadTooLarge>() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload_too_large",
+            e.to_string(),
+        )
+    } else if let Some(e) = rejection.find::<warp::reject::UnsupportedMediaType>() {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
+            e.to_string(),
+        )
+    } else if let Some(e) = rejection.find::<warp::reject::MethodNotAllowed>() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed",
+            e.to_string(),
+        )
+    } else {
+        tracing::error!("unhandled rejection: {:?}", rejection);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+            "internal server error".to_string(),
+        )
+    };
+
+    let mut response = warp::reply::with_status(
+        warp::reply::json(&ErrorResponse {
+            code: code.to_string(),
+            message,
+        }),
+        status,
+    )
+    .into_response();
+    if overloaded {
+        // Shed load now and tell clients when to come back instead of queueing without bound
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    }
+    Ok(response)
+}
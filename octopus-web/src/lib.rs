@@ -0,0 +1,16 @@
+//! The octopus trading platform server: the matching engine, the accounts and the REST API around them
+
+
+pub mod accounting;
+pub mod circuit_breaker;
+pub mod config;
+pub mod core;
+pub mod fees;
+pub mod filters;
+pub mod handlers;
+pub mod metrics;
+pub mod openapi;
+pub mod risk;
+pub mod sequencer;
+pub mod telemetry;
+pub mod trading_platform;
@@ -1,24 +1,148 @@
-#![allow(dead_code)]
+use hyper::service::make_service_fn;
+use octopus_web::{
+    config::Config, filters, metrics::Metrics, risk::RiskEngine, sequencer::Sequencer, telemetry,
+    trading_platform::TradingPlatform,
+};
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-mod accounting;
-mod core;
-mod filters;
-mod handlers;
-mod trading_platform;
+#[tokio::main]
+async fn main() {
+    let config = Config::from_env();
+    telemetry::init(config.log_format);
 
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use trading_platform::TradingPlatform;
+    let metrics = Arc::new(Metrics::new());
+    let ready = Arc::new(AtomicBool::new(false));
 
-const SERVER_ADDR: &str = "127.0.0.1:8080";
+    // Requests wait for the engine until the state is recovered, while the probes answer right away
+    let (sequencer, engine) = Sequencer::start(
+        config.queue_capacity,
+        recover(config.clone(), ready.clone()),
+    );
 
-#[tokio::main]
-async fn main() {
-    pretty_env_logger::init();
+    // Ends halts that are over and releases the orders queued meanwhile, even when no orders arrive
+    let ticker = tokio::spawn({
+        let sequencer = sequencer.clone();
+        let metrics = metrics.clone();
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                let metrics = metrics.clone();
+                let tick = move |platform: &mut TradingPlatform| {
+                    metrics.count_fills(platform, TradingPlatform::tick)
+                };
+                if let Err(e) = sequencer.submit(tick).await {
+                    tracing::debug!("tick skipped: {}", e);
+                }
+            }
+        }
+    });
+
+    // A command that panicked stopped the engine, so the server shuts down without it
+    let stopped = {
+        let sequencer = sequencer.clone();
+        async move { sequencer.stopped().await }
+    };
+    let shutdown = async {
+        tokio::select! {
+            _ = shutdown_signal() => {}
+            _ = stopped => tracing::error!("the matching engine stopped, shutting down"),
+        }
+    };
+
+    let routes = filters::routes(sequencer, metrics, ready);
+    let service = telemetry::Traced::new(warp::service(routes));
+    let make_service = make_service_fn(move |_| {
+        let service = service.clone();
+        async move { Ok::<_, Infallible>(service) }
+    });
+
+    let server = hyper::Server::try_bind(&config.addr)
+        .unwrap_or_else(|e| panic!("failed to bind {}: {}", config.addr, e))
+        .serve(make_service);
+    tracing::info!("listening on {}", server.local_addr());
+
+    // Stops accepting new connections once a signal arrives, then waits for in-flight requests to complete
+    if let Err(e) = server.with_graceful_shutdown(shutdown).await {
+        tracing::error!("server error: {}", e);
+    }
+
+    // The routes and the ticker were the last to hold the sequencer, so the engine applies what is queued and stops
+    ticker.abort();
+    let _ = ticker.await;
+    // The state after a panic may be half-changed, so the last snapshot is kept instead
+    let Ok(platform) = engine.await else {
+        tracing::error!("the matching engine panicked, the state is not saved");
+        std::process::exit(1);
+    };
+    if let Some(path) = &config.snapshot {
+        match platform.save(path) {
+            Ok(()) => tracing::info!("state flushed to {}", path.display()),
+            Err(e) => {
+                tracing::error!("failed to flush state to {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Restores the platform from the snapshot (if any), configures what it trades, its fees, risk checks and halts and marks the server as ready
+async fn recover(config: Config, ready: Arc<AtomicBool>) -> TradingPlatform {
+    let mut platform = TradingPlatform::new();
+    if let Some(path) = config.snapshot.filter(|path| path.exists()) {
+        tracing::info!("restoring state from {}", path.display());
+        let restored = tokio::task::spawn_blocking(move || TradingPlatform::load(&path))
+            .await
+            .expect("snapshot loading panicked");
+        match restored {
+            Ok(restored) => platform = restored,
+            Err(e) => {
+                tracing::error!("failed to restore the snapshot: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let platform = platform
+        .with_instrument(config.instrument, config.currency_scale)
+        .with_fees(config.fees, &config.fee_account)
+        .with_risk(RiskEngine::new(config.risk_limits))
+        .with_halts(config.circuit_breaker, config.halt_policy);
+    ready.store(true, Ordering::Release);
+    tracing::info!(
+        symbol = %platform.instrument().symbol,
+        "ready to accept orders"
+    );
+    platform
+}
+
+/// Resolves on the first SIGINT (Ctrl+C) or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for SIGINT");
+    };
 
-    let trading_platform = Arc::new(Mutex::new(TradingPlatform::new()));
-    let routes = filters::routes(trading_platform);
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    warp::serve(routes).run(([127, 0, 0, 1], 8080)).await;
-    // warp::serve(routes).run(SERVER_ADDR).await;
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("shutdown requested, draining in-flight requests");
 }
@@ -0,0 +1,226 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use octopus_common::{
+    core::types::{Receipt, Side},
+    errors::ApplicationError,
+};
+use prometheus::{
+    Counter, Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, Opts, Registry, TextEncoder,
+};
+use tokio::sync::RwLockReadGuard;
+
+use crate::{
+    sequencer::{Sequencer, SequencerError},
+    trading_platform::TradingPlatform,
+};
+
+/// Latency buckets for waiting on the engine, from 10µs to half a second
+const WAIT_BUCKETS: [f64; 10] = [
+    0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5,
+];
+
+/// Prometheus instrumentation of the server: counters follow the requests, gauges are refreshed when scraped
+pub struct Metrics {
+    registry: Registry,
+    orders_accepted: IntCounter,
+    orders_rejected: IntCounterVec,
+    orders_queued: IntCounter,
+    matches: IntCounter,
+    trade_volume: Counter,
+    book_depth: GaugeVec,
+    accounts: IntGauge,
+    queue_depth: IntGauge,
+    commands_shed: IntCounter,
+    command_latency: Histogram,
+    lock_wait: Histogram,
+    request_duration: HistogramVec,
+}
+
+impl Metrics {
+    /// Creates and registers all metrics with a fresh registry
+    pub fn new() -> Self {
+        let orders_accepted =
+            IntCounter::new("octopus_orders_accepted_total", "Orders accepted").unwrap();
+        let orders_rejected = IntCounterVec::new(
+            Opts::new("octopus_orders_rejected_total", "Orders rejected by reason"),
+            &["reason"],
+        )
+        .unwrap();
+        let orders_queued = IntCounter::new(
+            "octopus_orders_queued_total",
+            "Orders queued while trading was halted",
+        )
+        .unwrap();
+        let matches = IntCounter::new("octopus_matches_total", "Matches between orders").unwrap();
+        let trade_volume = Counter::new(
+            "octopus_trade_volume_total",
+            "Units traded across all matches",
+        )
+        .unwrap();
+        let book_depth = GaugeVec::new(
+            Opts::new("octopus_book_depth", "Units resting in the order book"),
+            &["side"],
+        )
+        .unwrap();
+        let accounts = IntGauge::new("octopus_accounts", "Number of accounts").unwrap();
+        let queue_depth = IntGauge::new(
+            "octopus_queue_depth",
+            "Commands waiting for the matching engine",
+        )
+        .unwrap();
+        let commands_shed = IntCounter::new(
+            "octopus_commands_shed_total",
+            "Commands refused because the queue was full",
+        )
+        .unwrap();
+        let command_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "octopus_command_latency_seconds",
+                "Time from queueing a command until the engine applied it",
+            )
+            .buckets(WAIT_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let lock_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "octopus_lock_wait_seconds",
+                "Time spent waiting to read the trading platform",
+            )
+            .buckets(WAIT_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "octopus_request_duration_seconds",
+                "Request latency by route",
+            ),
+            &["route"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(orders_accepted.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(orders_rejected.clone()))
+            .unwrap();
+        registry.register(Box::new(orders_queued.clone())).unwrap();
+        registry.register(Box::new(matches.clone())).unwrap();
+        registry.register(Box::new(trade_volume.clone())).unwrap();
+        registry.register(Box::new(book_depth.clone())).unwrap();
+        registry.register(Box::new(accounts.clone())).unwrap();
+        registry.register(Box::new(queue_depth.clone())).unwrap();
+        registry.register(Box::new(commands_shed.clone())).unwrap();
+        registry
+            .register(Box::new(command_latency.clone()))
+            .unwrap();
+        registry.register(Box::new(lock_wait.clone())).unwrap();
+        registry
+            .register(Box::new(request_duration.clone()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            orders_accepted,
+            orders_rejected,
+            orders_queued,
+            matches,
+            trade_volume,
+            book_depth,
+            accounts,
+            queue_depth,
+            commands_shed,
+            command_latency,
+            lock_wait,
+            request_duration,
+        }
+    }
+
+    /// Locks the platform for reading and records how long that took
+    pub async fn read<'a>(&self, sequencer: &'a Sequencer) -> RwLockReadGuard<'a, TradingPlatform> {
+        let start = Instant::now();
+        let guard = sequencer.read().await;
+        self.lock_wait.observe(start.elapsed().as_secs_f64());
+        guard
+    }
+
+    /// Records how long a command submitted to the [`Sequencer`] took, or that it was shed
+    pub async fn time_command<T>(
+        &self,
+        command: impl Future<Output = Result<T, SequencerError>>,
+    ) -> Result<T, SequencerError> {
+        let start = Instant::now();
+        let result = command.await;
+        match &result {
+            Ok(_) => self.command_latency.observe(start.elapsed().as_secs_f64()),
+            Err(SequencerError::Overloaded) => self.commands_shed.inc(),
+            Err(SequencerError::Stopped) => {}
+        }
+        result
+    }
+
+    /// Records the outcome of [`TradingPlatform::order`]
+    pub fn observe_order(&self, result: &Result<Receipt, ApplicationError>) {
+        match result {
+            // Queued orders are checked again when trading opens, and the audit log records how that went
+            Ok(receipt) if receipt.queued => self.orders_queued.inc(),
+            Ok(_) => self.orders_accepted.inc(),
+            Err(e) => self.orders_rejected.with_label_values(&[e.code()]).inc(),
+        }
+    }
+
+    /// Applies `command` to `platform` and counts the matches it recorded, also those of auctions and queued orders
+    pub fn count_fills<T>(
+        &self,
+        platform: &mut TradingPlatform,
+        command: impl FnOnce(&mut TradingPlatform) -> T,
+    ) -> T {
+        let before = platform.fills().len();
+        let result = command(platform);
+        let fills = platform.fills().get(before..).unwrap_or_default();
+        self.matches.inc_by(fills.len() as u64);
+        // Fractional quantities make the volume a float, which is exact up to 2^53 units
+        self.trade_volume
+            .inc_by(fills.iter().map(|fill| fill.amount.0.to_f64()).sum());
+        result
+    }
+
+    /// Records the latency of a request to `route`
+    pub fn observe_request(&self, route: &str, elapsed: Duration) {
+        self.request_duration
+            .with_label_values(&[route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Refreshes the gauges from `platform` and the number of `queued` commands, and renders all metrics in the Prometheus text format
+    pub fn render(&self, platform: &TradingPlatform, queued: usize) -> String {
+        let (mut bids, mut asks) = (0.0, 0.0);
+        for order in platform.orderbook() {
+            match order.side {
+                Side::Buy => bids += order.remaining.0.to_f64(),
+                Side::Sell => asks += order.remaining.0.to_f64(),
+            }
+        }
+        self.book_depth.with_label_values(&["buy"]).set(bids);
+        self.book_depth.with_label_values(&["sell"]).set(asks);
+        self.accounts.set(platform.accounts().len() as i64);
+        self.queue_depth.set(queued as i64);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("metrics are always encodable");
+        String::from_utf8(buffer).expect("metrics are valid UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
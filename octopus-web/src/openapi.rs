@@ -0,0 +1,90 @@
+use octopus_common::{
+    audit::{AuditEntry, AuditEvent},
+    core::{
+        decimal::Decimal,
+        types::{
+            AccountBalance, AccountBalanceRequest, AccountStatus, AccountUpdateRequest,
+            AccountsPage, Allocation, AmountRequest, AuctionStatus, AuditPage, ErrorResponse, Fee,
+            Instrument, InstrumentStatus, MarketStatus, Notional, Order, OrderbookPage,
+            PartialOrder, Price, Quantity, Receipt, RiskLimits, SendRequest, Side, Trade,
+            TradesPage, TradingState, TradingStateRequest, TradingStatus, TxLogPage,
+        },
+    },
+    tx::{LedgerEntry, Tx, TxKind},
+};
+use utoipa::OpenApi;
+
+use crate::handlers;
+
+/// The OpenAPI 3 description of the `/v1` and `/admin` APIs, served at `/v1/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Octopus", description = "A simple trading platform with an order book engine"),
+    paths(
+        handlers::deposit,
+        handlers::withdraw,
+        handlers::send,
+        handlers::order,
+        handlers::cancel,
+        handlers::cancel_orders,
+        handlers::balance,
+        handlers::limits,
+        handlers::orderbook,
+        handlers::instruments,
+        handlers::market,
+        handlers::auction,
+        handlers::trades,
+        handlers::txlog,
+        handlers::accounts,
+        handlers::disable,
+        handlers::enable,
+        handlers::set_limits,
+        handlers::set_market_state,
+        handlers::set_instrument_state,
+        handlers::audit,
+    ),
+    components(schemas(
+        Side,
+        Decimal,
+        Price,
+        Quantity,
+        Notional,
+        Instrument,
+        Allocation,
+        TradingState,
+        TradingStatus,
+        InstrumentStatus,
+        MarketStatus,
+        AuctionStatus,
+        TradingStateRequest,
+        Order,
+        PartialOrder,
+        Receipt,
+        Fee,
+        AmountRequest,
+        AccountBalance,
+        AccountStatus,
+        RiskLimits,
+        AccountUpdateRequest,
+        AccountBalanceRequest,
+        SendRequest,
+        ErrorResponse,
+        Tx,
+        TxKind,
+        LedgerEntry,
+        TxLogPage,
+        AccountsPage,
+        OrderbookPage,
+        Trade,
+        TradesPage,
+        AuditEvent,
+        AuditEntry,
+        AuditPage,
+    )),
+    tags(
+        (name = "accounts", description = "Balances and transfers"),
+        (name = "orders", description = "Order entry and the order book"),
+        (name = "admin", description = "Operations: disabling accounts, trading states and the audit log"),
+    )
+)]
+pub struct ApiDoc;
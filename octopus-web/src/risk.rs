@@ -0,0 +1,309 @@
+use octopus_common::{
+    core::{
+        decimal::Decimal,
+        types::{Notional, Order, Price, RiskLimits},
+    },
+    errors::ApplicationError,
+};
+
+/// What a [`RiskCheck`] knows about an order besides the order itself
+#[derive(Debug, Clone)]
+pub struct RiskContext {
+    /// The limits of the order's signer, with the defaults filled in
+    pub limits: RiskLimits,
+    /// What the order is worth at its limit price
+    pub notional: Notional,
+    /// Resting orders of the signer
+    pub open_orders: usize,
+    /// What the signer traded today (UTC)
+    pub daily_volume: Notional,
+    /// Price of the most recent trade
+    pub last_price: Option<Price>,
+    /// Halfway between the best bid and ask, if both sides have orders
+    pub mid: Option<Price>,
+}
+
+/// A check every order has to pass before it is matched
+pub trait RiskCheck: Send + Sync {
+    /// # Errors
+    /// The order breaks the check, with an error specific to it
+    fn check(&self, order: &Order, context: &RiskContext) -> Result<(), ApplicationError>;
+}
+
+/// [`RiskLimits::max_order_quantity`]
+pub struct MaxOrderQuantity;
+
+impl RiskCheck for MaxOrderQuantity {
+    fn check(&self, order: &Order, context: &RiskContext) -> Result<(), ApplicationError> {
+        match context.limits.max_order_quantity {
+            Some(max) if order.amount > max => {
+                Err(ApplicationError::OrderQuantityLimit(order.amount, max))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// [`RiskLimits::max_notional`]
+pub struct MaxNotional;
+
+impl RiskCheck for MaxNotional {
+    fn check(&self, _: &Order, context: &RiskContext) -> Result<(), ApplicationError> {
+        match context.limits.max_notional {
+            Some(max) if context.notional > max => {
+                Err(ApplicationError::OrderNotionalLimit(context.notional, max))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// [`RiskLimits::max_open_orders`]. Orders that could rest count, even if they are going to be filled right away.
+pub struct MaxOpenOrders;
+
+impl RiskCheck for MaxOpenOrders {
+    fn check(&self, order: &Order, context: &RiskContext) -> Result<(), ApplicationError> {
+        match context.limits.max_open_orders {
+            Some(max) if context.open_orders >= max => {
+                Err(ApplicationError::OpenOrdersLimit(order.signer.clone(), max))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// [`RiskLimits::max_daily_volume`], assuming the order is filled completely
+pub struct MaxDailyVolume;
+
+impl RiskCheck for MaxDailyVolume {
+    fn check(&self, order: &Order, context: &RiskContext) -> Result<(), ApplicationError> {
+        let Some(max) = context.limits.max_daily_volume else {
+            return Ok(());
+        };
+        match context.daily_volume.checked_add(context.notional) {
+            Some(volume) if volume <= max => Ok(()),
+            _ => Err(ApplicationError::DailyVolumeLimit(
+                order.signer.clone(),
+                max,
+            )),
+        }
+    }
+}
+
+/// The lowest and highest price within `bps` basis points of `reference`. The width is rounded down to the reference price's decimal places.
+pub fn collar(reference: Price, bps: u32) -> (Price, Price) {
+    let width = reference
+        .0
+        .checked_mul(Decimal::new(u64::from(bps), 4), reference.0.scale())
+        .unwrap_or(Decimal::new(u64::MAX, reference.0.scale()));
+    (
+        Price(reference.0.checked_sub(width).unwrap_or(Decimal::ZERO)),
+        Price(reference.0.saturating_add(width)),
+    )
+}
+
+/// [`RiskLimits::price_collar_bps`] around the last trade, or the mid before the first trade, see [`collar`]
+pub struct PriceCollar;
+
+impl RiskCheck for PriceCollar {
+    fn check(&self, order: &Order, context: &RiskContext) -> Result<(), ApplicationError> {
+        let (Some(bps), Some(reference)) = (
+            context.limits.price_collar_bps,
+            context.last_price.or(context.mid),
+        ) else {
+            // Without a reference, there is nothing to compare with and every price passes
+            return Ok(());
+        };
+        let (low, high) = collar(reference, bps);
+        if order.price < low || order.price > high {
+            return Err(ApplicationError::PriceOutsideCollar(order.price, low, high));
+        }
+        Ok(())
+    }
+}
+
+/// Runs the [`RiskCheck`]s in the order they were added, the first one to fail rejects the order
+pub struct RiskEngine {
+    defaults: RiskLimits,
+    checks: Vec<Box<dyn RiskCheck>>,
+}
+
+impl Default for RiskEngine {
+    fn default() -> Self {
+        RiskEngine::new(RiskLimits::default())
+    }
+}
+
+impl RiskEngine {
+    /// The built-in checks with the `defaults` for accounts without limits of their own
+    pub fn new(defaults: RiskLimits) -> Self {
+        RiskEngine {
+            defaults,
+            checks: vec![
+                Box::new(MaxOrderQuantity),
+                Box::new(MaxNotional),
+                Box::new(MaxOpenOrders),
+                Box::new(MaxDailyVolume),
+                Box::new(PriceCollar),
+            ],
+        }
+    }
+
+    /// Runs `check` after the ones there are
+    pub fn add(&mut self, check: impl RiskCheck + 'static) {
+        self.checks.push(Box::new(check));
+    }
+
+    /// The limits of accounts that don't have their own
+    pub fn defaults(&self) -> &RiskLimits {
+        &self.defaults
+    }
+
+    /// # Errors
+    /// The error of the first check that fails
+    pub fn check(&self, order: &Order, context: &RiskContext) -> Result<(), ApplicationError> {
+        self.checks
+            .iter()
+            .try_for_each(|check| check.check(order, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use octopus_common::core::types::{Quantity, Side};
+
+    fn order(price: &str, amount: u64) -> Order {
+        Order {
+            price: price.parse().unwrap(),
+            amount: Quantity::from(amount),
+            side: Side::Buy,
+            signer: "ALICE".to_string(),
+        }
+    }
+
+    fn context(limits: RiskLimits) -> RiskContext {
+        RiskContext {
+            limits,
+            notional: 100.into(),
+            open_orders: 2,
+            daily_volume: 900.into(),
+            last_price: None,
+            mid: None,
+        }
+    }
+
+    #[test]
+    fn test_RiskEngine_without_limits_passes_everything() {
+        let engine = RiskEngine::default();
+        assert!(engine
+            .check(&order("100", u64::MAX), &context(RiskLimits::default()))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_RiskEngine_rejects_each_limit_with_its_own_error() {
+        let engine = RiskEngine::default();
+        let cases = [
+            (
+                RiskLimits {
+                    max_order_quantity: Some(Quantity::from(9)),
+                    ..Default::default()
+                },
+                "order_quantity_limit",
+            ),
+            (
+                RiskLimits {
+                    max_notional: Some("99.99".parse().unwrap()),
+                    ..Default::default()
+                },
+                "order_notional_limit",
+            ),
+            (
+                RiskLimits {
+                    max_open_orders: Some(2),
+                    ..Default::default()
+                },
+                "open_orders_limit",
+            ),
+            (
+                RiskLimits {
+                    max_daily_volume: Some("999.99".parse().unwrap()),
+                    ..Default::default()
+                },
+                "daily_volume_limit",
+            ),
+        ];
+        for (limits, code) in cases {
+            let err = engine
+                .check(&order("10", 10), &context(limits))
+                .unwrap_err();
+            assert_eq!(err.code(), code);
+        }
+
+        // Right at the limits is fine
+        let limits = RiskLimits {
+            max_order_quantity: Some(Quantity::from(10)),
+            max_notional: Some(100.into()),
+            max_open_orders: Some(3),
+            max_daily_volume: Some(1_000.into()),
+            price_collar_bps: Some(0),
+        };
+        assert!(engine.check(&order("10", 10), &context(limits)).is_ok());
+    }
+
+    #[test]
+    fn test_PriceCollar_is_around_the_last_trade_or_the_mid() {
+        let limits = RiskLimits {
+            price_collar_bps: Some(1_000),
+            ..Default::default()
+        };
+        let check = |price: &str, last_price: Option<&str>, mid: Option<&str>| {
+            let context = RiskContext {
+                last_price: last_price.map(|p| p.parse().unwrap()),
+                mid: mid.map(|p| p.parse().unwrap()),
+                ..context(limits.clone())
+            };
+            PriceCollar.check(&order(price, 1), &context)
+        };
+
+        assert!(check("90.00", Some("100.00"), Some("50.00")).is_ok());
+        assert!(check("110.00", Some("100.00"), None).is_ok());
+        assert_eq!(
+            check("110.01", Some("100.00"), None),
+            Err(ApplicationError::PriceOutsideCollar(
+                "110.01".parse().unwrap(),
+                "90.00".parse().unwrap(),
+                "110.00".parse().unwrap()
+            ))
+        );
+        assert!(check("89.99", Some("100.00"), None).is_err());
+        // Before the first trade
+        assert!(check("54.99", None, Some("50.00")).is_ok());
+        assert!(check("55.01", None, Some("50.00")).is_err());
+        assert!(check("1000000", None, None).is_ok());
+    }
+
+    #[test]
+    fn test_RiskEngine_runs_added_checks() {
+        struct NoOddQuantities;
+        impl RiskCheck for NoOddQuantities {
+            fn check(&self, order: &Order, _: &RiskContext) -> Result<(), ApplicationError> {
+                if order.amount.0.is_multiple_of(2.into()) {
+                    Ok(())
+                } else {
+                    Err(ApplicationError::InvalidOrder("odd".to_string()))
+                }
+            }
+        }
+
+        let mut engine = RiskEngine::default();
+        engine.add(NoOddQuantities);
+        let context = context(RiskLimits::default());
+        assert!(engine.check(&order("10", 2), &context).is_ok());
+        assert!(engine.check(&order("10", 3), &context).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! The single writer of the [`TradingPlatform`].
+//!
+//! Every change to the platform is queued on a bounded channel and applied by one engine task, in order.
+//! A command that panics may have left the platform half-changed, so it stops the engine.
+
+// Request handlers never hold the platform while they change it, they wait for the engine's reply instead.
+// Reads share a read lock, so they don't queue behind each other, and the engine applies everything that
+// queued up meanwhile under a single write lock before it lets them in again.
+
+use std::{fmt, future::Future, sync::Arc};
+
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot, RwLock, RwLockReadGuard,
+    },
+    task::JoinHandle,
+};
+use tracing::Span;
+
+use crate::trading_platform::TradingPlatform;
+
+/// Most commands applied under one write lock, so readers get their turn on a busy platform
+const MAX_BATCH: usize = 64;
+
+type Command = Box<dyn FnOnce(&mut TradingPlatform) + Send>;
+
+/// Why a command wasn't applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequencerError {
+    /// The queue is full, the client should back off and retry
+    Overloaded,
+    /// The engine doesn't take commands anymore
+    Stopped,
+}
+
+impl SequencerError {
+    /// Error code for the API
+    pub fn code(&self) -> &'static str {
+        match self {
+            SequencerError::Overloaded => "overloaded",
+            SequencerError::Stopped => "unavailable",
+        }
+    }
+}
+
+impl fmt::Display for SequencerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SequencerError::Overloaded => write!(f, "too many pending requests, retry later"),
+            SequencerError::Stopped => write!(f, "the trading platform is not available"),
+        }
+    }
+}
+
+impl warp::reject::Reject for SequencerError {}
+
+/// Handle to the engine task. Clones share the same queue and platform.
+#[derive(Clone)]
+pub struct Sequencer {
+    commands: mpsc::Sender<Command>,
+    platform: Arc<RwLock<TradingPlatform>>,
+}
+
+impl Sequencer {
+    /// Spawns the engine task with room for `capacity` queued commands, on the platform `init` resolves to
+    ///
+    /// Once every handle is dropped, the engine applies what is left in the queue and the task returns the final state.
+    /// If a command panics, the task ends with that panic and every command after it fails with [`SequencerError::Stopped`].
+    pub fn start<F>(capacity: usize, init: F) -> (Sequencer, JoinHandle<TradingPlatform>)
+    where
+        F: Future<Output = TradingPlatform> + Send + 'static,
+    {
+        let (commands, mut queue) = mpsc::channel::<Command>(capacity);
+        let platform = Arc::new(RwLock::new(TradingPlatform::new()));
+        // Until `init` resolves, reads wait and commands queue up
+        let mut recovering = platform
+            .clone()
+            .try_write_owned()
+            .expect("nobody else knows the lock yet");
+
+        let state = platform.clone();
+        let engine = tokio::spawn(async move {
+            *recovering = init.await;
+            drop(recovering);
+
+            while let Some(command) = queue.recv().await {
+                let mut platform = state.write().await;
+                command(&mut platform);
+                for _ in 1..MAX_BATCH {
+                    match queue.try_recv() {
+                        Ok(command) => command(&mut platform),
+                        Err(_) => break,
+                    }
+                }
+            }
+            std::mem::take(&mut *state.write().await)
+        });
+
+        (Sequencer { commands, platform }, engine)
+    }
+
+    /// Queues `command` and waits until the engine has applied it, or fails with [`SequencerError::Overloaded`] if the queue is full
+    ///
+    /// The command runs inside the caller's span, so whatever it logs is attributed to the request.
+    pub async fn submit<T, F>(&self, command: F) -> Result<T, SequencerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut TradingPlatform) -> T + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let span = Span::current();
+        self.commands
+            .try_send(Box::new(move |platform| {
+                // The caller may have gone away, the command counts nonetheless
+                let _ = reply.send(span.in_scope(|| command(platform)));
+            }))
+            .map_err(|e| match e {
+                TrySendError::Full(_) => SequencerError::Overloaded,
+                TrySendError::Closed(_) => SequencerError::Stopped,
+            })?;
+        result.await.map_err(|_| SequencerError::Stopped)
+    }
+
+    /// Waits for the engine to finish its current batch and locks the platform for reading
+    pub async fn read(&self) -> RwLockReadGuard<'_, TradingPlatform> {
+        self.platform.read().await
+    }
+
+    /// Number of commands waiting for the engine
+    pub fn queued(&self) -> usize {
+        self.commands.max_capacity() - self.commands.capacity()
+    }
+
+    /// Whether the engine still takes commands
+    pub fn is_running(&self) -> bool {
+        !self.commands.is_closed()
+    }
+
+    /// Resolves once the engine doesn't take commands anymore
+    pub async fn stopped(&self) {
+        self.commands.closed().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use octopus_common::core::types::{Notional, Order, Price, Quantity, Side};
+    use std::future::ready;
+
+    #[tokio::test]
+    async fn test_Sequencer_applies_concurrent_commands_one_at_a_time() {
+        let (sequencer, _) = Sequencer::start(16, ready(TradingPlatform::new()));
+        let pending: Vec<_> = (1..=10)
+            .map(|price| {
+                let sequencer = sequencer.clone();
+                tokio::spawn(async move {
+                    sequencer
+                        .submit(move |platform| {
+                            platform.deposit("ALICE", 100.into()).unwrap();
+                            platform
+                                .order(Order {
+                                    price: Price::from(price),
+                                    amount: Quantity::from(1),
+                                    side: Side::Sell,
+                                    signer: "ALICE".to_string(),
+                                })
+                                .unwrap()
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        let mut ordinals = vec![];
+        for receipt in pending {
+            ordinals.push(receipt.await.unwrap().ordinal);
+        }
+        ordinals.sort_unstable();
+        assert_eq!(ordinals, (1..=10).collect::<Vec<_>>());
+        assert_eq!(
+            sequencer.read().await.balance_of("ALICE").copied(),
+            Ok(Notional::from(1000))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_Sequencer_rejects_commands_when_the_queue_is_full() {
+        // Nothing is applied before the platform is there, so the queue fills up
+        let (recovered, recovery) = oneshot::channel();
+        let (sequencer, _) = Sequencer::start(2, async {
+            recovery.await.unwrap();
+            TradingPlatform::new()
+        });
+        let queued: Vec<_> = (0..2)
+            .map(|_| {
+                let sequencer = sequencer.clone();
+                tokio::spawn(async move {
+                    sequencer
+                        .submit(|platform| platform.deposit("BOB", 1.into()))
+                        .await
+                })
+            })
+            .collect();
+        tokio::task::yield_now().await;
+
+        assert_eq!(sequencer.queued(), 2);
+        assert_eq!(
+            sequencer.submit(|_| ()).await,
+            Err(SequencerError::Overloaded)
+        );
+
+        recovered.send(()).unwrap();
+        for command in queued {
+            assert!(command.await.unwrap().unwrap().is_ok());
+        }
+        assert_eq!(
+            sequencer.read().await.balance_of("BOB").copied(),
+            Ok(Notional::from(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_Sequencer_drains_the_queue_and_returns_the_final_state() {
+        let (sequencer, engine) = Sequencer::start(16, async {
+            let mut platform = TradingPlatform::new();
+            platform.deposit("ALICE", 5.into()).unwrap();
+            platform
+        });
+        let deposit = sequencer.submit(|platform| platform.deposit("ALICE", 5.into()));
+        assert!(deposit.await.unwrap().is_ok());
+        drop(sequencer);
+
+        let platform = engine.await.unwrap();
+        assert_eq!(
+            platform.balance_of("ALICE").copied(),
+            Ok(Notional::from(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_Sequencer_stops_after_a_panicking_command() {
+        let (sequencer, engine) = Sequencer::start(16, ready(TradingPlatform::new()));
+        assert!(sequencer.is_running());
+
+        let result = sequencer.submit(|_| -> u64 { panic!("boom") }).await;
+        assert_eq!(result, Err(SequencerError::Stopped));
+        sequencer.stopped().await;
+        assert!(!sequencer.is_running());
+        let deposit = sequencer.submit(|platform| platform.deposit("ALICE", 1.into()));
+        assert_eq!(deposit.await, Err(SequencerError::Stopped));
+        assert!(engine.await.is_err_and(|e| e.is_panic()));
+    }
+}
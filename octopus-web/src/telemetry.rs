@@ -0,0 +1,150 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use hyper::{header::HeaderValue, service::Service, Body, Request, Response};
+use tracing::{field, Instrument};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::config::LogFormat;
+
+/// Carries the correlation ID of a request, both ways
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Client-provided request IDs longer than this are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Installs the global log subscriber. Verbosity is controlled by `RUST_LOG` (default: `info`).
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .init(),
+    }
+}
+
+/// Wraps a service so that everything logged while handling a request, down to the platform, carries its request ID
+///
+/// The ID is taken from the `X-Request-Id` header or generated if the header is absent, and it is echoed in the response.
+#[derive(Clone)]
+pub struct Traced<S> {
+    inner: S,
+}
+
+impl<S> Traced<S> {
+    pub fn new(inner: S) -> Self {
+        Traced { inner }
+    }
+}
+
+impl<S> Service<Request<Body>> for Traced<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.to_str().is_ok())
+            .cloned()
+            .unwrap_or_else(|| {
+                HeaderValue::from_str(&Uuid::new_v4().to_string()).expect("UUIDs are valid headers")
+            });
+        // Handlers see the same ID, whether it was provided or not
+        req.headers_mut()
+            .insert(REQUEST_ID_HEADER, request_id.clone());
+
+        let span = tracing::info_span!(
+            "request",
+            request_id = request_id.to_str().unwrap_or_default(),
+            method = %req.method(),
+            route = req.uri().path(),
+            signer = field::Empty,
+        );
+        let start = Instant::now();
+        let response = span.in_scope(|| self.inner.call(req));
+
+        Box::pin(
+            async move {
+                let mut response = response.await?;
+                response.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+                tracing::info!(
+                    status = response.status().as_u16(),
+                    latency_us = start.elapsed().as_micros() as u64,
+                    "request completed"
+                );
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Attaches the account that signed the current request to its span
+pub fn record_signer(signer: &str) {
+    tracing::Span::current().record("signer", signer);
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use crate::{
+        filters, metrics::Metrics, sequencer::Sequencer, trading_platform::TradingPlatform,
+    };
+    use std::{
+        future::ready,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    fn service() -> impl Service<Request<Body>, Response = Response<Body>, Error = Infallible> {
+        let (sequencer, _) = Sequencer::start(16, ready(TradingPlatform::new()));
+        Traced::new(warp::service(filters::routes(
+            sequencer,
+            Arc::new(Metrics::new()),
+            Arc::new(AtomicBool::new(true)),
+        )))
+    }
+
+    #[tokio::test]
+    async fn test_Traced_echoes_request_id() {
+        let req = Request::get("/healthz")
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = service().call(req).await.unwrap();
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "abc-123");
+    }
+
+    #[tokio::test]
+    async fn test_Traced_generates_missing_request_id() {
+        let req = Request::get("/healthz").body(Body::empty()).unwrap();
+
+        let res = service().call(req).await.unwrap();
+        let id = res.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+    }
+}
@@ -1,18 +1,110 @@
 use octopus_common::{
-    core::types::{Order, PartialOrder, Receipt, Side},
+    audit::{AuditEntry, AuditEvent},
+    core::{
+        decimal::Decimal,
+        types::{
+            AccountBalance, AccountStatus, AccountsQuery, AuctionStatus, AuditQuery, Fee,
+            Instrument, InstrumentStatus, MarketStatus, Notional, Order, OrderbookQuery, Page,
+            PartialOrder, Price, Quantity, Receipt, RiskLimits, Side, Trade, TradesQuery,
+            TradingState, TradingStateRequest, TradingStatus, TxLogQuery,
+        },
+    },
     errors::ApplicationError,
-    tx::Tx,
+    tx::{LedgerEntry, Tx},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    accounting::Accounts,
+    circuit_breaker::{CircuitBreaker, HaltPolicy},
+    core::matching::{Fill, MatchingEngine},
+    fees::{self, FeeSchedule, FeeTier, Volumes, DEFAULT_FEE_ACCOUNT},
+    risk::{RiskContext, RiskEngine},
 };
 
-use crate::{accounting::Accounts, core::matching::MatchingEngine};
+/// Page size if a query doesn't set a `limit`
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+/// Upper bound for the `limit` of a query
+pub const MAX_PAGE_SIZE: usize = 1000;
+/// Decimal places of balances unless configured otherwise
+pub const DEFAULT_CURRENCY_SCALE: u8 = 2;
+/// Milliseconds of a day, which daily volume limits start over after
+const DAY: u64 = 24 * 60 * 60 * 1000;
 
 /// The core of the core: the [`TradingPlatform`]. Manages accounts, validates-, and orchestrates the processing of each order.
 ///
 ///
+#[derive(Serialize, Deserialize)]
 pub struct TradingPlatform {
     matching_engine: MatchingEngine,
     accounts: Accounts,
-    transactions: Vec<Tx>,
+    transactions: Vec<LedgerEntry>,
+    /// What is traded. It is configuration rather than state, so it isn't part of a snapshot.
+    #[serde(skip)]
+    instrument: Instrument,
+    /// Decimal places of balances, which notionals are rounded down to
+    #[serde(skip, default = "default_currency_scale")]
+    currency_scale: u8,
+    /// The fee tiers, configuration like the instrument
+    #[serde(skip)]
+    fees: FeeSchedule,
+    /// Where fees are credited to
+    #[serde(skip, default = "default_fee_account")]
+    fee_account: String,
+    /// Recent trading volume of each account, which decides its fee tier
+    #[serde(default)]
+    volumes: Volumes,
+    /// The pre-trade checks and the default limits, configuration like the fees
+    #[serde(skip)]
+    risk: RiskEngine,
+    /// Limits of accounts that differ from the defaults
+    #[serde(default)]
+    risk_limits: HashMap<String, RiskLimits>,
+    /// Accounts that may not place orders
+    #[serde(default)]
+    disabled: HashSet<String>,
+    /// Administrative and bulk actions, oldest first
+    #[serde(default)]
+    audit: Vec<AuditEntry>,
+    /// Trading state of the whole market
+    #[serde(default)]
+    market_status: TradingStatus,
+    /// Trading state of the instrument
+    #[serde(default)]
+    instrument_status: TradingStatus,
+    /// Orders waiting for trading to open, oldest first, with the ordinals they were given when they arrived
+    #[serde(default)]
+    queued: VecDeque<PartialOrder>,
+    /// Orders were collected in the call phase of an auction, so the book may be crossed until it is uncrossed
+    #[serde(default)]
+    auction: bool,
+    /// Halts trading on large price moves, configuration like the fees. Recent prices aren't kept in a snapshot.
+    #[serde(skip)]
+    circuit_breaker: CircuitBreaker,
+    /// What happens to orders while trading is halted
+    #[serde(skip)]
+    halt_policy: HaltPolicy,
+}
+
+fn default_currency_scale() -> u8 {
+    DEFAULT_CURRENCY_SCALE
+}
+
+fn default_fee_account() -> String {
+    DEFAULT_FEE_ACCOUNT.to_string()
+}
+
+impl Default for TradingPlatform {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TradingPlatform {
@@ -22,122 +114,1608 @@ impl TradingPlatform {
             matching_engine: MatchingEngine::new(),
             accounts: Accounts::new(),
             transactions: vec![],
+            instrument: Instrument::default(),
+            currency_scale: DEFAULT_CURRENCY_SCALE,
+            fees: FeeSchedule::default(),
+            fee_account: default_fee_account(),
+            volumes: Volumes::default(),
+            risk: RiskEngine::default(),
+            risk_limits: HashMap::new(),
+            disabled: HashSet::new(),
+            audit: vec![],
+            market_status: TradingStatus::default(),
+            instrument_status: TradingStatus::default(),
+            queued: VecDeque::new(),
+            auction: false,
+            circuit_breaker: CircuitBreaker::default(),
+            halt_policy: HaltPolicy::default(),
+        }
+    }
+
+    /// Trades `instrument`, allocating matches by its rules, and keeps balances with `currency_scale` decimal places instead of the defaults
+    ///
+    /// # Panics
+    /// A balance, e.g. one restored from a snapshot, has more decimal places than `currency_scale`
+    pub fn with_instrument(mut self, instrument: Instrument, currency_scale: u8) -> Self {
+        if let Err(signer) = self.accounts.rescale(currency_scale) {
+            panic!(
+                "the balance of {} can't be kept with {} decimal places",
+                signer, currency_scale
+            );
+        }
+        self.matching_engine
+            .set_allocation(instrument.allocation, instrument.lot_size);
+        self.instrument = instrument;
+        self.currency_scale = currency_scale;
+        self
+    }
+
+    /// Charges fees by `schedule` and credits them to `fee_account` instead of trading for free
+    pub fn with_fees(mut self, schedule: FeeSchedule, fee_account: &str) -> Self {
+        self.fees = schedule;
+        self.fee_account = fee_account.to_string();
+        self
+    }
+
+    /// Runs the checks of `risk` before matching an order instead of the built-in ones without limits
+    pub fn with_risk(mut self, risk: RiskEngine) -> Self {
+        self.risk = risk;
+        self
+    }
+
+    /// Halts trading when `circuit_breaker` trips and treats orders meanwhile by `halt_policy`
+    pub fn with_halts(mut self, circuit_breaker: CircuitBreaker, halt_policy: HaltPolicy) -> Self {
+        self.circuit_breaker = circuit_breaker;
+        self.halt_policy = halt_policy;
+        self
+    }
+
+    /// The trading states of the market and the instrument
+    pub fn market_status(&self) -> MarketStatus {
+        MarketStatus {
+            market: self.market_status.clone(),
+            instruments: vec![InstrumentStatus {
+                symbol: self.instrument.symbol.clone(),
+                status: self.instrument_status.clone(),
+                state: self.trading_state(now()),
+                queued: self.queued.len(),
+                queued_orders: self.queued.iter().cloned().collect(),
+            }],
+        }
+    }
+
+    /// Where an auction would uncross if its call phase ended now
+    pub fn auction_status(&self) -> AuctionStatus {
+        let clearing = self.matching_engine.clearing();
+        AuctionStatus {
+            symbol: self.instrument.symbol.clone(),
+            state: self.trading_state(now()),
+            price: clearing.as_ref().map(|c| c.price),
+            volume: clearing.as_ref().map_or(Quantity::ZERO, |c| c.volume),
+            surplus: clearing.as_ref().map_or(Quantity::ZERO, |c| c.surplus),
+            surplus_side: clearing.and_then(|c| c.surplus_side),
+        }
+    }
+
+    /// Sets the trading state of the instrument with `symbol`, or of the whole market without one, and applies it at once
+    ///
+    /// # Errors
+    /// No instrument with `symbol` is traded
+    pub fn set_trading_state(
+        &mut self,
+        symbol: Option<&str>,
+        request: TradingStateRequest,
+    ) -> Result<MarketStatus, ApplicationError> {
+        let status = TradingStatus {
+            state: request.state,
+            reason: request.reason,
+            until: None,
+        };
+        match symbol {
+            None => self.market_status = status.clone(),
+            Some(symbol) if symbol == self.instrument.symbol => {
+                self.instrument_status = status.clone()
+            }
+            Some(symbol) => return Err(ApplicationError::InstrumentNotFound(symbol.to_string())),
+        }
+        tracing::warn!(symbol, state = ?status.state, reason = ?status.reason, "trading state set");
+        self.audit(AuditEvent::TradingStateChanged {
+            instrument: symbol.map(str::to_string),
+            state: status.state,
+            reason: status.reason,
+        });
+        self.tick();
+        Ok(self.market_status())
+    }
+
+    /// Ends halts that are over, uncrosses an auction whose call phase ended and releases the queued orders if it can
+    pub fn tick(&mut self) {
+        // Runs before every order, and should run regularly in between so queued orders don't wait for the next one
+        let now = now();
+        let mut ended = vec![];
+        if self.market_status.until.is_some_and(|until| until <= now) {
+            self.market_status = TradingStatus::default();
+            ended.push(None);
+        }
+        if self
+            .instrument_status
+            .until
+            .is_some_and(|until| until <= now)
+        {
+            self.instrument_status = TradingStatus::default();
+            ended.push(Some(self.instrument.symbol.clone()));
+        }
+        for instrument in ended {
+            tracing::warn!(symbol = ?instrument, "halt ended");
+            self.audit(AuditEvent::TradingStateChanged {
+                instrument,
+                state: TradingState::Open,
+                reason: Some("the halt is over".to_string()),
+            });
+        }
+        // A call phase that ended in a halt uncrosses once trading opens or closes
+        if self.auction
+            && matches!(
+                self.trading_state(now),
+                TradingState::Open | TradingState::Closed
+            )
+        {
+            self.uncross(now);
+        }
+        // Matching a queued order can trip the circuit breaker again
+        loop {
+            let state = self.trading_state(now);
+            if !matches!(state, TradingState::Open | TradingState::AuctionOnly) {
+                break;
+            }
+            let Some(queued) = self.queued.pop_front() else {
+                break;
+            };
+            let (ordinal, account) = (queued.ordinal, queued.signer.clone());
+            let order = Order {
+                price: queued.price,
+                amount: queued.remaining,
+                side: queued.side,
+                signer: queued.signer,
+            };
+            let placed = match state {
+                TradingState::AuctionOnly => self.call(order, Some(ordinal)),
+                _ => self.place(order, Some(ordinal)),
+            };
+            match placed {
+                Ok(_) => {
+                    tracing::info!(ordinal, signer = %account, "queued order placed");
+                    self.audit(AuditEvent::QueuedOrderPlaced { account, ordinal });
+                }
+                Err(err) => {
+                    tracing::warn!(ordinal, signer = %account, error = %err, "queued order rejected");
+                    self.audit(AuditEvent::QueuedOrderRejected {
+                        account,
+                        ordinal,
+                        reason: err.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// The state orders are treated by at `now`: the more restrictive of the market's and the instrument's
+    fn trading_state(&self, now: u64) -> TradingState {
+        self.market_status
+            .state_at(now)
+            .max(self.instrument_status.state_at(now))
+    }
+
+    /// The limits of `signer`, its own where it has them and the defaults otherwise
+    pub fn risk_limits(&self, signer: &str) -> RiskLimits {
+        self.risk_limits.get(signer).map_or_else(
+            || self.risk.defaults().clone(),
+            |limits| limits.or(self.risk.defaults()),
+        )
+    }
+
+    /// Gives an account its own limits, records it in the audit log and returns the limits that apply now
+    ///
+    /// # Errors
+    /// The account doesn't exist
+    pub fn set_risk_limits(
+        &mut self,
+        signer: &str,
+        limits: RiskLimits,
+    ) -> Result<RiskLimits, ApplicationError> {
+        self.balance_of(signer)?;
+        if limits == RiskLimits::default() {
+            self.risk_limits.remove(signer);
+        } else {
+            self.risk_limits.insert(signer.to_string(), limits.clone());
+        }
+        tracing::info!(signer, "risk limits set");
+        self.audit(AuditEvent::RiskLimitsSet {
+            account: signer.to_string(),
+            limits,
+        });
+        Ok(self.risk_limits(signer))
+    }
+
+    /// The fee tier `signer` is in at the moment
+    pub fn fee_tier(&self, signer: &str) -> FeeTier {
+        self.fees.tier(self.volumes.volume(signer, now()))
+    }
+
+    /// The instrument that is traded
+    pub fn instrument(&self) -> &Instrument {
+        &self.instrument
+    }
+
+    /// Decimal places of balances
+    pub fn currency_scale(&self) -> u8 {
+        self.currency_scale
+    }
+
+    /// Restores a platform from a snapshot previously written by [`TradingPlatform::save`]
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the complete state to `path`, replacing the previous snapshot at once
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        // Renaming a complete file means an interrupted write never leaves a torn snapshot behind
+        let tmp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(tmp, path)
+    }
+
+    /// Takes a resting order out of the book, or a queued order out of the queue
+    pub fn cancel(&mut self, ordinal: u64) -> Result<PartialOrder, ApplicationError> {
+        let queued = self
+            .queued
+            .iter()
+            .position(|order| order.ordinal == ordinal)
+            .and_then(|i| self.queued.remove(i));
+        let order = queued
+            .or_else(|| self.matching_engine.cancel(ordinal))
+            .ok_or(ApplicationError::OrderNotFound(ordinal))?;
+        tracing::info!(ordinal, signer = %order.signer, "order cancelled");
+        Ok(order)
+    }
+
+    /// Takes all orders of `signer`, or only those on `side`, out of the book and the queue and records it in the audit log
+    ///
+    /// # Errors
+    /// The account doesn't exist
+    pub fn cancel_orders(
+        &mut self,
+        signer: &str,
+        side: Option<Side>,
+    ) -> Result<Vec<PartialOrder>, ApplicationError> {
+        self.balance_of(signer)?;
+        let mut cancelled = self.matching_engine.cancel_all(signer, side.as_ref());
+        cancelled.extend(self.dequeue(|order| {
+            order.signer == signer && side.as_ref().is_none_or(|side| &order.side == side)
+        }));
+        tracing::info!(signer, side = ?side, cancelled = cancelled.len(), "orders cancelled");
+        self.audit(AuditEvent::OrdersCancelled {
+            account: signer.to_string(),
+            side,
+            cancelled: cancelled.iter().map(|order| order.ordinal).collect(),
+        });
+        Ok(cancelled)
+    }
+
+    /// Takes the queued orders that match `filter` out of the queue
+    fn dequeue(&mut self, filter: impl Fn(&PartialOrder) -> bool) -> Vec<PartialOrder> {
+        let (dequeued, queued) = self.queued.drain(..).partition(filter);
+        self.queued = queued;
+        Vec::from(dequeued)
+    }
+
+    /// Stops `signer` from placing orders, cancels its orders and records it in the audit log
+    ///
+    /// # Errors
+    /// The account doesn't exist
+    pub fn disable(&mut self, signer: &str) -> Result<AccountStatus, ApplicationError> {
+        self.balance_of(signer)?;
+        // Disabling a disabled account changes nothing, but is still recorded
+        self.disabled.insert(signer.to_string());
+        let mut cancelled = self.matching_engine.cancel_all(signer, None);
+        cancelled.extend(self.dequeue(|order| order.signer == signer));
+        tracing::warn!(signer, cancelled = cancelled.len(), "account disabled");
+        self.audit(AuditEvent::AccountDisabled {
+            account: signer.to_string(),
+            cancelled: cancelled.iter().map(|order| order.ordinal).collect(),
+        });
+        Ok(AccountStatus {
+            signer: signer.to_string(),
+            disabled: true,
+            cancelled,
+        })
+    }
+
+    /// Lets a disabled account place orders again. Its cancelled orders stay cancelled.
+    ///
+    /// # Errors
+    /// The account doesn't exist
+    pub fn enable(&mut self, signer: &str) -> Result<AccountStatus, ApplicationError> {
+        self.balance_of(signer)?;
+        self.disabled.remove(signer);
+        tracing::warn!(signer, "account enabled");
+        self.audit(AuditEvent::AccountEnabled {
+            account: signer.to_string(),
+        });
+        Ok(self.status(signer))
+    }
+
+    /// Whether `signer` may place orders
+    pub fn status(&self, signer: &str) -> AccountStatus {
+        AccountStatus {
+            signer: signer.to_string(),
+            disabled: self.disabled.contains(signer),
+            cancelled: vec![],
         }
     }
 
+    /// Fetches the filtered audit log one page at a time
+    pub fn query_audit(&self, query: &AuditQuery) -> Page<AuditEntry, u64> {
+        // Entry ids are positions in the log, so the cursor can be used as an index
+        let start = query
+            .after
+            .map_or(0, |after| after.min(self.audit.len() as u64) as usize);
+
+        let entries = self.audit[start..]
+            .iter()
+            .filter(|entry| {
+                query
+                    .account
+                    .as_ref()
+                    .is_none_or(|account| entry.event.account() == Some(account))
+            })
+            .cloned();
+        paginate(entries, query.limit, |entry| entry.id)
+    }
+
     /// Fetches the complete order book at this time
     pub fn orderbook(&self) -> Vec<PartialOrder> {
-        self.matching_engine
-            .asks
-            .values()
-            .cloned()
-            .chain(self.matching_engine.bids.values().cloned())
-            .flatten()
-            .collect()
+        self.matching_engine.orders().collect()
+    }
+
+    /// Fetches the filtered order book one page at a time, ordered by ordinal
+    pub fn query_orderbook(&self, query: &OrderbookQuery) -> Page<PartialOrder, u64> {
+        let mut orders = self.orderbook();
+        orders.sort_unstable_by_key(|order| order.ordinal);
+
+        let orders = orders.into_iter().filter(|order| {
+            query.after.is_none_or(|after| order.ordinal > after)
+                && query.side.as_ref().is_none_or(|side| &order.side == side)
+                && query
+                    .signer
+                    .as_ref()
+                    .is_none_or(|signer| &order.signer == signer)
+        });
+        paginate(orders, query.limit, |order| order.ordinal)
+    }
+
+    /// All matches so far, oldest first
+    pub fn fills(&self) -> &[Fill] {
+        &self.matching_engine.history
+    }
+
+    /// Fetches the trades one page at a time, newest first
+    pub fn query_trades(&self, query: &TradesQuery) -> Page<Trade, u64> {
+        // Trade ids are positions in the history, so the cursor can be used as an index
+        let history = &self.matching_engine.history;
+        let end = query.before.map_or(history.len(), |before| {
+            (before.saturating_sub(1) as usize).min(history.len())
+        });
+
+        let trades = history[..end]
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, fill)| Trade {
+                id: i as u64 + 1,
+                ordinal: fill.ordinal,
+                maker_ordinal: fill.maker_ordinal,
+                price: fill.price,
+                amount: fill.amount,
+                side: match fill.side {
+                    Side::Buy => Side::Sell,
+                    Side::Sell => Side::Buy,
+                },
+            });
+        paginate(trades, query.limit, |trade| trade.id)
     }
 
     /// Fetches list of all transactions
-    pub fn txlog(&self) -> &Vec<Tx> {
-        self.transactions.as_ref()
+    pub fn txlog(&self) -> Vec<&Tx> {
+        self.transactions.iter().map(|entry| &entry.tx).collect()
+    }
+
+    /// Fetches the filtered transaction log one page at a time
+    pub fn query_txlog(&self, query: &TxLogQuery) -> Page<LedgerEntry, u64> {
+        // Entry ids are positions in the log, so the cursor can be used as an index
+        let start = query.after.map_or(0, |after| {
+            after.min(self.transactions.len() as u64) as usize
+        });
+
+        let entries = self.transactions[start..]
+            .iter()
+            .filter(|entry| {
+                query
+                    .account
+                    .as_ref()
+                    .is_none_or(|account| entry.tx.account() == account)
+                    && query.kind.is_none_or(|kind| entry.tx.kind() == kind)
+                    && query.from.is_none_or(|from| entry.timestamp >= from)
+                    && query.to.is_none_or(|to| entry.timestamp < to)
+            })
+            .cloned();
+        paginate(entries, query.limit, |entry| entry.id)
     }
 
     /// Get the balance of account
-    pub fn balance_of(&self, signer: &str) -> Result<&u64, ApplicationError> {
+    pub fn balance_of(&self, signer: &str) -> Result<&Notional, ApplicationError> {
         self.accounts.balance_of(signer)
     }
 
     /// List all accounts
-    pub fn accounts(&self) -> Vec<(&String, &u64)> {
+    pub fn accounts(&self) -> Vec<(&String, &Notional)> {
         // self.accounts.accounts()
         self.accounts.accounts().iter().collect()
     }
 
+    /// Lists the filtered accounts one page at a time, ordered by signer
+    pub fn query_accounts(&self, query: &AccountsQuery) -> Page<AccountBalance, String> {
+        let mut accounts: Vec<_> = self
+            .accounts
+            .accounts()
+            .iter()
+            .filter(|(signer, balance)| {
+                query.after.as_ref().is_none_or(|after| *signer > after)
+                    && query
+                        .prefix
+                        .as_ref()
+                        .is_none_or(|prefix| signer.starts_with(prefix.as_str()))
+                    && query.min_balance.is_none_or(|min| **balance >= min)
+            })
+            .collect();
+        accounts.sort_unstable_by_key(|(signer, _)| *signer);
+
+        let accounts = accounts
+            .into_iter()
+            .map(|(signer, balance)| AccountBalance {
+                signer: signer.clone(),
+                balance: *balance,
+            });
+        paginate(accounts, query.limit, |account| account.signer.clone())
+    }
+
     /// Deposit funds
-    pub fn deposit(&mut self, signer: &str, amount: u64) -> Result<Tx, ApplicationError> {
-        self.accounts.deposit(signer, amount).map(|tx| {
-            self.transactions.push(tx.clone());
-            tx
+    pub fn deposit(&mut self, signer: &str, amount: Notional) -> Result<Tx, ApplicationError> {
+        let amount = self.currency(amount)?;
+        self.accounts.deposit(signer, amount).inspect(|tx| {
+            self.record(tx.clone());
         })
     }
 
-    /// Withdraw funds
-    pub fn withdraw(&mut self, signer: &str, amount: u64) -> Result<Tx, ApplicationError> {
-        self.accounts.withdraw(signer, amount).map(|tx| {
-            self.transactions.push(tx.clone());
-            tx
+    /// Withdraw funds, as long as the resting bids stay covered
+    pub fn withdraw(&mut self, signer: &str, amount: Notional) -> Result<Tx, ApplicationError> {
+        let amount = self.currency(amount)?;
+        self.spend(signer, amount)?;
+        self.accounts.withdraw(signer, amount).inspect(|tx| {
+            self.record(tx.clone());
         })
     }
 
-    /// Transfer funds between sender and recipient
+    /// Transfer funds between sender and recipient, as long as the sender's resting bids stay covered
     pub fn send(
         &mut self,
         sender: &str,
         recipient: &str,
-        amount: u64,
+        amount: Notional,
     ) -> Result<(Tx, Tx), ApplicationError> {
-        self.accounts.send(sender, recipient, amount).map(|tx| {
-            self.transactions.push(tx.0.clone());
-            self.transactions.push(tx.1.clone());
-            tx
+        let amount = self.currency(amount)?;
+        self.spend(sender, amount)?;
+        self.accounts.send(sender, recipient, amount).inspect(|tx| {
+            self.record(tx.0.clone());
+            self.record(tx.1.clone());
         })
     }
 
+    /// The balance of `signer` its resting bids and their fees don't need
+    pub fn available(&self, signer: &str) -> Result<Notional, ApplicationError> {
+        let balance = *self.balance_of(signer)?;
+        Ok(balance
+            .checked_sub(self.committed(signer))
+            .unwrap_or(Notional::ZERO))
+    }
+
+    /// The notional of the resting bids of `signer` at their limit prices and the maker fee of the highest tier on top
+    fn committed(&self, signer: &str) -> Notional {
+        // The tier of `signer` may change until the bids are filled, so the fee is reserved at the highest maker rate
+        let bps = self.fees.max_maker_bps();
+        self.matching_engine
+            .orders_of(signer)
+            .filter(|order| order.side == Side::Buy)
+            .fold(Notional::ZERO, |total, order| {
+                // A notional that doesn't fit can't be covered by any balance
+                let cost = order
+                    .price
+                    .notional(order.remaining, self.currency_scale)
+                    .and_then(|notional| {
+                        notional.checked_add(fees::fee(notional, bps, self.currency_scale))
+                    })
+                    .unwrap_or(Notional(Decimal::new(u64::MAX, self.currency_scale)));
+                Notional(total.0.saturating_add(cost.0))
+            })
+    }
+
+    /// Fails unless `signer` has `amount` available
+    fn spend(&self, signer: &str, amount: Notional) -> Result<(), ApplicationError> {
+        if self.available(signer)? < amount {
+            return Err(ApplicationError::AccountUnderFunded(
+                signer.to_string(),
+                amount,
+            ));
+        }
+        Ok(())
+    }
+
+    /// `amount` with the decimal places of balances. Nothing is rounded: an amount with more decimal places is rejected.
+    fn currency(&self, amount: Notional) -> Result<Notional, ApplicationError> {
+        amount
+            .0
+            .rescale(self.currency_scale)
+            .map(Notional)
+            .ok_or_else(|| {
+                ApplicationError::InvalidAmount(format!(
+                    "{} can't be given with {} decimal places",
+                    amount, self.currency_scale
+                ))
+            })
+    }
+
+    /// Appends a transaction to the log
+    fn record(&mut self, tx: Tx) {
+        self.transactions.push(LedgerEntry {
+            id: self.transactions.len() as u64 + 1,
+            timestamp: now(),
+            tx,
+        });
+    }
+
+    /// Appends an action to the audit log
+    fn audit(&mut self, event: AuditEvent) {
+        self.audit.push(AuditEntry {
+            id: self.audit.len() as u64 + 1,
+            timestamp: now(),
+            event,
+        });
+    }
+
+    /// Moves a fee from `signer` to the fee account
+    ///
+    /// # Panics
+    /// `signer` can't pay the fee, which was reserved with its order
+    fn charge(&mut self, signer: &str, amount: Notional) {
+        // Fees rounded down to nothing leave no trace in the log
+        if amount.is_zero() {
+            return;
+        }
+        let tx = self
+            .accounts
+            .charge(signer, &self.fee_account, amount)
+            .expect("fees are reserved with the orders they are charged for");
+        self.record(tx);
+    }
+
+    /// What the risk checks know about `order`, which is worth `notional`, at `now`
+    fn risk_context(&self, order: &Order, notional: Notional, now: u64) -> RiskContext {
+        let mid = match (
+            self.matching_engine.best(&Side::Buy),
+            self.matching_engine.best(&Side::Sell),
+        ) {
+            (Some(bid), Some(ask)) => {
+                let scale = self.instrument.price_scale;
+                bid.0
+                    .checked_add(ask.0)
+                    .and_then(|sum| sum.checked_mul(Decimal::new(5, 1), scale))
+                    .map(Price)
+            }
+            _ => None,
+        };
+        RiskContext {
+            limits: self.risk_limits(&order.signer),
+            notional,
+            open_orders: self.matching_engine.open_orders(&order.signer),
+            daily_volume: self.volumes.volume_since(&order.signer, now - now % DAY),
+            last_price: self.matching_engine.last_price(),
+            mid,
+        }
+    }
+
     /// Process a given order and apply the outcome to the accounts involved. Note that there are very few safeguards in place.
     ///
+    /// While trading is halted, the order may be queued until it opens, and during an auction it rests without matching.
+    ///
     /// # Errors
-    /// - Account has insufficient funds
+    /// - Trading is closed, or halted and orders are rejected meanwhile
+    /// - The account was disabled
+    /// - Account has insufficient funds for the notional and the fees
+    /// - The price or amount has more decimal places than the instrument
+    /// - The order is worth more than a balance can hold
+    /// - The order breaks one of the instrument's trading rules, see [`Instrument::check`]
+    /// - The order fails one of the pre-trade risk checks, see [`RiskEngine::check`]
     pub fn order(&mut self, order: Order) -> Result<Receipt, ApplicationError> {
-        let total_amount = order.amount * order.price;
+        self.tick();
+        match self.trading_state(now()) {
+            TradingState::Open => self.place(order, None),
+            TradingState::Closed => Err(ApplicationError::TradingClosed(
+                self.instrument.symbol.clone(),
+            )),
+            TradingState::Halted if self.halt_policy == HaltPolicy::Reject => Err(
+                ApplicationError::TradingHalted(self.instrument.symbol.clone()),
+            ),
+            TradingState::AuctionOnly => self.call(order, None),
+            TradingState::Halted => {
+                // Orders are checked now and again when trading opens, as the book and the balances may have changed by then
+                let order = self.check(order, now())?;
+                let ordinal = self.matching_engine.next_ordinal();
+                tracing::info!(ordinal, signer = %order.signer, "order queued");
+                self.queued.push_back(PartialOrder {
+                    price: order.price,
+                    amount: order.amount,
+                    remaining: order.amount,
+                    side: order.side,
+                    signer: order.signer,
+                    ordinal,
+                });
+                Ok(Receipt {
+                    ordinal,
+                    matches: vec![],
+                    fees: vec![],
+                    queued: true,
+                })
+            }
+        }
+    }
+
+    /// The order with the instrument's decimal places and what it is worth, if it may be placed under the instrument's rules
+    fn admit(&self, order: Order) -> Result<(Order, Notional), ApplicationError> {
         // Make sure the account has a deposit
-        match self.balance_of(&order.signer) {
-            Ok(balance) if order.side == Side::Buy && balance < &total_amount => {
+        self.balance_of(&order.signer)?;
+        if self.disabled.contains(&order.signer) {
+            return Err(ApplicationError::AccountDisabled(order.signer));
+        }
+        let order = self.instrument.normalize(order)?;
+        // Every order has to be worth an amount of currency that can exist, so its matches can be settled. Fills are rounded down like the order, so they never cost more than this in total.
+        let total_amount =
+            order
+                .notional(self.currency_scale)
+                .ok_or(ApplicationError::NotionalOverflow(
+                    order.price,
+                    order.amount,
+                ))?;
+        self.instrument.check(&order, total_amount)?;
+        Ok((order, total_amount))
+    }
+
+    /// Runs the pre-trade checks on an order about to enter the book: the order with the instrument's decimal places if it passes them
+    fn check(&self, order: Order, now: u64) -> Result<Order, ApplicationError> {
+        let (order, total_amount) = self.admit(order)?;
+        let available = self.available(&order.signer)?;
+        let context = self.risk_context(&order, total_amount, now);
+        self.risk.check(&order, &context)?;
+        // The taker pays the rate of its tier before the order for all of its fills, a buyer on top of the notional.
+        // What rests of a bid reserves the highest maker rate instead, so a buyer has to afford the higher of both.
+        let taker_tier = self.fees.tier(self.volumes.volume(&order.signer, now));
+        if order.side == Side::Buy {
+            let bps = taker_tier.taker_bps.max(self.fees.max_maker_bps());
+            let cost = total_amount
+                .checked_add(fees::fee(total_amount, bps, self.currency_scale))
+                .ok_or(ApplicationError::NotionalOverflow(
+                    order.price,
+                    order.amount,
+                ))?;
+            if available < cost {
                 return Err(ApplicationError::AccountUnderFunded(
                     order.signer.clone(),
-                    total_amount,
-                ))
+                    cost,
+                ));
             }
-            Ok(_) => {}
-            Err(e) => return Err(e),
         }
+        Ok(order)
+    }
+
+    /// Checks and matches an order while trading is open, and settles its matches
+    fn place(&mut self, order: Order, ordinal: Option<u64>) -> Result<Receipt, ApplicationError> {
+        let now = now();
+        self.cancel_underfunded_bids();
+        let order = self.check(order, now)?;
+        let taker_tier = self.fees.tier(self.volumes.volume(&order.signer, now));
         let signer = order.signer.clone();
         let side = order.side.clone();
         // Do the actual matching
-        let receipt = self.matching_engine.process(order)?;
+        // A queued order keeps the ordinal it was given when it arrived
+        let ordinal = ordinal.unwrap_or_else(|| self.matching_engine.next_ordinal());
+        let (price, amount) = (order.price, order.amount);
+        // Matching stops where the next trade would trip the circuit breaker
+        let bound = self.circuit_breaker.bound(&side, now);
+        let mut receipt = self.matching_engine.process_as(ordinal, order, bound)?;
+        tracing::info!(
+            ordinal = receipt.ordinal,
+            signer = %signer,
+            side = ?side,
+            matches = receipt.matches.len(),
+            "order accepted"
+        );
 
-        receipt
+        for m in &receipt.matches {
+            let (buyer, seller) = match side {
+                Side::Buy => (&signer, &m.signer),
+                Side::Sell => (&m.signer, &signer),
+            };
+            let maker_tier = self.fees.tier(self.volumes.volume(&m.signer, now));
+            let [maker, taker] = self.settle(
+                buyer,
+                seller,
+                m.price,
+                m.amount,
+                [
+                    (&m.signer, maker_tier.maker_bps),
+                    (&signer, taker_tier.taker_bps),
+                ],
+                now,
+            );
+            tracing::info!(
+                ordinal = receipt.ordinal,
+                buyer = %buyer,
+                seller = %seller,
+                amount = %m.amount,
+                price = %m.price,
+                maker_fee = %maker,
+                taker_fee = %taker,
+                "trade settled"
+            );
+            receipt.fees.push(Fee {
+                maker,
+                taker,
+                maker_bps: maker_tier.maker_bps,
+                taker_bps: taker_tier.taker_bps,
+            });
+            if let Some(from) = self.circuit_breaker.record(now, m.price) {
+                self.halt(from, m.price, now);
+            }
+        }
+        // The rest of an order stopped at the bound waits in the book, and trading halts at the price it would have traded at
+        let filled = receipt
             .matches
             .iter()
-            .map(|m| match side {
-                Side::Buy => self.send(&signer, &m.signer, m.amount * m.price),
-                Side::Sell => self.send(&m.signer, &signer, m.amount * m.price),
-            })
-            .collect::<Result<Vec<_>, ApplicationError>>()?;
+            .fold(Quantity::ZERO, |filled, m| filled + m.amount);
+        let opposite = match side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let beyond = self
+            .matching_engine
+            .best(&opposite)
+            .filter(|&best| match side {
+                Side::Buy => best <= price,
+                Side::Sell => best >= price,
+            });
+        if let Some(to) = beyond.filter(|_| filled < amount) {
+            if let Some(from) = self.circuit_breaker.trip(now, to) {
+                self.halt(from, to, now);
+            }
+        }
+        Ok(receipt)
+    }
+
+    /// Checks an order during the call phase of an auction and adds it to the book without matching it
+    fn call(&mut self, order: Order, ordinal: Option<u64>) -> Result<Receipt, ApplicationError> {
+        let order = self.check(order, now())?;
+        let signer = order.signer.clone();
+        // A queued order keeps the ordinal it was given when it arrived
+        let receipt = match ordinal {
+            Some(ordinal) => self.matching_engine.collect_as(ordinal, order),
+            None => self.matching_engine.collect(order),
+        };
+        self.auction = true;
+        tracing::info!(ordinal = receipt.ordinal, signer = %signer, "order collected");
         Ok(receipt)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    // reduce the warnings for naming tests
-    #![allow(non_snake_case)]
+    /// Ends the call phase of an auction: executes the crossed book at the clearing price and settles the trades
+    fn uncross(&mut self, now: u64) {
+        self.auction = false;
+        self.cancel_underfunded_bids();
+        let crosses = self.matching_engine.uncross();
+        let Some(price) = crosses.first().map(|cross| cross.price) else {
+            return;
+        };
+        tracing::warn!(
+            symbol = %self.instrument.symbol,
+            %price,
+            trades = crosses.len(),
+            "auction uncrossed"
+        );
+        for cross in &crosses {
+            // Neither side took liquidity, so both pay the maker rate of their tier
+            let rates = [&cross.buyer, &cross.seller].map(|signer| {
+                (
+                    signer.as_str(),
+                    self.fees.tier(self.volumes.volume(signer, now)).maker_bps,
+                )
+            });
+            let [buyer_fee, seller_fee] = self.settle(
+                &cross.buyer,
+                &cross.seller,
+                cross.price,
+                cross.amount,
+                rates,
+                now,
+            );
+            tracing::info!(
+                bid = cross.bid,
+                ask = cross.ask,
+                buyer = %cross.buyer,
+                seller = %cross.seller,
+                amount = %cross.amount,
+                price = %cross.price,
+                buyer_fee = %buyer_fee,
+                seller_fee = %seller_fee,
+                "trade settled"
+            );
+        }
+        if let Some(from) = self.circuit_breaker.record(now, price) {
+            self.halt(from, price, now);
+        }
+    }
+
+    /// Takes the bids of every signer whose balance doesn't cover them out of the book, before they can match
+    fn cancel_underfunded_bids(&mut self) {
+        // Withdrawals can't touch committed funds, but bids restored from an older snapshot were never committed,
+        // and a fee schedule with a higher maker rate commits more than when they were placed
+        let signers: BTreeSet<String> = self
+            .matching_engine
+            .orders()
+            .filter(|order| order.side == Side::Buy)
+            .map(|order| order.signer)
+            .collect();
+        for signer in signers {
+            let balance = self
+                .balance_of(&signer)
+                .map_or(Notional::ZERO, |balance| *balance);
+            if self.committed(&signer) <= balance {
+                continue;
+            }
+            let cancelled = self.matching_engine.cancel_all(&signer, Some(&Side::Buy));
+            tracing::warn!(signer = %signer, %balance, cancelled = cancelled.len(), "underfunded bids cancelled");
+            self.audit(AuditEvent::OrdersCancelled {
+                account: signer,
+                side: Some(Side::Buy),
+                cancelled: cancelled.iter().map(|order| order.ordinal).collect(),
+            });
+        }
+    }
+
+    /// Pays for a trade, charges the fees at `rates` in basis points and returns the fees that were charged
+    ///
+    /// # Panics
+    /// The buyer can't pay for the trade, which its balance has to cover before matching
+    fn settle(
+        &mut self,
+        buyer: &str,
+        seller: &str,
+        price: Price,
+        amount: Quantity,
+        rates: [(&str, u32); 2],
+        now: u64,
+    ) -> [Notional; 2] {
+        // The matching is done, so nothing here may fail. Both orders were checked to be worth less,
+        // and underfunded bids are cancelled before matching.
+        let notional = price
+            .notional(amount, self.currency_scale)
+            .expect("a match is worth less than its orders");
+        let (withdraw, deposit) = self
+            .accounts
+            .send(buyer, seller, notional)
+            .expect("a buyer's balance covers its resting bids");
+        self.record(withdraw);
+        self.record(deposit);
+        let fees = rates.map(|(signer, bps)| {
+            let fee = fees::fee(notional, bps, self.currency_scale);
+            self.charge(signer, fee);
+            fee
+        });
+        self.volumes.record(buyer, now, notional);
+        self.volumes.record(seller, now, notional);
+        fees
+    }
+
+    /// Halts the instrument after its price moved, or would have moved, from `from` to `to`
+    fn halt(&mut self, from: Price, to: Price, now: u64) {
+        // The order that tripped the breaker may have left the book crossed, so trading resumes with an auction
+        self.auction = true;
+        let reason = format!("the price moved from {} to {}", from, to);
+        tracing::warn!(symbol = %self.instrument.symbol, %from, %to, "circuit breaker tripped");
+        self.instrument_status = TradingStatus {
+            state: TradingState::Halted,
+            reason: Some(reason.clone()),
+            until: self
+                .circuit_breaker
+                .halt_duration()
+                .map(|duration| now.saturating_add(duration)),
+        };
+        self.audit(AuditEvent::TradingStateChanged {
+            instrument: Some(self.instrument.symbol.clone()),
+            state: TradingState::Halted,
+            reason: Some(reason),
+        });
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Takes up to `limit` items and, if any are left, returns the cursor of the last one taken
+fn paginate<T, C>(
+    items: impl Iterator<Item = T>,
+    limit: Option<usize>,
+    cursor: impl Fn(&T) -> C,
+) -> Page<T, C> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let mut items = items.peekable();
+    let page: Vec<T> = items.by_ref().take(limit).collect();
+    let next = items.peek().and(page.last()).map(cursor);
+
+    Page { items: page, next }
+}
+
+#[cfg(test)]
+mod tests {
+    // reduce the warnings for naming tests
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use octopus_common::{
+        core::{
+            decimal::Decimal,
+            types::{Allocation, Price, Quantity},
+        },
+        tx::TxKind,
+    };
+
+    #[test]
+    fn test_TradingPlatform_order_requires_deposit_to_order() {
+        let mut trading_platform = TradingPlatform::new();
+
+        assert_eq!(
+            trading_platform.order(Order {
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                side: Side::Sell,
+                signer: "ALICE".to_string(),
+            }),
+            Err(ApplicationError::AccountNotFound("ALICE".to_string()))
+        );
+        assert!(trading_platform.matching_engine.levels(&Side::Sell) == 0);
+        assert!(trading_platform.matching_engine.levels(&Side::Buy) == 0);
+    }
+
+    /// Found by the `platform` fuzz target: the notional of every order was computed first and overflowed
+    #[test]
+    fn test_TradingPlatform_order_does_not_overflow_the_notional() {
+        let mut trading_platform = TradingPlatform::new();
+        let order = |side, price, amount| Order {
+            price: Price(price),
+            amount: Quantity(amount),
+            side,
+            signer: "ALICE".to_string(),
+        };
+        // The largest price and balance there can be with two decimal places
+        let max = Decimal::new(u64::MAX, 2);
+
+        assert_eq!(
+            trading_platform.order(order(
+                Side::Sell,
+                Decimal::from(18422256854559489968),
+                Decimal::from(65535)
+            )),
+            Err(ApplicationError::AccountNotFound("ALICE".to_string()))
+        );
+
+        assert!(trading_platform.deposit("ALICE", Notional(max)).is_ok());
+        assert_eq!(
+            trading_platform.order(order(Side::Buy, max, Decimal::from(2))),
+            Err(ApplicationError::NotionalOverflow(
+                Price(max),
+                Quantity::from(2)
+            ))
+        );
+        // Sells aren't paid for upfront, but their matches have to be settled all the same
+        assert_eq!(
+            trading_platform.order(order(Side::Sell, max, Decimal::from(u64::MAX))),
+            Err(ApplicationError::NotionalOverflow(
+                Price(max),
+                Quantity::from(u64::MAX)
+            ))
+        );
+        assert!(trading_platform
+            .order(order(Side::Sell, max, Decimal::from(1)))
+            .is_ok());
+        assert_eq!(trading_platform.balance_of("ALICE"), Ok(&Notional(max)));
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_rejects_more_decimal_places_than_the_instrument() {
+        let mut trading_platform = TradingPlatform::new();
+        trading_platform.deposit("ALICE", 100.into()).unwrap();
+        let order = |price: &str, amount: &str| Order {
+            price: price.parse().unwrap(),
+            amount: amount.parse().unwrap(),
+            side: Side::Buy,
+            signer: "ALICE".to_string(),
+        };
+
+        assert_eq!(
+            trading_platform.order(order("10.005", "1")),
+            Err(ApplicationError::InvalidOrder(
+                "price 10.005 can't be given with 2 decimal places".to_string()
+            ))
+        );
+        assert_eq!(
+            trading_platform.order(order("10", "1.5")),
+            Err(ApplicationError::InvalidOrder(
+                "amount 1.5 can't be given with 0 decimal places".to_string()
+            ))
+        );
+        // Too large once it has two decimal places
+        assert!(matches!(
+            trading_platform.order(order("18446744073709551615", "1")),
+            Err(ApplicationError::InvalidOrder(_))
+        ));
+        assert!(trading_platform.orderbook().is_empty());
+
+        // Trailing zeros are no extra precision, and the book keeps the instrument's decimal places
+        let receipt = trading_platform.order(order("10.500", "2.0")).unwrap();
+        let resting = trading_platform.orderbook();
+        assert_eq!(resting[0].ordinal, receipt.ordinal);
+        assert_eq!(resting[0].price.to_string(), "10.50");
+        assert_eq!(resting[0].amount.to_string(), "2");
+    }
+
+    #[test]
+    fn test_TradingPlatform_deposit_rejects_more_decimal_places_than_balances() {
+        let mut trading_platform = TradingPlatform::new();
+
+        assert_eq!(
+            trading_platform.deposit("ALICE", "1.001".parse().unwrap()),
+            Err(ApplicationError::InvalidAmount(
+                "1.001 can't be given with 2 decimal places".to_string()
+            ))
+        );
+        assert!(trading_platform
+            .deposit("ALICE", "1.5".parse().unwrap())
+            .is_ok());
+        assert_eq!(
+            trading_platform.balance_of("ALICE").map(|b| b.to_string()),
+            Ok("1.50".to_string())
+        );
+        assert!(trading_platform
+            .send("ALICE", "ALICE", "0.015".parse().unwrap())
+            .is_err());
+        assert!(trading_platform
+            .withdraw("ALICE", "0.001".parse().unwrap())
+            .is_err());
+        assert!(trading_platform.txlog().len() == 1);
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_charges_maker_and_taker_fees() {
+        let mut trading_platform =
+            TradingPlatform::new().with_fees("0:10:20".parse().unwrap(), "FEES");
+        trading_platform.deposit("ALICE", 1_000.into()).unwrap();
+        trading_platform.deposit("BOB", 1_000.into()).unwrap();
+        let order = |signer: &str, side, amount: u64| Order {
+            price: Price::from(50),
+            amount: Quantity::from(amount),
+            side,
+            signer: signer.to_string(),
+        };
+
+        let receipt = trading_platform
+            .order(order("ALICE", Side::Sell, 10))
+            .unwrap();
+        assert!(receipt.fees.is_empty());
+        let receipt = trading_platform.order(order("BOB", Side::Buy, 10)).unwrap();
+        assert_eq!(
+            receipt.fees,
+            vec![Fee {
+                maker: "0.50".parse().unwrap(),
+                taker: 1.into(),
+                maker_bps: 10,
+                taker_bps: 20,
+            }]
+        );
+
+        let balance = |signer| trading_platform.balance_of(signer).map(|b| b.to_string());
+        assert_eq!(balance("ALICE"), Ok("1499.50".to_string()));
+        assert_eq!(balance("BOB"), Ok("499.00".to_string()));
+        assert_eq!(balance("FEES"), Ok("1.50".to_string()));
+        assert_eq!(
+            trading_platform.txlog()[4..],
+            [
+                &Tx::Fee {
+                    account: "ALICE".to_string(),
+                    fee_account: "FEES".to_string(),
+                    amount: "0.50".parse().unwrap(),
+                },
+                &Tx::Fee {
+                    account: "BOB".to_string(),
+                    fee_account: "FEES".to_string(),
+                    amount: 1.into(),
+                },
+            ]
+        );
+
+        // A buyer has to afford the taker fee on top of the notional
+        trading_platform.deposit("CHARLIE", 100.into()).unwrap();
+        assert_eq!(
+            trading_platform.order(order("CHARLIE", Side::Buy, 2)),
+            Err(ApplicationError::AccountUnderFunded(
+                "CHARLIE".to_string(),
+                "100.20".parse().unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_charges_fees_by_volume_tier() {
+        let mut trading_platform =
+            TradingPlatform::new().with_fees("0:10:20,1000:0:5".parse().unwrap(), "FEES");
+        trading_platform.deposit("ALICE", 10_000.into()).unwrap();
+        trading_platform.deposit("BOB", 10_000.into()).unwrap();
+        let order = |signer: &str, side| Order {
+            price: Price::from(100),
+            amount: Quantity::from(6),
+            side,
+            signer: signer.to_string(),
+        };
+        assert_eq!(trading_platform.fee_tier("BOB").taker_bps, 20);
+
+        for _ in 0..2 {
+            trading_platform.order(order("ALICE", Side::Sell)).unwrap();
+            let receipt = trading_platform.order(order("BOB", Side::Buy)).unwrap();
+            assert_eq!(receipt.fees[0].taker_bps, 20);
+        }
+
+        // Both sides traded 1200 by now
+        assert_eq!(trading_platform.fee_tier("ALICE").maker_bps, 0);
+        assert_eq!(trading_platform.fee_tier("BOB").taker_bps, 5);
+        trading_platform.order(order("ALICE", Side::Sell)).unwrap();
+        let receipt = trading_platform.order(order("BOB", Side::Buy)).unwrap();
+        assert_eq!(
+            receipt.fees,
+            vec![Fee {
+                maker: Notional::ZERO,
+                taker: "0.30".parse().unwrap(),
+                maker_bps: 0,
+                taker_bps: 5,
+            }]
+        );
+        // Free trades leave no fee in the log
+        assert_eq!(
+            trading_platform
+                .txlog()
+                .iter()
+                .filter(|tx| tx.kind() == TxKind::Fee)
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_reserves_the_maker_fee_of_resting_bids() {
+        let mut trading_platform =
+            TradingPlatform::new().with_fees("0:10:20".parse().unwrap(), "FEES");
+        trading_platform.deposit("ALICE", 1_002.into()).unwrap();
+        trading_platform.deposit("BOB", 10.into()).unwrap();
+        let order = |signer: &str, side| Order {
+            price: Price::from(100),
+            amount: Quantity::from(10),
+            side,
+            signer: signer.to_string(),
+        };
+        trading_platform.order(order("ALICE", Side::Buy)).unwrap();
+
+        // The resting bid holds on to its notional and the maker fee of 1, which can't be withdrawn
+        assert_eq!(trading_platform.available("ALICE"), Ok(1.into()));
+        assert_eq!(
+            trading_platform.withdraw("ALICE", 2.into()),
+            Err(ApplicationError::AccountUnderFunded(
+                "ALICE".to_string(),
+                2.into()
+            ))
+        );
+        trading_platform.withdraw("ALICE", 1.into()).unwrap();
+
+        // The maker is charged in full when the bid is filled
+        let receipt = trading_platform.order(order("BOB", Side::Sell)).unwrap();
+        assert_eq!(
+            receipt.fees,
+            vec![Fee {
+                maker: 1.into(),
+                taker: 2.into(),
+                maker_bps: 10,
+                taker_bps: 20,
+            }]
+        );
+        assert_eq!(trading_platform.balance_of("ALICE"), Ok(&Notional::ZERO));
+        assert_eq!(
+            trading_platform.balance_of("BOB"),
+            Ok(&Notional::from(1_008))
+        );
+        assert_eq!(trading_platform.balance_of("FEES"), Ok(&Notional::from(3)));
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_reserves_the_highest_maker_rate() {
+        // Trading is free until 50 of volume
+        let mut trading_platform =
+            TradingPlatform::new().with_fees("0:0:0,50:20:20".parse().unwrap(), "FEES");
+        trading_platform.deposit("ALICE", 100.into()).unwrap();
+        let order = |side| Order {
+            price: Price::from(10),
+            amount: Quantity::from(10),
+            side,
+            signer: "ALICE".to_string(),
+        };
+
+        // A free taker still has to afford the fee its bid may owe as a maker in a higher tier
+        assert_eq!(
+            trading_platform.order(order(Side::Buy)),
+            Err(ApplicationError::AccountUnderFunded(
+                "ALICE".to_string(),
+                "100.20".parse().unwrap()
+            ))
+        );
+        trading_platform
+            .deposit("ALICE", "0.20".parse().unwrap())
+            .unwrap();
+        trading_platform.order(order(Side::Buy)).unwrap();
+        assert_eq!(trading_platform.available("ALICE"), Ok(Notional::ZERO));
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_cancels_underfunded_bids_before_matching() {
+        let mut trading_platform = TradingPlatform::new();
+        trading_platform.deposit("ALICE", 100.into()).unwrap();
+        trading_platform.deposit("BOB", 100.into()).unwrap();
+        let order = |signer: &str, side| Order {
+            price: Price::from(10),
+            amount: Quantity::from(10),
+            side,
+            signer: signer.to_string(),
+        };
+        trading_platform.order(order("ALICE", Side::Buy)).unwrap();
+        // Like a bid restored from an older snapshot
+        trading_platform
+            .accounts
+            .withdraw("ALICE", 100.into())
+            .unwrap();
+
+        let receipt = trading_platform.order(order("BOB", Side::Sell)).unwrap();
+        assert!(receipt.matches.is_empty());
+        assert_eq!(trading_platform.balance_of("BOB"), Ok(&Notional::from(100)));
+        let book = trading_platform.orderbook();
+        assert_eq!(book.len(), 1);
+        assert_eq!(book[0].side, Side::Sell);
+        assert!(trading_platform.audit.iter().any(|entry| matches!(
+            &entry.event,
+            AuditEvent::OrdersCancelled { account, side: Some(Side::Buy), cancelled }
+                if account == "ALICE" && cancelled.len() == 1
+        )));
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_runs_the_risk_checks_with_account_limits() {
+        let defaults = RiskLimits {
+            max_open_orders: Some(2),
+            price_collar_bps: Some(1_000),
+            ..RiskLimits::default()
+        };
+        let mut trading_platform = TradingPlatform::new().with_risk(RiskEngine::new(defaults));
+        trading_platform.deposit("ALICE", 10_000.into()).unwrap();
+        trading_platform.deposit("BOB", 10_000.into()).unwrap();
+        let order = |signer: &str, side, price: u64, amount: u64| Order {
+            price: Price::from(price),
+            amount: Quantity::from(amount),
+            side,
+            signer: signer.to_string(),
+        };
+
+        // Nothing to compare the price with yet
+        trading_platform
+            .order(order("ALICE", Side::Sell, 100, 1))
+            .unwrap();
+        trading_platform
+            .order(order("ALICE", Side::Sell, 200, 1))
+            .unwrap();
+        assert_eq!(
+            trading_platform.order(order("ALICE", Side::Sell, 300, 1)),
+            Err(ApplicationError::OpenOrdersLimit("ALICE".to_string(), 2))
+        );
+
+        // Without bids there is no mid either, after that the collar is around the mid of 55
+        trading_platform
+            .order(order("BOB", Side::Buy, 10, 1))
+            .unwrap();
+        assert!(matches!(
+            trading_platform.order(order("BOB", Side::Buy, 100, 1)),
+            Err(ApplicationError::PriceOutsideCollar(..))
+        ));
+        assert_eq!(
+            trading_platform
+                .set_risk_limits(
+                    "BOB",
+                    RiskLimits {
+                        price_collar_bps: Some(10_000),
+                        ..RiskLimits::default()
+                    }
+                )
+                .map(|limits| limits.price_collar_bps),
+            Ok(Some(10_000))
+        );
+        // Trades at 100, which the collar is around from now on
+        trading_platform
+            .order(order("BOB", Side::Buy, 100, 1))
+            .unwrap();
+        assert_eq!(
+            trading_platform.order(order("BOB", Side::Buy, 201, 1)),
+            Err(ApplicationError::PriceOutsideCollar(
+                Price::from(201),
+                Price::from(0),
+                Price::from(200)
+            ))
+        );
+
+        // Own limits fall back to the defaults where they aren't set
+        assert_eq!(trading_platform.risk_limits("BOB").max_open_orders, Some(2));
+        assert_eq!(
+            trading_platform.set_risk_limits("CHARLIE", RiskLimits::default()),
+            Err(ApplicationError::AccountNotFound("CHARLIE".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_limits_the_daily_volume() {
+        let mut trading_platform = TradingPlatform::new();
+        trading_platform.deposit("ALICE", 10_000.into()).unwrap();
+        trading_platform.deposit("BOB", 10_000.into()).unwrap();
+        trading_platform
+            .set_risk_limits(
+                "BOB",
+                RiskLimits {
+                    max_daily_volume: Some(1_000.into()),
+                    max_notional: Some(600.into()),
+                    ..RiskLimits::default()
+                },
+            )
+            .unwrap();
+        let order = |signer: &str, side, amount: u64| Order {
+            price: Price::from(100),
+            amount: Quantity::from(amount),
+            side,
+            signer: signer.to_string(),
+        };
+
+        assert_eq!(
+            trading_platform.order(order("BOB", Side::Buy, 7)),
+            Err(ApplicationError::OrderNotionalLimit(700.into(), 600.into()))
+        );
+        trading_platform
+            .order(order("ALICE", Side::Sell, 6))
+            .unwrap();
+        trading_platform.order(order("BOB", Side::Buy, 6)).unwrap();
+        // 600 traded today, another 500 would be too much
+        assert_eq!(
+            trading_platform.order(order("BOB", Side::Buy, 5)),
+            Err(ApplicationError::DailyVolumeLimit(
+                "BOB".to_string(),
+                1_000.into()
+            ))
+        );
+        assert!(trading_platform.order(order("BOB", Side::Buy, 4)).is_ok());
+        // ALICE has no limits of her own
+        assert!(trading_platform
+            .order(order("ALICE", Side::Buy, 50))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_allocates_by_the_instrument() {
+        let instrument = Instrument {
+            lot_size: "0.5".parse().unwrap(),
+            allocation: Allocation::ProRata {
+                min_allocation: Quantity::ZERO,
+            },
+            ..Instrument::new("OCTO", 2, 1)
+        };
+        let mut trading_platform = TradingPlatform::new().with_instrument(instrument, 2);
+        for signer in ["ALICE", "BOB", "CHARLIE"] {
+            trading_platform.deposit(signer, 100.into()).unwrap();
+        }
+        let order = |signer: &str, side, amount: &str| Order {
+            price: Price::from(10),
+            amount: amount.parse().unwrap(),
+            side,
+            signer: signer.to_string(),
+        };
+        trading_platform
+            .order(order("ALICE", Side::Sell, "1.5"))
+            .unwrap();
+        trading_platform
+            .order(order("BOB", Side::Sell, "3"))
+            .unwrap();
+
+        // 0.67 and 1.33 are rounded down to 0.5 and 1, and ALICE's order is older so it gets the lot left over
+        let receipt = trading_platform
+            .order(order("CHARLIE", Side::Buy, "2"))
+            .unwrap();
+        let fills: Vec<_> = receipt
+            .matches
+            .iter()
+            .map(|m| (m.signer.as_str(), m.amount))
+            .collect();
+        assert_eq!(
+            fills,
+            vec![
+                ("ALICE", "1".parse().unwrap()),
+                ("BOB", "1".parse().unwrap())
+            ]
+        );
+        assert_eq!(
+            trading_platform.balance_of("ALICE"),
+            Ok(&Notional::from(110))
+        );
+        assert_eq!(trading_platform.balance_of("BOB"), Ok(&Notional::from(110)));
+        assert_eq!(
+            trading_platform.balance_of("CHARLIE"),
+            Ok(&Notional::from(80))
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_enforces_the_trading_rules() {
+        let instrument = Instrument {
+            tick_size: "0.05".parse().unwrap(),
+            lot_size: "0.5".parse().unwrap(),
+            min_quantity: 1.into(),
+            max_quantity: Some(100.into()),
+            min_notional: 10.into(),
+            ..Instrument::new("OCTO", 2, 1)
+        };
+        assert_eq!(instrument.validate(), Ok(()));
+        let mut trading_platform = TradingPlatform::new().with_instrument(instrument, 2);
+        trading_platform.deposit("ALICE", 10_000.into()).unwrap();
+        let order = |price: &str, amount: &str| Order {
+            price: price.parse().unwrap(),
+            amount: amount.parse().unwrap(),
+            side: Side::Buy,
+            signer: "ALICE".to_string(),
+        };
+
+        assert_eq!(
+            trading_platform.order(order("10.01", "2")),
+            Err(ApplicationError::PriceOffTick(
+                "10.01".parse().unwrap(),
+                "0.05".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            trading_platform.order(order("10.05", "2.2")),
+            Err(ApplicationError::QuantityOffLot(
+                "2.2".parse().unwrap(),
+                "0.5".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            trading_platform.order(order("20", "0.5")),
+            Err(ApplicationError::QuantityBelowMinimum(
+                "0.5".parse().unwrap(),
+                1.into()
+            ))
+        );
+        assert_eq!(
+            trading_platform.order(order("1", "100.5")),
+            Err(ApplicationError::QuantityAboveMaximum(
+                "100.5".parse().unwrap(),
+                100.into()
+            ))
+        );
+        // 1.5 units at 6.65 are worth 9.975, rounded down to 9.97
+        assert_eq!(
+            trading_platform.order(order("6.65", "1.5")),
+            Err(ApplicationError::NotionalBelowMinimum(
+                "9.97".parse().unwrap(),
+                10.into()
+            ))
+        );
+        assert!(trading_platform.orderbook().is_empty());
+
+        assert!(trading_platform.order(order("6.70", "1.5")).is_ok());
+        assert!(trading_platform.order(order("1.00", "100")).is_ok());
+        assert_eq!(trading_platform.orderbook().len(), 2);
+    }
+
+    #[test]
+    fn test_Instrument_validate_rejects_rules_that_cant_be_met() {
+        let instrument = Instrument::new("OCTO", 2, 0);
+        assert_eq!(instrument.validate(), Ok(()));
 
-    use super::*;
+        for broken in [
+            Instrument {
+                tick_size: Price::from(0),
+                ..instrument.clone()
+            },
+            Instrument {
+                tick_size: "0.005".parse().unwrap(),
+                ..instrument.clone()
+            },
+            Instrument {
+                lot_size: "0.5".parse().unwrap(),
+                ..instrument.clone()
+            },
+            Instrument {
+                min_quantity: 10.into(),
+                max_quantity: Some(5.into()),
+                ..instrument.clone()
+            },
+        ] {
+            assert!(broken.validate().is_err(), "{:?}", broken);
+        }
+    }
 
     #[test]
-    fn test_TradingPlatform_order_requires_deposit_to_order() {
-        let mut trading_platform = TradingPlatform::new();
+    fn test_TradingPlatform_order_settles_notionals_rounded_down() {
+        let instrument = Instrument::new("OCTO", 2, 1);
+        let mut trading_platform = TradingPlatform::new().with_instrument(instrument, 2);
+        trading_platform.deposit("ALICE", 1.into()).unwrap();
+        trading_platform.deposit("BOB", 1.into()).unwrap();
+        let order = |signer: &str, side, amount: &str| Order {
+            price: "0.25".parse().unwrap(),
+            amount: amount.parse().unwrap(),
+            side,
+            signer: signer.to_string(),
+        };
+
+        trading_platform
+            .order(order("ALICE", Side::Sell, "1"))
+            .unwrap();
+        // 0.5 units at 0.25 are worth 0.125, of which the 0.005 are dropped. The second half is worth the same, so the whole unit costs 0.24 instead of 0.25.
+        for _ in 0..2 {
+            let receipt = trading_platform
+                .order(order("BOB", Side::Buy, "0.5"))
+                .unwrap();
+            assert_eq!(receipt.matches.len(), 1);
+        }
 
         assert_eq!(
-            trading_platform.order(Order {
-                price: 10,
-                amount: 1,
-                side: Side::Sell,
-                signer: "ALICE".to_string(),
-            }),
-            Err(ApplicationError::AccountNotFound("ALICE".to_string()))
+            trading_platform.balance_of("ALICE").map(|b| b.to_string()),
+            Ok("1.24".to_string())
+        );
+        assert_eq!(
+            trading_platform.balance_of("BOB").map(|b| b.to_string()),
+            Ok("0.76".to_string())
         );
-        assert!(trading_platform.matching_engine.asks.is_empty());
-        assert!(trading_platform.matching_engine.bids.is_empty());
     }
 
     #[test]
@@ -145,13 +1723,16 @@ mod tests {
         let mut trading_platform = TradingPlatform::new();
 
         // Set up accounts
-        assert!(trading_platform.accounts.deposit("ALICE", 100).is_ok());
-        assert!(trading_platform.accounts.deposit("BOB", 100).is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("ALICE", 100.into())
+            .is_ok());
+        assert!(trading_platform.accounts.deposit("BOB", 100.into()).is_ok());
 
         let alice_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -161,8 +1742,8 @@ mod tests {
 
         let bob_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -171,20 +1752,26 @@ mod tests {
         assert_eq!(
             bob_receipt.matches,
             vec![PartialOrder {
-                price: 10,
-                amount: 1,
-                remaining: 0,
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                remaining: Quantity::from(0),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
                 ordinal: 1
             }]
         );
-        assert!(trading_platform.matching_engine.asks.is_empty());
-        assert_eq!(trading_platform.matching_engine.bids.len(), 1);
+        assert!(trading_platform.matching_engine.levels(&Side::Sell) == 0);
+        assert_eq!(trading_platform.matching_engine.levels(&Side::Buy), 1);
 
         // Check the account balances
-        assert_eq!(trading_platform.accounts.balance_of("ALICE"), Ok(&110));
-        assert_eq!(trading_platform.accounts.balance_of("BOB"), Ok(&90));
+        assert_eq!(
+            trading_platform.accounts.balance_of("ALICE"),
+            Ok(&Notional::from(110))
+        );
+        assert_eq!(
+            trading_platform.accounts.balance_of("BOB"),
+            Ok(&Notional::from(90))
+        );
     }
 
     #[test]
@@ -192,13 +1779,16 @@ mod tests {
         let mut trading_platform = TradingPlatform::new();
 
         // Set up accounts
-        assert!(trading_platform.accounts.deposit("ALICE", 100).is_ok());
-        assert!(trading_platform.accounts.deposit("BOB", 100).is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("ALICE", 100.into())
+            .is_ok());
+        assert!(trading_platform.accounts.deposit("BOB", 100.into()).is_ok());
 
         let alice_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -208,8 +1798,8 @@ mod tests {
 
         let bob_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -218,9 +1808,9 @@ mod tests {
         assert_eq!(
             bob_receipt.matches,
             vec![PartialOrder {
-                price: 10,
-                amount: 2,
-                remaining: 0,
+                price: Price::from(10),
+                amount: Quantity::from(2),
+                remaining: Quantity::from(0),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
                 ordinal: 1
@@ -228,12 +1818,18 @@ mod tests {
         );
 
         // A fully matched order doesn't remain in the book
-        assert!(trading_platform.matching_engine.asks.is_empty());
-        assert!(trading_platform.matching_engine.bids.is_empty());
+        assert!(trading_platform.matching_engine.levels(&Side::Sell) == 0);
+        assert!(trading_platform.matching_engine.levels(&Side::Buy) == 0);
 
         // Check the account balances
-        assert_eq!(trading_platform.accounts.balance_of("ALICE"), Ok(&120));
-        assert_eq!(trading_platform.accounts.balance_of("BOB"), Ok(&80));
+        assert_eq!(
+            trading_platform.accounts.balance_of("ALICE"),
+            Ok(&Notional::from(120))
+        );
+        assert_eq!(
+            trading_platform.accounts.balance_of("BOB"),
+            Ok(&Notional::from(80))
+        );
     }
 
     #[test]
@@ -241,14 +1837,20 @@ mod tests {
         let mut trading_platform = TradingPlatform::new();
 
         // Set up accounts
-        assert!(trading_platform.accounts.deposit("ALICE", 100).is_ok());
-        assert!(trading_platform.accounts.deposit("BOB", 100).is_ok());
-        assert!(trading_platform.accounts.deposit("CHARLIE", 100).is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("ALICE", 100.into())
+            .is_ok());
+        assert!(trading_platform.accounts.deposit("BOB", 100.into()).is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("CHARLIE", 100.into())
+            .is_ok());
 
         let alice_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -258,8 +1860,8 @@ mod tests {
 
         let charlie_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
             })
@@ -269,8 +1871,8 @@ mod tests {
 
         let bob_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "BOB".to_string(),
             })
@@ -280,17 +1882,17 @@ mod tests {
             bob_receipt.matches,
             vec![
                 PartialOrder {
-                    price: 10,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(10),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Sell,
                     signer: "ALICE".to_string(),
                     ordinal: 1
                 },
                 PartialOrder {
-                    price: 10,
-                    amount: 1,
-                    remaining: 0,
+                    price: Price::from(10),
+                    amount: Quantity::from(1),
+                    remaining: Quantity::from(0),
                     side: Side::Sell,
                     signer: "CHARLIE".to_string(),
                     ordinal: 2
@@ -298,13 +1900,22 @@ mod tests {
             ]
         );
         // A fully matched order doesn't remain in the book
-        assert!(trading_platform.matching_engine.asks.is_empty());
-        assert!(trading_platform.matching_engine.bids.is_empty());
+        assert!(trading_platform.matching_engine.levels(&Side::Sell) == 0);
+        assert!(trading_platform.matching_engine.levels(&Side::Buy) == 0);
 
         // Check account balances
-        assert_eq!(trading_platform.accounts.balance_of("ALICE"), Ok(&110));
-        assert_eq!(trading_platform.accounts.balance_of("BOB"), Ok(&80));
-        assert_eq!(trading_platform.accounts.balance_of("CHARLIE"), Ok(&110));
+        assert_eq!(
+            trading_platform.accounts.balance_of("ALICE"),
+            Ok(&Notional::from(110))
+        );
+        assert_eq!(
+            trading_platform.accounts.balance_of("BOB"),
+            Ok(&Notional::from(80))
+        );
+        assert_eq!(
+            trading_platform.accounts.balance_of("CHARLIE"),
+            Ok(&Notional::from(110))
+        );
     }
 
     #[test]
@@ -312,13 +1923,19 @@ mod tests {
         let mut trading_platform = TradingPlatform::new();
 
         // Set up accounts
-        assert!(trading_platform.accounts.deposit("ALICE", 100).is_ok());
-        assert!(trading_platform.accounts.deposit("CHARLIE", 100).is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("ALICE", 100.into())
+            .is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("CHARLIE", 100.into())
+            .is_ok());
 
         let alice_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -328,8 +1945,8 @@ mod tests {
 
         let charlie_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 1,
+                price: Price::from(10),
+                amount: Quantity::from(1),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
             })
@@ -339,8 +1956,8 @@ mod tests {
 
         let bob_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Buy,
                 signer: "ALICE".to_string(),
             })
@@ -349,20 +1966,26 @@ mod tests {
         assert_eq!(
             bob_receipt.matches,
             vec![PartialOrder {
-                price: 10,
-                amount: 1,
-                remaining: 0,
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                remaining: Quantity::from(0),
                 side: Side::Sell,
                 signer: "CHARLIE".to_string(),
                 ordinal: 2
             }]
         );
         // A fully matched order doesn't remain in the book
-        assert_eq!(trading_platform.matching_engine.asks.len(), 1);
-        assert_eq!(trading_platform.matching_engine.bids.len(), 1);
+        assert_eq!(trading_platform.matching_engine.levels(&Side::Sell), 1);
+        assert_eq!(trading_platform.matching_engine.levels(&Side::Buy), 1);
         // Check account balances
-        assert_eq!(trading_platform.accounts.balance_of("ALICE"), Ok(&90));
-        assert_eq!(trading_platform.accounts.balance_of("CHARLIE"), Ok(&110));
+        assert_eq!(
+            trading_platform.accounts.balance_of("ALICE"),
+            Ok(&Notional::from(90))
+        );
+        assert_eq!(
+            trading_platform.accounts.balance_of("CHARLIE"),
+            Ok(&Notional::from(110))
+        );
     }
 
     #[test]
@@ -370,13 +1993,16 @@ mod tests {
         let mut trading_platform = TradingPlatform::new();
 
         // Set up accounts
-        assert!(trading_platform.accounts.deposit("ALICE", 100).is_ok());
-        assert!(trading_platform.accounts.deposit("BOB", 100).is_ok());
+        assert!(trading_platform
+            .accounts
+            .deposit("ALICE", 100.into())
+            .is_ok());
+        assert!(trading_platform.accounts.deposit("BOB", 100.into()).is_ok());
 
         let alice_receipt = trading_platform
             .order(Order {
-                price: 10,
-                amount: 2,
+                price: Price::from(10),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "ALICE".to_string(),
             })
@@ -386,8 +2012,8 @@ mod tests {
 
         let bob_receipt = trading_platform
             .order(Order {
-                price: 11,
-                amount: 2,
+                price: Price::from(11),
+                amount: Quantity::from(2),
                 side: Side::Sell,
                 signer: "BOB".to_string(),
             })
@@ -397,7 +2023,745 @@ mod tests {
         assert_eq!(trading_platform.orderbook().len(), 2);
 
         // Check the account balances
-        assert_eq!(trading_platform.accounts.balance_of("ALICE"), Ok(&100));
-        assert_eq!(trading_platform.accounts.balance_of("BOB"), Ok(&100));
+        assert_eq!(
+            trading_platform.accounts.balance_of("ALICE"),
+            Ok(&Notional::from(100))
+        );
+        assert_eq!(
+            trading_platform.accounts.balance_of("BOB"),
+            Ok(&Notional::from(100))
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_save_and_load_restores_state() {
+        let mut trading_platform = TradingPlatform::new();
+
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 100.into()).is_ok());
+        trading_platform
+            .order(Order {
+                price: Price::from(10),
+                amount: Quantity::from(2),
+                side: Side::Sell,
+                signer: "ALICE".to_string(),
+            })
+            .unwrap();
+        trading_platform
+            .order(Order {
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                side: Side::Buy,
+                signer: "BOB".to_string(),
+            })
+            .unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("octopus-snapshot-{}.json", std::process::id()));
+        trading_platform.save(&path).unwrap();
+        let restored = TradingPlatform::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.orderbook(), trading_platform.orderbook());
+        assert_eq!(restored.txlog(), trading_platform.txlog());
+        assert_eq!(restored.balance_of("ALICE"), Ok(&Notional::from(110)));
+        assert_eq!(restored.balance_of("BOB"), Ok(&Notional::from(90)));
+        assert_eq!(restored.matching_engine.ordinal(), 2);
+        assert_eq!(
+            restored.query_trades(&TradesQuery::default()),
+            trading_platform.query_trades(&TradesQuery::default())
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_cancel_takes_order_off_the_book() {
+        let mut trading_platform = TradingPlatform::new();
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+
+        let receipt = trading_platform
+            .order(Order {
+                price: Price::from(10),
+                amount: Quantity::from(1),
+                side: Side::Sell,
+                signer: "ALICE".to_string(),
+            })
+            .unwrap();
+
+        let cancelled = trading_platform.cancel(receipt.ordinal).unwrap();
+        assert_eq!(cancelled.signer, "ALICE");
+        assert!(trading_platform.orderbook().is_empty());
+        assert_eq!(
+            trading_platform.cancel(receipt.ordinal),
+            Err(ApplicationError::OrderNotFound(receipt.ordinal))
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_disable_stops_orders_until_enabled() {
+        let mut trading_platform = TradingPlatform::new();
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        let order = |side| Order {
+            price: Price::from(10),
+            amount: Quantity::from(1),
+            side,
+            signer: "ALICE".to_string(),
+        };
+        trading_platform.order(order(Side::Sell)).unwrap();
+        trading_platform.order(order(Side::Sell)).unwrap();
+
+        let status = trading_platform.disable("ALICE").unwrap();
+        assert!(status.disabled);
+        assert_eq!(status.cancelled.len(), 2);
+        assert!(trading_platform.orderbook().is_empty());
+        assert_eq!(
+            trading_platform.order(order(Side::Buy)),
+            Err(ApplicationError::AccountDisabled("ALICE".to_string()))
+        );
+        // Deposits and withdrawals aren't affected
+        assert!(trading_platform.withdraw("ALICE", 1.into()).is_ok());
+
+        // Disabled accounts stay disabled after a restart
+        let path = std::env::temp_dir().join(format!(
+            "octopus-snapshot-disabled-{}.json",
+            std::process::id()
+        ));
+        trading_platform.save(&path).unwrap();
+        let mut trading_platform = TradingPlatform::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(trading_platform.status("ALICE").disabled);
+        assert!(trading_platform
+            .disable("ALICE")
+            .unwrap()
+            .cancelled
+            .is_empty());
+
+        assert!(!trading_platform.enable("ALICE").unwrap().disabled);
+        assert!(trading_platform.order(order(Side::Sell)).is_ok());
+        assert_eq!(
+            trading_platform.disable("BOB"),
+            Err(ApplicationError::AccountNotFound("BOB".to_string()))
+        );
+        assert_eq!(
+            trading_platform
+                .query_audit(&AuditQuery::default())
+                .items
+                .iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_respects_the_trading_states() {
+        let mut trading_platform = TradingPlatform::new();
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        let order = || Order {
+            price: Price::from(10),
+            amount: Quantity::from(1),
+            side: Side::Sell,
+            signer: "ALICE".to_string(),
+        };
+        let state = |state| TradingStateRequest {
+            state,
+            reason: Some("maintenance".to_string()),
+        };
+        trading_platform.order(order()).unwrap();
+
+        let status = trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::Halted))
+            .unwrap();
+        assert_eq!(status.instruments[0].state, TradingState::Halted);
+        assert_eq!(
+            trading_platform.order(order()),
+            Err(ApplicationError::TradingHalted("OCTO".to_string()))
+        );
+        // The book stays readable and orders can be cancelled
+        assert_eq!(trading_platform.orderbook().len(), 1);
+        assert!(trading_platform.cancel(1).is_ok());
+
+        // The more restrictive state applies
+        trading_platform
+            .set_trading_state(None, state(TradingState::Closed))
+            .unwrap();
+        assert_eq!(
+            trading_platform.order(order()),
+            Err(ApplicationError::TradingClosed("OCTO".to_string()))
+        );
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::Open))
+            .unwrap();
+        assert!(trading_platform.order(order()).is_err());
+        trading_platform
+            .set_trading_state(None, state(TradingState::Open))
+            .unwrap();
+        assert!(trading_platform.order(order()).is_ok());
+
+        assert_eq!(
+            trading_platform.set_trading_state(Some("XYZ"), state(TradingState::Halted)),
+            Err(ApplicationError::InstrumentNotFound("XYZ".to_string()))
+        );
+        assert_eq!(
+            trading_platform
+                .query_audit(&AuditQuery::default())
+                .items
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_queues_while_halted() {
+        let mut trading_platform =
+            TradingPlatform::new().with_halts(CircuitBreaker::default(), HaltPolicy::Queue);
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 100.into()).is_ok());
+        let order = |signer: &str, side, amount: u64| Order {
+            price: Price::from(10),
+            amount: Quantity::from(amount),
+            side,
+            signer: signer.to_string(),
+        };
+        trading_platform
+            .set_trading_state(
+                Some("OCTO"),
+                TradingStateRequest {
+                    state: TradingState::Halted,
+                    reason: None,
+                },
+            )
+            .unwrap();
+
+        let receipt = trading_platform
+            .order(order("ALICE", Side::Sell, 2))
+            .unwrap();
+        assert!(receipt.queued);
+        assert_eq!(receipt.ordinal, 1);
+        trading_platform.order(order("BOB", Side::Buy, 1)).unwrap();
+        trading_platform
+            .order(order("ALICE", Side::Buy, 5))
+            .unwrap();
+        // Orders that fail the checks now aren't queued
+        assert_eq!(
+            trading_platform.order(order("CHARLIE", Side::Buy, 1)),
+            Err(ApplicationError::AccountNotFound("CHARLIE".to_string()))
+        );
+        assert_eq!(
+            trading_platform.order(order("BOB", Side::Buy, 20)),
+            Err(ApplicationError::AccountUnderFunded(
+                "BOB".to_string(),
+                200.into()
+            ))
+        );
+        // Queued orders can be cancelled like resting ones
+        let receipt = trading_platform.order(order("BOB", Side::Sell, 1)).unwrap();
+        assert_eq!(trading_platform.cancel(receipt.ordinal).unwrap().ordinal, 4);
+        assert!(trading_platform.orderbook().is_empty());
+        let status = trading_platform.market_status();
+        assert_eq!(status.instruments[0].queued, 3);
+        assert_eq!(
+            status.instruments[0]
+                .queued_orders
+                .iter()
+                .map(|order| (order.ordinal, order.signer.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "ALICE"), (2, "BOB"), (3, "ALICE")]
+        );
+        // Queued bids don't hold on to funds, so they may not be covered anymore once trading opens
+        assert!(trading_platform.withdraw("ALICE", 100.into()).is_ok());
+
+        let status = trading_platform
+            .set_trading_state(
+                Some("OCTO"),
+                TradingStateRequest {
+                    state: TradingState::Open,
+                    reason: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(status.instruments[0].queued, 0);
+        // In the order they arrived: ALICE's order rests, BOB's is matched against it
+        let book = trading_platform.orderbook();
+        assert_eq!(book.len(), 1);
+        assert_eq!((book[0].ordinal, book[0].remaining), (1, Quantity::from(1)));
+        assert_eq!(trading_platform.balance_of("BOB"), Ok(&Notional::from(90)));
+        let released: Vec<_> = trading_platform
+            .audit
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                AuditEvent::QueuedOrderPlaced { ordinal, .. } => Some((*ordinal, true)),
+                AuditEvent::QueuedOrderRejected { ordinal, .. } => Some((*ordinal, false)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(released, vec![(1, true), (2, true), (3, false)]);
+    }
+
+    #[test]
+    fn test_TradingPlatform_auction_uncrosses_when_the_call_phase_ends() {
+        let mut trading_platform = TradingPlatform::new();
+        assert!(trading_platform.deposit("ALICE", 1_000.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 1_000.into()).is_ok());
+        let order = |signer: &str, side, price: u64, amount: u64| Order {
+            price: Price::from(price),
+            amount: Quantity::from(amount),
+            side,
+            signer: signer.to_string(),
+        };
+        let state = |state| TradingStateRequest {
+            state,
+            reason: None,
+        };
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::AuctionOnly))
+            .unwrap();
+
+        // Crossing orders rest without matching
+        for (signer, side, price, amount) in [
+            ("ALICE", Side::Buy, 12, 5),
+            ("BOB", Side::Sell, 10, 3),
+            ("BOB", Side::Sell, 11, 4),
+        ] {
+            let receipt = trading_platform
+                .order(order(signer, side, price, amount))
+                .unwrap();
+            assert!(!receipt.queued);
+            assert!(receipt.matches.is_empty());
+        }
+        // The pre-trade checks still apply
+        assert_eq!(
+            trading_platform.order(order("CHARLIE", Side::Buy, 12, 1)),
+            Err(ApplicationError::AccountNotFound("CHARLIE".to_string()))
+        );
+        assert_eq!(trading_platform.orderbook().len(), 3);
+        // 5 units at 11 and 12, and 11 leaves the sellers' surplus at the lowest price
+        assert_eq!(
+            trading_platform.auction_status(),
+            AuctionStatus {
+                symbol: "OCTO".to_string(),
+                state: TradingState::AuctionOnly,
+                price: Some(Price::from(11)),
+                volume: Quantity::from(5),
+                surplus: Quantity::from(2),
+                surplus_side: Some(Side::Sell),
+            }
+        );
+
+        // The opening auction executes at one price and leaves the rest for continuous trading
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::Open))
+            .unwrap();
+        assert_eq!(
+            trading_platform.balance_of("ALICE"),
+            Ok(&Notional::from(945))
+        );
+        assert_eq!(
+            trading_platform.balance_of("BOB"),
+            Ok(&Notional::from(1_055))
+        );
+        let book = trading_platform.orderbook();
+        assert_eq!(book.len(), 1);
+        assert_eq!(
+            (book[0].price, book[0].remaining),
+            (Price::from(11), Quantity::from(2))
+        );
+        let auction = trading_platform.auction_status();
+        assert_eq!((auction.state, auction.price), (TradingState::Open, None));
+
+        // The closing auction uncrosses when trading closes
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::AuctionOnly))
+            .unwrap();
+        trading_platform
+            .order(order("ALICE", Side::Buy, 12, 2))
+            .unwrap();
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::Closed))
+            .unwrap();
+        assert_eq!(
+            trading_platform.balance_of("ALICE"),
+            Ok(&Notional::from(923))
+        );
+        assert!(trading_platform.orderbook().is_empty());
+    }
+
+    #[test]
+    fn test_TradingPlatform_auction_settles_only_funded_bids() {
+        let mut trading_platform = TradingPlatform::new();
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 100.into()).is_ok());
+        let order = |signer: &str, side| Order {
+            price: Price::from(10),
+            amount: Quantity::from(10),
+            side,
+            signer: signer.to_string(),
+        };
+        let state = |state| TradingStateRequest {
+            state,
+            reason: None,
+        };
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::AuctionOnly))
+            .unwrap();
+        trading_platform.order(order("ALICE", Side::Buy)).unwrap();
+        trading_platform.order(order("BOB", Side::Sell)).unwrap();
+
+        // A bid in the call phase holds on to its notional
+        assert_eq!(
+            trading_platform.withdraw("ALICE", 100.into()),
+            Err(ApplicationError::AccountUnderFunded(
+                "ALICE".to_string(),
+                100.into()
+            ))
+        );
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::Open))
+            .unwrap();
+        assert_eq!(trading_platform.balance_of("ALICE"), Ok(&Notional::ZERO));
+        assert_eq!(trading_platform.balance_of("BOB"), Ok(&Notional::from(200)));
+
+        // A bid its balance doesn't cover is cancelled instead of crossing
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::AuctionOnly))
+            .unwrap();
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        trading_platform.order(order("ALICE", Side::Buy)).unwrap();
+        trading_platform.order(order("BOB", Side::Sell)).unwrap();
+        trading_platform
+            .accounts
+            .withdraw("ALICE", 100.into())
+            .unwrap();
+        trading_platform
+            .set_trading_state(Some("OCTO"), state(TradingState::Open))
+            .unwrap();
+        assert_eq!(trading_platform.balance_of("BOB"), Ok(&Notional::from(200)));
+        let book = trading_platform.orderbook();
+        assert_eq!(book.len(), 1);
+        assert_eq!(book[0].side, Side::Sell);
+        assert!(trading_platform.audit.iter().any(|entry| matches!(
+            &entry.event,
+            AuditEvent::OrdersCancelled { account, side: Some(Side::Buy), cancelled }
+                if account == "ALICE" && cancelled.len() == 1
+        )));
+    }
+
+    #[test]
+    fn test_TradingPlatform_order_halts_on_large_price_moves() {
+        // Moves of more than 10% within a minute halt trading until it is resumed
+        let mut trading_platform = TradingPlatform::new()
+            .with_halts(CircuitBreaker::new(1_000, 60_000, None), HaltPolicy::Reject);
+        assert!(trading_platform.deposit("ALICE", 1_000.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 1_000.into()).is_ok());
+        let order = |signer: &str, side, price: u64| Order {
+            price: Price::from(price),
+            amount: Quantity::from(1),
+            side,
+            signer: signer.to_string(),
+        };
+        for price in [100, 110] {
+            trading_platform
+                .order(order("ALICE", Side::Sell, price))
+                .unwrap();
+            trading_platform
+                .order(order("BOB", Side::Buy, price))
+                .unwrap();
+        }
+        assert_eq!(
+            trading_platform.market_status().instruments[0].state,
+            TradingState::Open
+        );
+
+        // An order sweeping the book matches up to 110, the most it may move from 100
+        for price in [105, 110, 115] {
+            trading_platform
+                .order(order("ALICE", Side::Sell, price))
+                .unwrap();
+        }
+        let receipt = trading_platform
+            .order(Order {
+                amount: Quantity::from(3),
+                ..order("BOB", Side::Buy, 115)
+            })
+            .unwrap();
+        assert_eq!(
+            receipt.matches.iter().map(|m| m.price).collect::<Vec<_>>(),
+            vec![Price::from(105), Price::from(110)]
+        );
+        let status = trading_platform.market_status();
+        assert_eq!(status.instruments[0].state, TradingState::Halted);
+        assert_eq!(
+            status.instruments[0].status.reason.as_deref(),
+            Some("the price moved from 100.00 to 115.00")
+        );
+        assert_eq!(status.instruments[0].status.until, None);
+        assert_eq!(
+            trading_platform.order(order("BOB", Side::Buy, 115)),
+            Err(ApplicationError::TradingHalted("OCTO".to_string()))
+        );
+        // The rest of the order waits in the crossed book and trades once trading resumes
+        trading_platform
+            .set_trading_state(
+                Some("OCTO"),
+                TradingStateRequest {
+                    state: TradingState::Open,
+                    reason: None,
+                },
+            )
+            .unwrap();
+        assert!(trading_platform.orderbook().is_empty());
+        assert_eq!(
+            trading_platform.balance_of("ALICE"),
+            Ok(&Notional::from(1_000 + 100 + 110 + 105 + 110 + 115))
+        );
+
+        // Halts that end by themselves are over at the next order
+        let mut trading_platform = TradingPlatform::new().with_halts(
+            CircuitBreaker::new(1_000, 60_000, Some(0)),
+            HaltPolicy::Reject,
+        );
+        assert!(trading_platform.deposit("ALICE", 1_000.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 1_000.into()).is_ok());
+        for price in [100, 120] {
+            trading_platform
+                .order(order("ALICE", Side::Sell, price))
+                .unwrap();
+            trading_platform
+                .order(order("BOB", Side::Buy, price))
+                .unwrap();
+        }
+        assert!(trading_platform
+            .order(order("ALICE", Side::Sell, 120))
+            .is_ok());
+        let states: Vec<_> = trading_platform
+            .query_audit(&AuditQuery::default())
+            .items
+            .into_iter()
+            .map(|entry| match entry.event {
+                AuditEvent::TradingStateChanged { state, .. } => state,
+                event => panic!("unexpected {:?}", event),
+            })
+            .collect();
+        assert_eq!(states, vec![TradingState::Halted, TradingState::Open]);
+    }
+
+    #[test]
+    fn test_TradingPlatform_query_trades_lists_newest_first() {
+        let mut trading_platform = TradingPlatform::new();
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 100.into()).is_ok());
+
+        for (price, amount, side, signer) in [
+            (10, 1, Side::Sell, "ALICE"),
+            (11, 1, Side::Sell, "ALICE"),
+            (11, 2, Side::Buy, "BOB"),
+            (9, 1, Side::Buy, "BOB"),
+            (9, 1, Side::Sell, "ALICE"),
+        ] {
+            trading_platform
+                .order(Order {
+                    price: Price::from(price),
+                    amount: Quantity::from(amount),
+                    side,
+                    signer: signer.to_string(),
+                })
+                .unwrap();
+        }
+
+        let page = trading_platform.query_trades(&TradesQuery {
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(
+            page.items,
+            vec![
+                Trade {
+                    id: 3,
+                    ordinal: 5,
+                    maker_ordinal: 4,
+                    price: Price::from(9),
+                    amount: Quantity::from(1),
+                    side: Side::Sell,
+                },
+                Trade {
+                    id: 2,
+                    ordinal: 3,
+                    maker_ordinal: 2,
+                    price: Price::from(11),
+                    amount: Quantity::from(1),
+                    side: Side::Buy,
+                },
+            ]
+        );
+        assert_eq!(page.next, Some(2));
+
+        let page = trading_platform.query_trades(&TradesQuery {
+            before: page.next,
+            ..Default::default()
+        });
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|t| (t.id, t.price))
+                .collect::<Vec<_>>(),
+            vec![(1, Price::from(10))]
+        );
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn test_TradingPlatform_query_txlog_filters_and_paginates() {
+        let mut trading_platform = TradingPlatform::new();
+
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 100.into()).is_ok());
+        assert!(trading_platform.withdraw("ALICE", 10.into()).is_ok());
+        assert!(trading_platform.deposit("ALICE", 5.into()).is_ok());
+        assert!(trading_platform.send("BOB", "ALICE", 1.into()).is_ok());
+
+        let query = TxLogQuery {
+            limit: Some(2),
+            account: Some("ALICE".to_string()),
+            ..Default::default()
+        };
+        let page = trading_platform.query_txlog(&query);
+        assert_eq!(
+            page.items.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(page.next, Some(3));
+
+        let page = trading_platform.query_txlog(&TxLogQuery {
+            after: page.next,
+            ..query.clone()
+        });
+        assert_eq!(
+            page.items.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![4, 6]
+        );
+        assert_eq!(page.next, None);
+
+        let page = trading_platform.query_txlog(&TxLogQuery {
+            kind: Some(TxKind::Withdraw),
+            ..Default::default()
+        });
+        assert_eq!(
+            page.items.into_iter().map(|e| e.tx).collect::<Vec<_>>(),
+            vec![
+                Tx::Withdraw {
+                    account: "ALICE".to_string(),
+                    amount: 10.into()
+                },
+                Tx::Withdraw {
+                    account: "BOB".to_string(),
+                    amount: 1.into()
+                }
+            ]
+        );
+
+        let now = trading_platform.transactions[0].timestamp;
+        let page = trading_platform.query_txlog(&TxLogQuery {
+            to: Some(now),
+            ..Default::default()
+        });
+        assert!(page.items.is_empty());
+        let page = trading_platform.query_txlog(&TxLogQuery {
+            from: Some(now),
+            after: Some(100),
+            ..Default::default()
+        });
+        assert!(page.items.is_empty());
+    }
+
+    #[test]
+    fn test_TradingPlatform_query_accounts_filters_and_paginates() {
+        let mut trading_platform = TradingPlatform::new();
+
+        for (signer, amount) in [("bot-2", 20), ("ALICE", 100), ("bot-1", 5), ("bot-3", 30)] {
+            assert!(trading_platform.deposit(signer, amount.into()).is_ok());
+        }
+
+        let query = AccountsQuery {
+            limit: Some(1),
+            prefix: Some("bot-".to_string()),
+            min_balance: Some(10.into()),
+            ..Default::default()
+        };
+        let page = trading_platform.query_accounts(&query);
+        assert_eq!(
+            page.items,
+            vec![AccountBalance {
+                signer: "bot-2".to_string(),
+                balance: 20.into()
+            }]
+        );
+        assert_eq!(page.next, Some("bot-2".to_string()));
+
+        let page = trading_platform.query_accounts(&AccountsQuery {
+            after: page.next,
+            ..query
+        });
+        assert_eq!(
+            page.items,
+            vec![AccountBalance {
+                signer: "bot-3".to_string(),
+                balance: 30.into()
+            }]
+        );
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn test_TradingPlatform_query_orderbook_filters_and_paginates() {
+        let mut trading_platform = TradingPlatform::new();
+
+        assert!(trading_platform.deposit("ALICE", 100.into()).is_ok());
+        assert!(trading_platform.deposit("BOB", 100.into()).is_ok());
+        for (price, side, signer) in [
+            (10, Side::Sell, "ALICE"),
+            (5, Side::Buy, "BOB"),
+            (11, Side::Sell, "BOB"),
+            (12, Side::Sell, "ALICE"),
+        ] {
+            trading_platform
+                .order(Order {
+                    price: Price::from(price),
+                    amount: Quantity::from(1),
+                    side,
+                    signer: signer.to_string(),
+                })
+                .unwrap();
+        }
+
+        let query = OrderbookQuery {
+            limit: Some(1),
+            side: Some(Side::Sell),
+            ..Default::default()
+        };
+        let page = trading_platform.query_orderbook(&query);
+        assert_eq!(
+            page.items.iter().map(|o| o.ordinal).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(page.next, Some(1));
+
+        let page = trading_platform.query_orderbook(&OrderbookQuery {
+            limit: Some(10),
+            after: page.next,
+            ..query
+        });
+        assert_eq!(
+            page.items.iter().map(|o| o.ordinal).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert_eq!(page.next, None);
+
+        let page = trading_platform.query_orderbook(&OrderbookQuery {
+            signer: Some("BOB".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            page.items.iter().map(|o| o.ordinal).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
     }
 }
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashSet,
+    net::TcpListener,
+    path::PathBuf,
+    process::{Child, Command},
+    time::Duration,
+};
+
+use octopus_common::core::types::{AccountUpdateRequest, Order, Price, Quantity, Receipt, Side};
+
+const ACCOUNTS: [&str; 4] = ["ALICE", "BOB", "CHARLIE", "DAVE"];
+const ORDERS: usize = 400;
+
+/// Starts the server binary on a free port, persisting its state to `snapshot`
+fn spawn_server(snapshot: &PathBuf) -> (Child, String) {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addr = format!("127.0.0.1:{}", port);
+    let child = Command::new(env!("CARGO_BIN_EXE_octopus-web"))
+        .env("OCTOPUS_ADDR", &addr)
+        .env("OCTOPUS_SNAPSHOT", snapshot)
+        .spawn()
+        .expect("failed to start octopus-web");
+    (child, format!("http://{}", addr))
+}
+
+async fn wait_until_up(client: &reqwest::Client, base_url: &str) {
+    for _ in 0..100 {
+        if client
+            .get(format!("{}/accounts", base_url))
+            .send()
+            .await
+            .is_ok()
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("octopus-web didn't come up");
+}
+
+fn terminate(child: &Child) {
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test]
+async fn test_sigterm_drains_requests_and_keeps_acknowledged_orders() {
+    let snapshot = std::env::temp_dir().join(format!(
+        "octopus-graceful-shutdown-{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&snapshot);
+
+    let (mut child, base_url) = spawn_server(&snapshot);
+    let client = reqwest::Client::new();
+    wait_until_up(&client, &base_url).await;
+
+    for account in ACCOUNTS {
+        let response = client
+            .post(format!("{}/account/deposit", base_url))
+            .json(&AccountUpdateRequest {
+                signer: account.to_string(),
+                amount: 1_000_000.into(),
+            })
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+    }
+
+    let orders = (0..ORDERS).map(|i| {
+        let client = client.clone();
+        let url = format!("{}/order", base_url);
+        tokio::spawn(async move {
+            let order = Order {
+                price: Price::from(10 + (i % 5) as u64),
+                amount: Quantity::from(1 + (i % 3) as u64),
+                side: if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                signer: ACCOUNTS[i % ACCOUNTS.len()].to_string(),
+            };
+            match client.post(url).json(&order).send().await {
+                Ok(response) if response.status().is_success() => {
+                    response.json::<Receipt>().await.ok()
+                }
+                _ => None,
+            }
+        })
+    });
+    let orders: Vec<_> = orders.collect();
+
+    // Signal while the orders above are still being sent
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    terminate(&child);
+
+    let mut acknowledged = HashSet::new();
+    for order in orders {
+        if let Some(receipt) = order.await.unwrap() {
+            acknowledged.insert(receipt.ordinal);
+        }
+    }
+
+    let status = tokio::task::spawn_blocking(move || child.wait())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(status.success(), "server exited with {}", status);
+    assert!(!acknowledged.is_empty(), "no order was acknowledged");
+
+    let state: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&snapshot).expect("no snapshot written")).unwrap();
+    std::fs::remove_file(&snapshot).unwrap();
+
+    // An acknowledged order traded as taker or maker, so it's in the history, or it rests in the book
+    let engine = &state["matching_engine"];
+    let traded = engine["history"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|receipt| {
+            std::iter::once(receipt).chain(receipt["matches"].as_array().unwrap().iter())
+        });
+    let resting = ["bids", "asks"].into_iter().flat_map(|side| {
+        engine[side]
+            .as_object()
+            .unwrap()
+            .values()
+            .flat_map(|level| level.as_array().unwrap().iter())
+    });
+    let persisted: HashSet<u64> = traded
+        .chain(resting)
+        .map(|entry| entry["ordinal"].as_u64().unwrap())
+        .collect();
+    let lost: Vec<_> = acknowledged.difference(&persisted).collect();
+    assert!(lost.is_empty(), "acknowledged orders lost: {:?}", lost);
+}
@@ -0,0 +1,230 @@
+//! Property tests: random sequences of operations on a [`TradingPlatform`] keep its invariants after every step.
+
+// The operations are deposits, withdrawals, transfers, orders and cancels. A failing sequence is shrunk to the
+// shortest one that still fails.
+
+// reduce the warnings for naming tests
+#![allow(non_snake_case)]
+
+use octopus_common::core::{
+    decimal::Decimal,
+    types::{Instrument, Notional, Order, PartialOrder, Price, Quantity, Receipt, Side},
+};
+use octopus_web::trading_platform::TradingPlatform;
+use proptest::prelude::*;
+
+/// Few accounts, so that their orders meet often
+const ACCOUNTS: [&str; 4] = ["ALICE", "BOB", "CHARLIE", "DAVE"];
+/// Cents and tenths of a unit make notionals with three decimal places, which are rounded down to the two of balances
+const PRICE_SCALE: u8 = 2;
+const QUANTITY_SCALE: u8 = 1;
+const CURRENCY_SCALE: u8 = 2;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Deposit {
+        account: usize,
+        amount: u64,
+    },
+    Withdraw {
+        account: usize,
+        amount: u64,
+    },
+    Send {
+        sender: usize,
+        recipient: usize,
+        amount: u64,
+    },
+    Order {
+        account: usize,
+        side: Side,
+        price: u64,
+        amount: u64,
+    },
+    Cancel {
+        ordinal: u64,
+    },
+}
+
+fn account() -> impl Strategy<Value = usize> {
+    0..ACCOUNTS.len()
+}
+
+fn side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::Buy), Just(Side::Sell)]
+}
+
+/// Mostly prices that orders can afford and meet at, and now and then one whose notional overflows
+fn price() -> impl Strategy<Value = u64> {
+    prop_oneof![9 => 1..=2_000u64, 1 => Just(u64::MAX)]
+}
+
+// Amounts of currency, prices and quantities are given in units of their last decimal place
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        2 => (account(), 0..1_000_000u64).prop_map(|(account, amount)| Op::Deposit { account, amount }),
+        1 => (account(), 0..200_000u64).prop_map(|(account, amount)| Op::Withdraw { account, amount }),
+        1 => (account(), account(), 0..200_000u64).prop_map(|(sender, recipient, amount)| Op::Send {
+            sender,
+            recipient,
+            amount,
+        }),
+        6 => (account(), side(), price(), 1..=100u64).prop_map(|(account, side, price, amount)| {
+            Op::Order {
+                account,
+                side,
+                price,
+                amount,
+            }
+        }),
+        1 => (1..=100u64).prop_map(|ordinal| Op::Cancel { ordinal }),
+    ]
+}
+
+fn currency(units: u64) -> Notional {
+    Notional(Decimal::new(units, CURRENCY_SCALE))
+}
+
+/// What the platform's state must agree with, tracked next to it, in units of the currency
+#[derive(Default)]
+struct Model {
+    deposited: u128,
+    withdrawn: u128,
+    last_ordinal: u64,
+}
+
+fn total_balance(platform: &TradingPlatform) -> u128 {
+    platform
+        .accounts()
+        .iter()
+        .map(|(_, balance)| {
+            let units = balance
+                .0
+                .rescale(CURRENCY_SCALE)
+                .expect("balances have two decimal places");
+            units.units() as u128
+        })
+        .sum()
+}
+
+/// Applies `op`, updating the model with what succeeded
+fn apply(platform: &mut TradingPlatform, model: &mut Model, op: &Op) -> Result<(), TestCaseError> {
+    match *op {
+        Op::Deposit { account, amount } => {
+            if platform
+                .deposit(ACCOUNTS[account], currency(amount))
+                .is_ok()
+            {
+                model.deposited += amount as u128;
+            }
+        }
+        Op::Withdraw { account, amount } => {
+            if platform
+                .withdraw(ACCOUNTS[account], currency(amount))
+                .is_ok()
+            {
+                model.withdrawn += amount as u128;
+            }
+        }
+        Op::Send {
+            sender,
+            recipient,
+            amount,
+        } => {
+            let _ = platform.send(ACCOUNTS[sender], ACCOUNTS[recipient], currency(amount));
+        }
+        Op::Order {
+            account,
+            ref side,
+            price,
+            amount,
+        } => {
+            let order = Order {
+                price: Price(Decimal::new(price, PRICE_SCALE)),
+                amount: Quantity(Decimal::new(amount, QUANTITY_SCALE)),
+                side: side.clone(),
+                signer: ACCOUNTS[account].to_string(),
+            };
+            if let Ok(receipt) = platform.order(order.clone()) {
+                check_receipt(model, &order, &receipt)?;
+            }
+        }
+        Op::Cancel { ordinal } => {
+            let _ = platform.cancel(ordinal);
+        }
+    }
+    Ok(())
+}
+
+fn check_receipt(model: &mut Model, order: &Order, receipt: &Receipt) -> Result<(), TestCaseError> {
+    prop_assert!(
+        receipt.ordinal > model.last_ordinal,
+        "ordinal {} after {}",
+        receipt.ordinal,
+        model.last_ordinal
+    );
+    model.last_ordinal = receipt.ordinal;
+
+    for m in &receipt.matches {
+        match order.side {
+            Side::Buy => prop_assert!(m.price <= order.price, "bought above the limit: {:?}", m),
+            Side::Sell => prop_assert!(m.price >= order.price, "sold below the limit: {:?}", m),
+        }
+        prop_assert_ne!(&m.signer, &order.signer, "matched against itself");
+    }
+    let filled = receipt
+        .matches
+        .iter()
+        .fold(Quantity::ZERO, |filled, m| filled + m.amount);
+    prop_assert!(filled <= order.amount, "overfilled {:?}", receipt);
+    Ok(())
+}
+
+/// Bids and asks that overlap in price may only belong to the same signer, whose orders never match each other
+fn check_book(book: &[PartialOrder]) -> Result<(), TestCaseError> {
+    let (bids, asks): (Vec<_>, Vec<_>) = book.iter().partition(|order| order.side == Side::Buy);
+    for bid in &bids {
+        for ask in asks.iter().filter(|ask| ask.price <= bid.price) {
+            prop_assert_eq!(
+                &bid.signer,
+                &ask.signer,
+                "crossed book: bid {:?} and ask {:?}",
+                bid,
+                ask
+            );
+        }
+    }
+    for order in book {
+        prop_assert!(
+            !order.remaining.is_zero() && order.remaining <= order.amount,
+            "bad resting order {:?}",
+            order
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_TradingPlatform_keeps_invariants(ops in proptest::collection::vec(op(), 1..200)) {
+        let instrument = Instrument::new("OCTO", PRICE_SCALE, QUANTITY_SCALE);
+        // Fees move currency to the fee account, which counts towards the total like any other
+        let mut platform = TradingPlatform::new()
+            .with_instrument(instrument, CURRENCY_SCALE)
+            .with_fees("0:10:20,1000:5:10".parse().unwrap(), "FEES");
+        let mut model = Model::default();
+
+        for op in &ops {
+            apply(&mut platform, &mut model, op)?;
+
+            // Balances are unsigned, an overdraft would have panicked or wrapped around, which breaks conservation
+            prop_assert_eq!(
+                total_balance(&platform),
+                model.deposited - model.withdrawn,
+                "currency not conserved after {:?}",
+                op
+            );
+            check_book(&platform.orderbook())?;
+        }
+    }
+}